@@ -0,0 +1,212 @@
+//! What the tasks send each other
+
+use crate::feed::Screen;
+use crate::retry::{CircuitState, RetryPolicy};
+use crate::state::State;
+use desk_buddy_ui::date::DateTime;
+use desk_buddy_ui::display::{
+    BlueSkyNotificationData, CalendarEvent, FeedPost, InsideSensorData, MAX_EVENTS, MAX_FEED_POSTS,
+};
+use desk_buddy_ui::weather::{AirQualityResponse, ForecastResponse};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::Duration;
+use heapless::String;
+
+/// Longest text the message command takes, the most a Home Assistant text entity can send
+pub const MESSAGE_SIZE: usize = 255;
+
+/// Web requests for the network client to make
+pub type WebRequestChannel = Channel<CriticalSectionRawMutex, WebRequestEvents, 10>;
+/// Events for the orchestrator to react to and update the state
+pub type EventChannel = Channel<CriticalSectionRawMutex, GeneralEvents, 10>;
+/// Each new state for the clock and the display
+pub type StateChannel = Channel<CriticalSectionRawMutex, State, 1>;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+/// These are events that trigger web requests.
+pub enum WebRequestEvents {
+    UpdateForecast,
+    UpdateAirQuality,
+    UpdateOfficeStatus,
+    GetTime,
+    CheckBlueSkyNotifications,
+    ///From the button, see button_task
+    MarkBlueSkyNotificationsSeen,
+    ///Posts for the feed screen
+    UpdateFeed,
+    UpdateCalendar,
+}
+
+impl WebRequestEvents {
+    /// Every event in declaration order, so `ALL[event as usize] == event`. A new variant goes here too
+    pub const ALL: [WebRequestEvents; 8] = [
+        WebRequestEvents::UpdateForecast,
+        WebRequestEvents::UpdateAirQuality,
+        WebRequestEvents::UpdateOfficeStatus,
+        WebRequestEvents::GetTime,
+        WebRequestEvents::CheckBlueSkyNotifications,
+        WebRequestEvents::MarkBlueSkyNotificationsSeen,
+        WebRequestEvents::UpdateFeed,
+        WebRequestEvents::UpdateCalendar,
+    ];
+
+    /// How many there are, for arrays indexed by `event as usize`
+    pub const COUNT: usize = Self::ALL.len();
+
+    /// Short name for logs and the display
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebRequestEvents::UpdateForecast => "Forecast",
+            WebRequestEvents::UpdateAirQuality => "Air",
+            WebRequestEvents::UpdateOfficeStatus => "Office",
+            WebRequestEvents::GetTime => "Time",
+            WebRequestEvents::CheckBlueSkyNotifications => "Bluesky",
+            WebRequestEvents::MarkBlueSkyNotificationsSeen => "Bsky seen",
+            WebRequestEvents::UpdateFeed => "Feed",
+            WebRequestEvents::UpdateCalendar => "Calendar",
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match self {
+            WebRequestEvents::UpdateForecast => RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(20),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(15 * 60),
+            },
+            //Only updated hourly so no rush
+            WebRequestEvents::UpdateAirQuality => RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_secs(5),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(20),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(30 * 60),
+            },
+            WebRequestEvents::UpdateOfficeStatus => RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(10),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(10 * 60),
+            },
+            //Nothing works right without the time so try hard
+            WebRequestEvents::GetTime => RetryPolicy {
+                attempts: 5,
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(60),
+                timeout: Duration::from_secs(15),
+                failures_to_open: 5,
+                open_for: Duration::from_secs(5 * 60),
+            },
+            WebRequestEvents::CheckBlueSkyNotifications => RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_secs(5),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(20),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(30 * 60),
+            },
+            //Someone is waiting on this after pressing the button so do not wait long between tries
+            WebRequestEvents::MarkBlueSkyNotificationsSeen => RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(5),
+                timeout: Duration::from_secs(20),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(5 * 60),
+            },
+            WebRequestEvents::UpdateFeed => RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_secs(5),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(20),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(30 * 60),
+            },
+            //Calendars with years of events in them take a while to read through
+            WebRequestEvents::UpdateCalendar => RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_secs(5),
+                max_delay: Duration::from_secs(30),
+                timeout: Duration::from_secs(45),
+                failures_to_open: 3,
+                open_for: Duration::from_secs(30 * 60),
+            },
+        }
+    }
+}
+
+//Big responses go through the channel as they are, there is no heap to box them on
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum GeneralEvents {
+    ForecastUpdated(ForecastResponse),
+    AirQualityUpdated(AirQualityResponse),
+    ///Local time and the UTC offset in seconds
+    TimeFromApi(DateTime, i64),
+    //TODO also pass what was changed? Like hour, minute etc
+    TimeDigitChanged(DateTime),
+    SensorUpdate(InsideSensorData),
+    ///True if clocked in
+    OfficeStatusUpdated(bool),
+    BlueSkyNotificationUpdate(BlueSkyNotificationData),
+    BlueSkyNotificationsSeen,
+    ///Button to show the next page of notifications
+    NotificationPageTurned,
+    FeedUpdated(heapless::Vec<FeedPost, MAX_FEED_POSTS>),
+    CalendarUpdated(heapless::Vec<CalendarEvent, MAX_EVENTS>),
+    ///A web service went offline or came back
+    WebCircuitChanged(&'static str, CircuitState),
+    ///Asked for over MQTT or the local API
+    ScreenRequested(Screen),
+    ///Shown full screen for MESSAGE_MINUTES. Empty takes it down
+    ShowMessage(String<MESSAGE_SIZE>),
+}
+
+impl GeneralEvents {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GeneralEvents::ForecastUpdated(_) => "ForecastUpdated",
+            GeneralEvents::AirQualityUpdated(_) => "AirQualityUpdated",
+            GeneralEvents::TimeFromApi(_, _) => "TimeFromApi",
+            GeneralEvents::TimeDigitChanged(_) => "TimeDigitChanged",
+            GeneralEvents::SensorUpdate(_) => "SensorUpdate",
+            GeneralEvents::OfficeStatusUpdated(_) => "OfficeStatusUpdated",
+            GeneralEvents::BlueSkyNotificationUpdate(_) => "BlueSkyNotificationUpdate",
+            GeneralEvents::BlueSkyNotificationsSeen => "BlueSkyNotificationsSeen",
+            GeneralEvents::NotificationPageTurned => "NotificationPageTurned",
+            GeneralEvents::FeedUpdated(_) => "FeedUpdated",
+            GeneralEvents::CalendarUpdated(_) => "CalendarUpdated",
+            GeneralEvents::WebCircuitChanged(_, _) => "WebCircuitChanged",
+            GeneralEvents::ScreenRequested(_) => "ScreenRequested",
+            GeneralEvents::ShowMessage(_) => "ShowMessage",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StateChanges {
+    None,
+    ForecastUpdated,
+    AirQualityUpdated,
+    OfficeStatusUpdated,
+    TimeSet,
+    NewTimeDigit,
+    SensorUpdate,
+    BlueSkyNotificationUpdate,
+    FeedUpdated,
+    CalendarUpdated,
+    WebCircuitChanged,
+    ///The message, feed or dashboard was asked for
+    ScreenChanged,
+}
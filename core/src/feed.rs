@@ -0,0 +1,111 @@
+/// Where the posts for the feed screen come from. Set with BLUESKY_FEED in the .env
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FeedSource {
+    /// Feed screen is turned off
+    Off,
+    /// Your home timeline, app.bsky.feed.getTimeline
+    Timeline,
+    /// A custom feed by its at:// uri, app.bsky.feed.getFeed
+    Feed(&'static str),
+}
+
+/// Feed screen settings
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FeedConfig {
+    pub source: FeedSource,
+    /// Minutes of the dashboard between showing the feed. 0 leaves the feed up all the time
+    pub every_minutes: u16,
+    /// Minutes the feed stays up, one post a minute
+    pub show_minutes: u16,
+}
+
+impl FeedConfig {
+    /// From BLUESKY_FEED, FEED_EVERY_MINUTES and FEED_SHOW_MINUTES in the .env. Off by default
+    pub fn parse(feed: &'static str, every_minutes: &str, show_minutes: &str) -> Self {
+        let source = match feed {
+            "" => FeedSource::Off,
+            "timeline" => FeedSource::Timeline,
+            uri if uri.starts_with("at://") => FeedSource::Feed(uri),
+            _ => {
+                error!("BLUESKY_FEED should be timeline or an at:// feed uri, turning the feed screen off");
+                FeedSource::Off
+            }
+        };
+        Self {
+            source,
+            every_minutes: every_minutes.parse().unwrap_or(10),
+            show_minutes: show_minutes.parse().unwrap_or(2).max(1),
+        }
+    }
+}
+
+/// The two screens the buddy takes turns with, for asking for one from MQTT or the local API
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Screen {
+    Dashboard,
+    Feed,
+}
+
+impl Screen {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dashboard" => Some(Screen::Dashboard),
+            "feed" => Some(Screen::Feed),
+            _ => None,
+        }
+    }
+}
+
+/// Swaps between the dashboard and the feed screen on the minute tick, moving to the next post every minute the feed is up
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeedRotation {
+    pub showing_feed: bool,
+    pub post_index: usize,
+    minutes: u16,
+}
+
+impl FeedRotation {
+    pub const fn new() -> Self {
+        Self {
+            showing_feed: false,
+            post_index: 0,
+            minutes: 0,
+        }
+    }
+
+    /// Puts up a screen now. It stays for the usual time before taking turns again
+    pub fn show(&mut self, screen: Screen) {
+        self.showing_feed = screen == Screen::Feed;
+        self.minutes = 0;
+    }
+
+    /// Call once a minute. Returns true if the screen or the post changed
+    pub fn tick(&mut self, config: &FeedConfig, post_count: usize) -> bool {
+        if config.source == FeedSource::Off || post_count == 0 {
+            let changed = self.showing_feed;
+            self.showing_feed = false;
+            self.minutes = 0;
+            return changed;
+        }
+
+        self.minutes = self.minutes.saturating_add(1);
+        if self.showing_feed {
+            if config.every_minutes != 0 && self.minutes >= config.show_minutes {
+                self.showing_feed = false;
+                self.minutes = 0;
+            }
+            self.post_index = (self.post_index + 1) % post_count;
+            return true;
+        }
+
+        if self.minutes >= config.every_minutes {
+            self.showing_feed = true;
+            self.minutes = 0;
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,125 @@
+use embassy_time::{Duration, Instant};
+
+/// How long a piece of state is good for before the display calls it stale and it gets asked for again
+#[derive(Debug, Clone, Copy)]
+pub struct FreshnessPolicy {
+    /// Older than this and it is shown as stale
+    pub stale_after: Duration,
+    /// How often to ask for it again while it stays stale so a dead connection is not hammered.
+    /// None for the ones that are not asked for again, they keep coming on their own schedule
+    pub retry_every: Option<Duration>,
+}
+
+/// Forecast is normally updated every 15 minutes
+pub const FORECAST_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(60 * 60),
+    retry_every: Some(Duration::from_secs(5 * 60)),
+};
+
+/// Open-Meteo only updates air quality hourly
+pub const AIR_QUALITY_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(3 * 60 * 60),
+    retry_every: Some(Duration::from_secs(15 * 60)),
+};
+
+/// The scd40 is read every 30 seconds so a few minutes means something is wrong. There is nothing to ask for,
+/// the sensor task keeps trying on its own
+pub const SENSOR_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(5 * 60),
+    retry_every: None,
+};
+
+/// Checked every 15 minutes with the forecast
+pub const BLUE_SKY_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(60 * 60),
+    retry_every: Some(Duration::from_secs(5 * 60)),
+};
+
+/// Polled every OFFICE_POLL_MINUTES already, a badge from this morning is not worth showing as if it were current
+pub const OFFICE_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(20 * 60),
+    retry_every: None,
+};
+
+/// Fetched every 15 minutes with the forecast. Big calendars take a while so not as often as the rest
+pub const CALENDAR_FRESHNESS: FreshnessPolicy = FreshnessPolicy {
+    stale_after: Duration::from_secs(60 * 60),
+    retry_every: Some(Duration::from_secs(10 * 60)),
+};
+
+/// A value in the State with when it was fetched
+#[derive(Debug, Clone)]
+pub struct Timestamped<T> {
+    pub value: T,
+    pub fetched_at: Instant,
+}
+
+impl<T> Timestamped<T> {
+    /// Stamps the value with the current time
+    pub fn now(value: T) -> Self {
+        Self {
+            value,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    pub fn age(&self) -> Duration {
+        self.fetched_at.elapsed()
+    }
+
+    pub fn is_stale(&self, policy: &FreshnessPolicy) -> bool {
+        self.age() > policy.stale_after
+    }
+}
+
+/// Returns the age of the slot if it is stale. Used by the display to know what to mark
+pub fn stale_age<T>(slot: &Option<Timestamped<T>>, policy: &FreshnessPolicy) -> Option<Duration> {
+    match slot {
+        Some(timestamped) if timestamped.is_stale(policy) => Some(timestamped.age()),
+        _ => None,
+    }
+}
+
+/// Keeps track of when a refetch was last asked for so it is only asked for every `retry_every`
+#[derive(Default)]
+pub struct RefetchTimer {
+    last_requested: Option<Instant>,
+}
+
+impl RefetchTimer {
+    pub const fn new() -> Self {
+        Self {
+            last_requested: None,
+        }
+    }
+
+    /// True if the slot is stale, or was never filled after a whole retry period, and we have not asked recently.
+    /// Always false for a policy without `retry_every`
+    pub fn should_refetch<T>(
+        &mut self,
+        slot: &Option<Timestamped<T>>,
+        policy: &FreshnessPolicy,
+    ) -> bool {
+        let Some(retry_every) = policy.retry_every else {
+            return false;
+        };
+        let needs_data = match slot {
+            Some(timestamped) => timestamped.is_stale(policy),
+            //Give the start up requests a chance first
+            None => Instant::now().as_secs() > retry_every.as_secs(),
+        };
+        if !needs_data {
+            return false;
+        }
+
+        let asked_recently = self
+            .last_requested
+            .is_some_and(|last_requested| last_requested.elapsed() < retry_every);
+        if asked_recently {
+            return false;
+        }
+
+        self.last_requested = Some(Instant::now());
+        true
+    }
+}
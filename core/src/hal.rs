@@ -0,0 +1,51 @@
+//! The hardware the tasks need, as traits. The firmware implements these for the RP2040's RTC, the scd40, the cyw43
+//! with the HTTP service and the e-ink panel. The tests use mocks
+
+use crate::events::{GeneralEvents, WebRequestEvents};
+use desk_buddy_ui::date::DateTime;
+use desk_buddy_ui::display::InsideSensorData;
+use embassy_time::Duration;
+
+/// Keeps the local time, like the RTC
+pub trait Clock {
+    type Error;
+
+    fn now(&mut self) -> Result<DateTime, Self::Error>;
+
+    fn set(&mut self, date_time: &DateTime) -> Result<(), Self::Error>;
+}
+
+/// The co2, temperature and humidity sensor
+//Everything runs on one executor thread so the futures do not need to be Send
+#[allow(async_fn_in_trait)]
+pub trait Sensor {
+    type Error;
+
+    /// Gets it measuring. Called once, and should not return until the first reading is ready
+    async fn start(&mut self) -> Result<(), Self::Error>;
+
+    fn measure(&mut self) -> Result<InsideSensorData, Self::Error>;
+}
+
+/// Makes the web requests. Retries and turning off a service that keeps failing are handled by
+/// run_web_requests, this just makes one try
+#[allow(async_fn_in_trait)]
+pub trait NetworkClient {
+    type Error;
+
+    /// The event to send with what came back, or None if there is nothing to tell the orchestrator
+    async fn fetch(
+        &mut self,
+        request: WebRequestEvents,
+        timeout: Duration,
+    ) -> Result<Option<GeneralEvents>, Self::Error>;
+
+    /// True if trying again could help, like a timeout or a server error. A bad url or JSON fails the same way every time
+    fn is_retryable(error: &Self::Error) -> bool;
+}
+
+/// Where finished frames go, the e-ink panel on the buddy
+pub trait DisplaySink {
+    /// Puts up a whole frame. 1 bit a pixel a row at a time with 1 as white, like Display4in2::buffer
+    fn show(&mut self, frame: &[u8]);
+}
@@ -0,0 +1,15 @@
+#![no_std]
+
+#[macro_use]
+mod fmt;
+
+pub mod events;
+pub mod feed;
+pub mod freshness;
+pub mod hal;
+pub mod office;
+pub mod orchestrator;
+pub mod retry;
+pub mod screen;
+pub mod state;
+pub mod tasks;
@@ -0,0 +1,45 @@
+use desk_buddy_ui::date::{DateTime, DayOfWeek};
+
+/// When to ask for the clock in status and when being clocked out means you forgot to clock back in
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OfficeSchedule {
+    pub poll_minutes: u16,
+    /// Minutes of the day. Clocked out between these after being in earlier means you forgot to clock back in
+    pub lunch_end: u16,
+    pub work_end: u16,
+}
+
+impl OfficeSchedule {
+    /// From OFFICE_POLL_MINUTES, LUNCH_END and WORK_END in the .env, times like 13:00
+    pub fn parse(poll_minutes: &str, lunch_end: &str, work_end: &str) -> Self {
+        Self {
+            poll_minutes: poll_minutes.parse().unwrap_or(5).max(1),
+            lunch_end: minutes_of_day(lunch_end).unwrap_or(13 * 60),
+            work_end: minutes_of_day(work_end).unwrap_or(17 * 60),
+        }
+    }
+
+    /// After lunch on a day you were clocked in earlier, but are not now
+    pub fn forgot_to_clock_in(
+        &self,
+        clocked_in: bool,
+        clocked_in_today: bool,
+        now: &DateTime,
+    ) -> bool {
+        let weekend = matches!(now.day_of_week, DayOfWeek::Saturday | DayOfWeek::Sunday);
+        let minutes = now.hour as u16 * 60 + now.minute as u16;
+        !clocked_in
+            && clocked_in_today
+            && !weekend
+            && (self.lunch_end..self.work_end).contains(&minutes)
+    }
+}
+
+/// 13:00 to minutes of the day
+fn minutes_of_day(time: &str) -> Option<u16> {
+    let (hour, minute) = time.split_once(':')?;
+    let hour = hour.parse::<u16>().ok()?;
+    let minute = minute.parse::<u16>().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
@@ -0,0 +1,244 @@
+//! Keeps the State up to date from the events and asks for things again when they go stale
+
+use crate::events::{
+    EventChannel, GeneralEvents, StateChanges, StateChannel, WebRequestChannel, WebRequestEvents,
+};
+use crate::feed::FeedConfig;
+use crate::freshness::{
+    RefetchTimer, Timestamped, AIR_QUALITY_FRESHNESS, BLUE_SKY_FRESHNESS, CALENDAR_FRESHNESS,
+    FORECAST_FRESHNESS,
+};
+use crate::office::OfficeSchedule;
+use crate::retry::CircuitState;
+use crate::state::State;
+use desk_buddy_ui::display::OfficeBadge;
+
+/// Settings from the .env the orchestrator needs
+#[derive(Debug, Clone, Copy)]
+pub struct OrchestratorConfig {
+    pub feed: FeedConfig,
+    /// None if there is no office status to poll
+    pub office: Option<OfficeSchedule>,
+    /// HANDLE is set so Bluesky notifications are checked
+    pub blue_sky: bool,
+    /// CALENDAR_URL is set
+    pub calendar: bool,
+    /// How long a message stays up
+    pub message_minutes: u16,
+}
+
+pub struct Orchestrator {
+    pub state: State,
+    config: OrchestratorConfig,
+    forecast_refetch: RefetchTimer,
+    air_quality_refetch: RefetchTimer,
+    blue_sky_refetch: RefetchTimer,
+    calendar_refetch: RefetchTimer,
+    minutes_since_office_poll: u16,
+}
+
+impl Orchestrator {
+    pub fn new(config: OrchestratorConfig) -> Self {
+        Self {
+            state: State::new(),
+            config,
+            forecast_refetch: RefetchTimer::new(),
+            air_quality_refetch: RefetchTimer::new(),
+            blue_sky_refetch: RefetchTimer::new(),
+            calendar_refetch: RefetchTimer::new(),
+            minutes_since_office_poll: 0,
+        }
+    }
+
+    /// Updates the state from one event. Anything that needs fetching again goes on `web_requests`
+    pub fn handle(&mut self, event: GeneralEvents, web_requests: &WebRequestChannel) {
+        let state = &mut self.state;
+        match event {
+            GeneralEvents::ForecastUpdated(forecast_response) => {
+                state.utc_offset_seconds = Some(forecast_response.utc_offset_seconds);
+                state.forecast = Some(Timestamped::now(forecast_response));
+                state.state_change = StateChanges::ForecastUpdated;
+            }
+            GeneralEvents::AirQualityUpdated(air_quality_response) => {
+                state.air_quality = Some(Timestamped::now(air_quality_response));
+                state.state_change = StateChanges::AirQualityUpdated;
+            }
+            GeneralEvents::TimeFromApi(time, utc_offset_seconds) => {
+                info!("Time received from API");
+                state.date_time_from_api = Some(time);
+                state.utc_offset_seconds = Some(utc_offset_seconds);
+                state.state_change = StateChanges::TimeSet;
+            }
+            GeneralEvents::TimeDigitChanged(time) => {
+                info!("Time digit changed");
+                if state
+                    .approximately_current_time
+                    .as_ref()
+                    .is_some_and(|last_time| last_time.day != time.day)
+                {
+                    state.clocked_in_today = false;
+                }
+                state.approximately_current_time = Some(time);
+                state.state_change = StateChanges::NewTimeDigit;
+
+                //Piggy back on the minute tick to ask again for anything that has gone stale.
+                //try_send so a backed up network client can not block the orchestrator
+                if self
+                    .forecast_refetch
+                    .should_refetch(&state.forecast, &FORECAST_FRESHNESS)
+                {
+                    warn!("Forecast is stale, asking for it again");
+                    let _ = web_requests.try_send(WebRequestEvents::UpdateForecast);
+                }
+                if self
+                    .air_quality_refetch
+                    .should_refetch(&state.air_quality, &AIR_QUALITY_FRESHNESS)
+                {
+                    warn!("Air quality is stale, asking for it again");
+                    let _ = web_requests.try_send(WebRequestEvents::UpdateAirQuality);
+                }
+                if self.config.blue_sky
+                    && self
+                        .blue_sky_refetch
+                        .should_refetch(&state.blue_sky_notification_data, &BLUE_SKY_FRESHNESS)
+                {
+                    warn!("Bluesky notifications are stale, asking for them again");
+                    let _ = web_requests.try_send(WebRequestEvents::CheckBlueSkyNotifications);
+                }
+                if self.config.calendar
+                    && self
+                        .calendar_refetch
+                        .should_refetch(&state.calendar, &CALENDAR_FRESHNESS)
+                {
+                    warn!("Calendar is stale, asking for it again");
+                    let _ = web_requests.try_send(WebRequestEvents::UpdateCalendar);
+                }
+
+                let post_count = state
+                    .feed_posts
+                    .as_ref()
+                    .map_or(0, |posts| posts.value.len());
+                if let Some(office) = &self.config.office {
+                    self.minutes_since_office_poll += 1;
+                    if self.minutes_since_office_poll >= office.poll_minutes {
+                        self.minutes_since_office_poll = 0;
+                        let _ = web_requests.try_send(WebRequestEvents::UpdateOfficeStatus);
+                    }
+                    //Forgetting to clock back in happens with time passing, not with a new status
+                    state.office_badge = office_badge(state, office);
+                }
+
+                if let Some((_, minutes_left)) = &mut state.message {
+                    //MESSAGE_MINUTES="0" still takes it down on the next minute instead of wrapping
+                    *minutes_left = minutes_left.saturating_sub(1);
+                    if *minutes_left == 0 {
+                        state.message = None;
+                    }
+                }
+
+                if state.feed_rotation.tick(&self.config.feed, post_count) {
+                    info!(
+                        "Feed screen showing: {}, post {}",
+                        state.feed_rotation.showing_feed, state.feed_rotation.post_index
+                    );
+                }
+            }
+            GeneralEvents::OfficeStatusUpdated(clocked_in) => {
+                state.clocked_in = Some(Timestamped::now(clocked_in));
+                state.clocked_in_today |= clocked_in;
+                if let Some(office) = &self.config.office {
+                    state.office_badge = office_badge(state, office);
+                }
+                state.state_change = StateChanges::OfficeStatusUpdated;
+            }
+            GeneralEvents::SensorUpdate(sensor_data) => {
+                state.sensor_data = Some(Timestamped::now(sensor_data));
+                state.state_change = StateChanges::SensorUpdate;
+            }
+            GeneralEvents::BlueSkyNotificationUpdate(notification_data) => {
+                state.blue_sky_notification_data = Some(Timestamped::now(notification_data));
+                state.notification_page = 0;
+                state.state_change = StateChanges::BlueSkyNotificationUpdate;
+            }
+            GeneralEvents::BlueSkyNotificationsSeen => {
+                //Keeps the fetched time so marking them seen does not make stale data look fresh
+                if let Some(notification_data) = &mut state.blue_sky_notification_data {
+                    notification_data.value.unread_notifications = 0;
+                    for notification in notification_data.value.notifications.iter_mut() {
+                        notification.is_read = true;
+                    }
+                }
+                state.state_change = StateChanges::BlueSkyNotificationUpdate;
+            }
+            GeneralEvents::NotificationPageTurned => {
+                if let Some(notification_data) = &state.blue_sky_notification_data {
+                    state.notification_page =
+                        (state.notification_page + 1) % notification_data.value.pages();
+                }
+                state.state_change = StateChanges::BlueSkyNotificationUpdate;
+            }
+            GeneralEvents::FeedUpdated(posts) => {
+                state.feed_posts = Some(Timestamped::now(posts));
+                state.feed_rotation.post_index = 0;
+                state.state_change = StateChanges::FeedUpdated;
+            }
+            GeneralEvents::CalendarUpdated(events) => {
+                state.calendar = Some(Timestamped::now(events));
+                state.state_change = StateChanges::CalendarUpdated;
+            }
+            GeneralEvents::WebCircuitChanged(name, circuit_state) => {
+                state.offline_services.retain(|service| *service != name);
+                if circuit_state != CircuitState::Closed {
+                    let _ = state.offline_services.push(name);
+                }
+                state.state_change = StateChanges::WebCircuitChanged;
+            }
+            GeneralEvents::ScreenRequested(screen) => {
+                //Asking for a screen takes down the message too
+                state.message = None;
+                state.feed_rotation.show(screen);
+                state.state_change = StateChanges::ScreenChanged;
+            }
+            GeneralEvents::ShowMessage(message) => {
+                state.message =
+                    (!message.is_empty()).then_some((message, self.config.message_minutes));
+                state.state_change = StateChanges::ScreenChanged;
+            }
+        }
+    }
+}
+
+/// The clock in badge, None until we have heard from the office status
+fn office_badge(state: &State, office: &OfficeSchedule) -> Option<OfficeBadge> {
+    let clocked_in = state.clocked_in.as_ref()?.value;
+    let forgot = state
+        .approximately_current_time
+        .as_ref()
+        .is_some_and(|now| office.forgot_to_clock_in(clocked_in, state.clocked_in_today, now));
+    Some(match (clocked_in, forgot) {
+        (true, _) => OfficeBadge::ClockedIn,
+        (false, true) => OfficeBadge::ForgotToClockIn,
+        (false, false) => OfficeBadge::ClockedOut,
+    })
+}
+
+/// Waits for events and sends each new state on to the clock and the display. `on_state` sees every state first,
+/// the firmware uses it to hand the state to the local API and MQTT
+pub async fn run_orchestrator(
+    config: OrchestratorConfig,
+    events: &EventChannel,
+    states: &StateChannel,
+    web_requests: &WebRequestChannel,
+    mut on_state: impl FnMut(&State),
+) -> ! {
+    let mut orchestrator = Orchestrator::new(config);
+    loop {
+        //Wait for an event
+        let event = events.receive().await;
+        info!("Event received: {:?}", event.as_str());
+        orchestrator.handle(event, web_requests);
+        info!("State change: {:?}", orchestrator.state.state_change);
+        on_state(&orchestrator.state);
+        states.send(orchestrator.state.clone()).await;
+    }
+}
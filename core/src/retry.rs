@@ -0,0 +1,82 @@
+use embassy_time::{Duration, Instant};
+
+/// How a kind of web request is retried when it fails
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Tries in total, including the first one
+    pub attempts: u8,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Passed on to each WebRequest
+    pub timeout: Duration,
+    /// Failed events in a row (after all their retries) before the circuit opens
+    pub failures_to_open: u8,
+    /// How long an open circuit skips requests before letting one through to test the water
+    pub open_for: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with half the delay fixed and the other half random,
+    /// so a few devices on the same network do not all retry at the same time
+    pub fn backoff(&self, attempt: u8, random: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .as_millis()
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay.as_millis());
+        let half = exponential / 2;
+        Duration::from_millis(half + random as u64 % (half + 1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CircuitState {
+    /// Working normally
+    Closed,
+    /// Failed too many times in a row so requests are skipped for a while
+    Open,
+    /// Done waiting, the next request decides if it closes or opens again
+    HalfOpen,
+}
+
+/// Stops the wireless_task from hammering a service that is down
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CircuitBreaker {
+    failures: u8,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub const fn new() -> Self {
+        Self {
+            failures: 0,
+            opened_at: None,
+        }
+    }
+
+    pub fn state(&self, policy: &RetryPolicy) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < policy.open_for => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub fn allows_request(&self, policy: &RetryPolicy) -> bool {
+        self.state(policy) != CircuitState::Open
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.opened_at = None;
+    }
+
+    pub fn record_failure(&mut self, policy: &RetryPolicy) {
+        self.failures = self.failures.saturating_add(1);
+        if self.failures >= policy.failures_to_open {
+            //Also restarts the wait if the half open test request failed
+            self.opened_at = Some(Instant::now());
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Draws the State and decides when a new frame goes up. The e-ink panel is slow and flashes, so most changes
+//! are only drawn into the frame and go up with the next minute
+
+use crate::events::{StateChanges, StateChannel};
+use crate::freshness::{
+    stale_age, AIR_QUALITY_FRESHNESS, BLUE_SKY_FRESHNESS, CALENDAR_FRESHNESS, FORECAST_FRESHNESS,
+    OFFICE_FRESHNESS, SENSOR_FRESHNESS,
+};
+use crate::hal::DisplaySink;
+use crate::state::State;
+use desk_buddy_ui::annual::AnnualEvents;
+use desk_buddy_ui::astronomy::{self, SunTimes};
+use desk_buddy_ui::date::{parse_date_time, utc_minutes, DateTime};
+use desk_buddy_ui::display::{
+    draw_air_quality, draw_badge_area, draw_blue_sky_notification, draw_calendar,
+    draw_current_outside_weather, draw_feed_post, draw_forecast, draw_message, draw_moon_phase,
+    draw_scd_data, draw_stale_data, draw_sun_times, draw_time, ForecastConfig,
+};
+use desk_buddy_ui::io::easy_format;
+use desk_buddy_ui::layout;
+use embedded_graphics::prelude::*;
+use epd_waveshare::{color::Color, epd4in2_v2::Display4in2};
+use heapless::String;
+
+/// Settings from the .env the screens need
+pub struct DisplayConfig {
+    pub forecast: ForecastConfig,
+    pub annual_events: AnnualEvents,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub fahrenheit: bool,
+}
+
+/// The dashboard frame, kept between states so only what changed is drawn again, and the frame the feed and
+/// messages share
+pub struct Screens<'a> {
+    config: DisplayConfig,
+    pub display: Display4in2,
+    pub feed_display: &'a mut Display4in2,
+}
+
+impl<'a> Screens<'a> {
+    pub fn new(config: DisplayConfig, feed_display: &'a mut Display4in2) -> Self {
+        let mut display = Display4in2::default();
+        //TODO need to come back and look at the epd driver I think there should be a cleaner clear function
+        display.clear(Color::White).ok();
+        Self {
+            config,
+            display,
+            feed_display,
+        }
+    }
+
+    /// Draws what changed in the state and puts a frame up on `sink` if it needs to be seen now
+    pub fn update(&mut self, state: &State, sink: &mut impl DisplaySink) {
+        let config = &self.config;
+        let display = &mut self.display;
+        match state.state_change {
+            StateChanges::None => {}
+            StateChanges::ForecastUpdated => {
+                if let Some(forecast) = &state.forecast {
+                    let forecast = &forecast.value;
+                    //Bottom of the screen is for the forecast
+                    draw_forecast(
+                        layout::FORECAST,
+                        layout::FORECAST_SIZE,
+                        forecast,
+                        config.forecast,
+                        &config.annual_events,
+                        state.approximately_current_time.clone(),
+                        display,
+                    );
+                    //Current forecast
+                    let mut daytime = true;
+                    if let Some(current_time) = &state.approximately_current_time {
+                        info!(
+                            "Current time: {}:{}:{} ",
+                            current_time.hour, current_time.minute, current_time.second
+                        );
+                        daytime = todays_sun_times(
+                            state,
+                            current_time,
+                            config.latitude,
+                            config.longitude,
+                        )
+                        .is_daytime(current_time);
+                    }
+                    draw_current_outside_weather(
+                        layout::CURRENT_WEATHER,
+                        forecast.current.clone(),
+                        forecast.current_units.clone(),
+                        daytime,
+                        display,
+                    );
+
+                    //Moon phase goes to the right of the current weather at night
+                    let possible_moon = match (daytime, &state.approximately_current_time) {
+                        (false, Some(current_time)) => Some(astronomy::moon_info(
+                            current_time,
+                            forecast.utc_offset_seconds,
+                        )),
+                        _ => None,
+                    };
+                    draw_moon_phase(layout::MOON, possible_moon, config.latitude < 0.0, display);
+                    draw_stale_state(state, display);
+                    //Still drawn while the feed screen is up so the dashboard is ready when it comes back
+                    if state.message.is_none() && !state.feed_rotation.showing_feed {
+                        sink.show(display.buffer());
+                    }
+                }
+            }
+            StateChanges::AirQualityUpdated => {
+                //Same as the sensor data, let the next digit change update the display
+                if let Some(air_quality) = &state.air_quality {
+                    draw_air_quality(
+                        layout::AIR_QUALITY,
+                        air_quality.value.current.clone(),
+                        display,
+                    );
+                }
+            }
+            StateChanges::OfficeStatusUpdated => {
+                //Same as the sensor data, let the next digit change update the display
+                draw_badge_state(state, &config.annual_events, display);
+            }
+            StateChanges::TimeSet => {
+                //Ignoring this event and it should hopefully not get hit since RTC loads first
+                //All time updates for display will come via the time digit change event
+            }
+            StateChanges::NewTimeDigit => {
+                if let Some(date_time) = state.approximately_current_time.clone() {
+                    //No forecast yet so at least show the sun times worked out offline
+                    if state.forecast.is_none() {
+                        draw_sun_times(
+                            layout::FORECAST,
+                            todays_sun_times(state, &date_time, config.latitude, config.longitude),
+                            display,
+                        );
+                    }
+                    draw_time(date_time, display);
+                }
+                //Keeps the "5m" next to each notification up to date
+                draw_blue_sky_state(state, display);
+                draw_badge_state(state, &config.annual_events, display);
+                //Keeps the countdown going
+                if !draw_stale_state(state, display) {
+                    draw_calendar_state(state, display);
+                }
+
+                //The feed screen moves to the next post on the minute too
+                sink.show(pick_frame(state, display, self.feed_display));
+            }
+            StateChanges::SensorUpdate => {
+                //TODO not updating the display and just let another like digit change update it
+                if let Some(sensor_data) = &state.sensor_data {
+                    draw_scd_data(
+                        layout::SENSORS,
+                        sensor_data.value,
+                        config.fahrenheit,
+                        display,
+                    );
+                }
+            }
+            StateChanges::WebCircuitChanged => {
+                //Shown with the stale data on the next digit change
+            }
+            StateChanges::BlueSkyNotificationUpdate => {
+                if state.blue_sky_notification_data.is_some() {
+                    draw_blue_sky_state(state, display);
+                    //TODO when this is a timer task just let the new digit update the display
+                    if state.message.is_none() && !state.feed_rotation.showing_feed {
+                        sink.show(display.buffer());
+                    }
+                }
+            }
+            StateChanges::FeedUpdated => {
+                if state.message.is_none()
+                    && state.feed_rotation.showing_feed
+                    && draw_feed_state(state, self.feed_display)
+                {
+                    sink.show(self.feed_display.buffer());
+                }
+            }
+            StateChanges::CalendarUpdated => {
+                //Same as the sensor data, let the next digit change update the display
+                if !draw_stale_state(state, display) {
+                    draw_calendar_state(state, display);
+                }
+            }
+            StateChanges::ScreenChanged => {
+                sink.show(pick_frame(state, display, self.feed_display));
+            }
+        }
+    }
+}
+
+/// Clears the panel, then draws every new state
+pub async fn run_display(
+    config: DisplayConfig,
+    feed_display: &mut Display4in2,
+    states: &StateChannel,
+    sink: &mut impl DisplaySink,
+) -> ! {
+    let mut screens = Screens::new(config, feed_display);
+    sink.show(screens.display.buffer());
+    info!("Forecast config: {:?}", screens.config.forecast);
+
+    loop {
+        let state = states.receive().await;
+        info!("State received Display: {:?}", state.state_change);
+        screens.update(&state, sink);
+    }
+}
+
+/// Draws the current page of notifications in the top right
+fn draw_blue_sky_state(state: &State, display: &mut impl DrawTarget<Color = Color>) {
+    let Some(notification_data) = &state.blue_sky_notification_data else {
+        return;
+    };
+    let now = state
+        .approximately_current_time
+        .as_ref()
+        .map(|current_time| {
+            astronomy::julian_day(current_time, state.utc_offset_seconds.unwrap_or(0))
+        });
+    draw_blue_sky_notification(
+        layout::NOTIFICATIONS,
+        &notification_data.value,
+        state.notification_page,
+        now,
+        display,
+    );
+}
+
+/// Draws the post the feed rotation is on. False if there are no posts to show
+fn draw_feed_state(state: &State, display: &mut impl DrawTarget<Color = Color>) -> bool {
+    let Some(posts) = &state.feed_posts else {
+        return false;
+    };
+    let posts = &posts.value;
+    if posts.is_empty() {
+        return false;
+    }
+    let position = state.feed_rotation.post_index % posts.len();
+    let now = state
+        .approximately_current_time
+        .as_ref()
+        .map(|current_time| {
+            astronomy::julian_day(current_time, state.utc_offset_seconds.unwrap_or(0))
+        });
+    draw_feed_post(&posts[position], position, posts.len(), now, display);
+    true
+}
+
+/// A message goes over everything, then the feed if it is its turn, otherwise the dashboard.
+/// The message and feed share a frame
+fn pick_frame<'a>(
+    state: &State,
+    display: &'a Display4in2,
+    feed_display: &'a mut Display4in2,
+) -> &'a [u8] {
+    if let Some((message, _)) = &state.message {
+        draw_message(message, feed_display);
+        return feed_display.buffer();
+    }
+    if state.feed_rotation.showing_feed && draw_feed_state(state, feed_display) {
+        return feed_display.buffer();
+    }
+    display.buffer()
+}
+
+/// The badge under the time. draw_badge_area picks what goes there
+fn draw_badge_state(
+    state: &State,
+    annual_events: &AnnualEvents,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    draw_badge_area(
+        layout::BADGE,
+        state.office_badge,
+        annual_events,
+        state.approximately_current_time.as_ref(),
+        display,
+    );
+}
+
+/// Draws the next calendar events where the stale list goes
+fn draw_calendar_state(state: &State, display: &mut impl DrawTarget<Color = Color>) {
+    let (Some(calendar), Some(current_time)) = (&state.calendar, &state.approximately_current_time)
+    else {
+        return;
+    };
+    let utc_offset_seconds = state.utc_offset_seconds.unwrap_or(0);
+    draw_calendar(
+        layout::STATUS,
+        &calendar.value,
+        utc_minutes(current_time, utc_offset_seconds),
+        utc_offset_seconds / 60,
+        display,
+    );
+}
+
+/// Lists anything in the state that has gone stale to the right of the current weather.
+/// False if there is nothing to list, the calendar gets the spot then
+fn draw_stale_state(state: &State, display: &mut impl DrawTarget<Color = Color>) -> bool {
+    //Ages in minutes
+    let mut stale: heapless::Vec<(&str, u64), 4> = heapless::Vec::new();
+    if let Some(age) = stale_age(&state.forecast, &FORECAST_FRESHNESS) {
+        let _ = stale.push(("Forecast", age.as_secs() / 60));
+    }
+    if let Some(age) = stale_age(&state.air_quality, &AIR_QUALITY_FRESHNESS) {
+        let _ = stale.push(("Air", age.as_secs() / 60));
+    }
+    if let Some(age) = stale_age(&state.sensor_data, &SENSOR_FRESHNESS) {
+        let _ = stale.push(("Sensor", age.as_secs() / 60));
+    }
+    if let Some(age) = stale_age(&state.blue_sky_notification_data, &BLUE_SKY_FRESHNESS) {
+        let _ = stale.push(("Bluesky", age.as_secs() / 60));
+    }
+    if let Some(age) = stale_age(&state.clocked_in, &OFFICE_FRESHNESS) {
+        let _ = stale.push(("Office", age.as_secs() / 60));
+    }
+    if let Some(age) = stale_age(&state.calendar, &CALENDAR_FRESHNESS) {
+        let _ = stale.push(("Calendar", age.as_secs() / 60));
+    }
+    draw_stale_data(layout::STATUS, &stale, &state.offline_services, display);
+    !stale.is_empty() || !state.offline_services.is_empty()
+}
+
+/// Today's sun times. Uses the forecast when it still has today in it,
+/// otherwise falls back to working it out offline from the configured location
+fn todays_sun_times(
+    state: &State,
+    current_time: &DateTime,
+    latitude: f64,
+    longitude: f64,
+) -> SunTimes {
+    let utc_offset_seconds = state.utc_offset_seconds.unwrap_or(0);
+    let mut sun_times = astronomy::sun_times(current_time, latitude, longitude, utc_offset_seconds);
+
+    //A stale forecast is still fine here as long as it has today in it
+    let Some(forecast) = &state.forecast else {
+        return sun_times;
+    };
+    let forecast = &forecast.value;
+
+    let today: String<10> = easy_format(format_args!(
+        "{:04}-{:02}-{:02}",
+        current_time.year, current_time.month, current_time.day
+    ));
+    //If the forecast is stale today may not be the first day or in there at all
+    if let Some(index) = forecast.daily.time.iter().position(|date| *date == today) {
+        //Keeps the offline times for any the forecast has that we can not read
+        if let Ok(sunrise) = parse_date_time(&forecast.daily.sunrise[index]) {
+            let sunrise = sunrise.date_time;
+            sun_times.sunrise = Some(sunrise.hour as u16 * 60 + sunrise.minute as u16);
+        }
+        if let Ok(sunset) = parse_date_time(&forecast.daily.sunset[index]) {
+            let sunset = sunset.date_time;
+            sun_times.sunset = Some(sunset.hour as u16 * 60 + sunset.minute as u16);
+        }
+    }
+    sun_times
+}
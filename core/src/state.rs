@@ -0,0 +1,69 @@
+use crate::events::{StateChanges, WebRequestEvents, MESSAGE_SIZE};
+use crate::feed::FeedRotation;
+use crate::freshness::Timestamped;
+use desk_buddy_ui::date::DateTime;
+use desk_buddy_ui::display::{
+    BlueSkyNotificationData, CalendarEvent, FeedPost, InsideSensorData, OfficeBadge, MAX_EVENTS,
+    MAX_FEED_POSTS,
+};
+use desk_buddy_ui::weather::{AirQualityResponse, ForecastResponse};
+use heapless::String;
+
+///Anything that comes from a web request or sensor is timestamped so the display can tell when it is stale
+#[derive(Debug, Clone)]
+pub struct State {
+    pub forecast: Option<Timestamped<ForecastResponse>>,
+    pub air_quality: Option<Timestamped<AirQualityResponse>>,
+    pub date_time_from_api: Option<DateTime>,
+    pub approximately_current_time: Option<DateTime>,
+    ///Needed to work out the sun and moon offline. Comes from the time api or the forecast
+    pub utc_offset_seconds: Option<i64>,
+    pub sensor_data: Option<Timestamped<InsideSensorData>>,
+    ///True if clocked in
+    pub clocked_in: Option<Timestamped<bool>>,
+    ///Resets at midnight. Tells a day off apart from forgetting to clock back in after lunch
+    pub clocked_in_today: bool,
+    pub office_badge: Option<OfficeBadge>,
+    pub calendar: Option<Timestamped<heapless::Vec<CalendarEvent, MAX_EVENTS>>>,
+    pub blue_sky_notification_data: Option<Timestamped<BlueSkyNotificationData>>,
+    ///Which page of notifications is showing
+    pub notification_page: usize,
+    pub feed_posts: Option<Timestamped<heapless::Vec<FeedPost, MAX_FEED_POSTS>>>,
+    ///If the feed screen is up instead of the dashboard and which post it is on
+    pub feed_rotation: FeedRotation,
+    ///A message to show over everything else and the minutes it has left
+    pub message: Option<(String<MESSAGE_SIZE>, u16)>,
+    ///Web services the network client has given up on for now
+    pub offline_services: heapless::Vec<&'static str, { WebRequestEvents::COUNT }>,
+    pub state_change: StateChanges,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self {
+            forecast: None,
+            air_quality: None,
+            date_time_from_api: None,
+            approximately_current_time: None,
+            utc_offset_seconds: None,
+            sensor_data: None,
+            clocked_in: None,
+            clocked_in_today: false,
+            office_badge: None,
+            calendar: None,
+            blue_sky_notification_data: None,
+            notification_page: 0,
+            feed_posts: None,
+            feed_rotation: FeedRotation::new(),
+            message: None,
+            offline_services: heapless::Vec::new(),
+            state_change: StateChanges::None,
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
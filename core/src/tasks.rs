@@ -0,0 +1,130 @@
+//! The loops behind the clock, sensor and network tasks. The firmware's tasks hand these the real hardware
+
+use crate::events::{
+    EventChannel, GeneralEvents, StateChanges, StateChannel, WebRequestChannel, WebRequestEvents,
+};
+use crate::hal::{Clock, NetworkClient, Sensor};
+use crate::retry::{CircuitBreaker, RetryPolicy};
+use embassy_time::{Duration, Timer};
+
+/// Sets the clock once the time comes from the API, then sends the time every time the minute changes
+pub async fn run_clock<C: Clock>(clock: &mut C, states: &StateChannel, events: &EventChannel) -> ! {
+    loop {
+        //TODO This task needs to break before the display starts listening to the same channel so currently just have a 30sec delay before display init
+        let state = states.receive().await;
+        info!("State received RTC: {:?}", state.state_change);
+        if state.state_change != StateChanges::TimeSet {
+            continue;
+        }
+        let Some(time) = state.date_time_from_api else {
+            continue;
+        };
+        match clock.set(&time) {
+            Ok(_) => {
+                if let Ok(time) = clock.now() {
+                    events.send(GeneralEvents::TimeDigitChanged(time)).await;
+                }
+                info!("Time received and set");
+                break;
+            }
+            Err(_) => error!("Could not set the clock"),
+        }
+    }
+
+    let mut hour = 0;
+    let mut minute = 0;
+
+    loop {
+        //TODO need a loop that watches for event to set time as well for daily for time drift?
+        match clock.now() {
+            Ok(time) => {
+                if time.hour != hour || time.minute != minute {
+                    info!("Time: {}:{} {}", time.hour, time.minute, time.second);
+                    hour = time.hour;
+                    minute = time.minute;
+                    events.send(GeneralEvents::TimeDigitChanged(time)).await;
+                }
+            }
+            Err(_) => error!("Could not read the clock"),
+        }
+        Timer::after(Duration::from_secs(1)).await;
+    }
+}
+
+/// Reads the sensor every `every` once it has started. A bad reading is skipped, the display shows it as stale
+/// if it keeps happening
+pub async fn run_sensor<S: Sensor>(sensor: &mut S, events: &EventChannel, every: Duration) -> ! {
+    while sensor.start().await.is_err() {
+        error!("Could not start the sensor, trying again");
+        Timer::after(every).await;
+    }
+    loop {
+        match sensor.measure() {
+            Ok(data) => events.send(GeneralEvents::SensorUpdate(data)).await,
+            Err(_) => error!("Could not read the sensor"),
+        }
+        Timer::after(every).await;
+    }
+}
+
+/// Makes each web request that comes in with retries and a circuit breaker per kind of request, and sends on
+/// what came back. `retry_policy` is normally WebRequestEvents::retry_policy and `random` spreads out the retries
+pub async fn run_web_requests<N: NetworkClient>(
+    client: &mut N,
+    web_requests: &WebRequestChannel,
+    events: &EventChannel,
+    retry_policy: impl Fn(&WebRequestEvents) -> RetryPolicy,
+    mut random: impl FnMut() -> u32,
+) -> ! {
+    let mut circuits = [CircuitBreaker::new(); WebRequestEvents::COUNT];
+
+    loop {
+        //Wait for an event
+        let event = web_requests.receive().await;
+        info!("Web request received: {:?}", event);
+        let policy = retry_policy(&event);
+        let circuit = &mut circuits[event as usize];
+        if !circuit.allows_request(&policy) {
+            warn!("{} is offline, skipping {:?}", event.name(), event);
+            continue;
+        }
+        let circuit_before = circuit.state(&policy);
+
+        let mut attempt = 0;
+        let result = loop {
+            match client.fetch(event, policy.timeout).await {
+                Err(e) if N::is_retryable(&e) && attempt + 1 < policy.attempts => {
+                    let delay = policy.backoff(attempt, random());
+                    warn!("{:?} failed, retrying in {}ms", event, delay.as_millis());
+                    Timer::after(delay).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(possible_event) => {
+                circuit.record_success();
+                if let Some(general_event) = possible_event {
+                    events.send(general_event).await;
+                }
+            }
+            Err(_) => {
+                error!("{:?} failed", event);
+                circuit.record_failure(&policy);
+            }
+        }
+
+        let circuit_after = circuit.state(&policy);
+        if circuit_after != circuit_before {
+            info!("{} circuit is now {:?}", event.name(), circuit_after);
+            events
+                .send(GeneralEvents::WebCircuitChanged(
+                    event.name(),
+                    circuit_after,
+                ))
+                .await;
+        }
+    }
+}
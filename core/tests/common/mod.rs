@@ -0,0 +1,178 @@
+//! Mocks for the hal traits and a way to run the async loops on embassy's std executor
+
+#![allow(dead_code)]
+
+use desk_buddy_core::events::{GeneralEvents, WebRequestEvents};
+use desk_buddy_core::hal::{Clock, DisplaySink, NetworkClient, Sensor};
+use desk_buddy_ui::date::{DateTime, DayOfWeek};
+use desk_buddy_ui::display::InsideSensorData;
+use embassy_executor::Executor;
+use embassy_time::Duration;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::thread;
+
+/// Tuesday afternoon
+pub fn at(hour: u8, minute: u8) -> DateTime {
+    DateTime {
+        year: 2025,
+        month: 1,
+        day: 14,
+        day_of_week: DayOfWeek::Tuesday,
+        hour,
+        minute,
+        second: 0,
+    }
+}
+
+/// The tasks want their channels to live forever like the firmware's statics
+pub fn leak<T>(value: T) -> &'static T {
+    Box::leak(Box::new(value))
+}
+
+type TestFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[embassy_executor::task(pool_size = 8)]
+async fn test_task(test: TestFuture, done: mpsc::Sender<()>) {
+    test.await;
+    let _ = done.send(());
+}
+
+/// Runs `test` on an embassy executor on its own thread, the executor never returns so the thread is left behind.
+/// Fails if it panics or takes longer than `timeout`
+pub fn block_on(test: impl Future<Output = ()> + Send + 'static, timeout: std::time::Duration) {
+    let (done, finished) = mpsc::channel();
+    let test: TestFuture = Box::pin(test);
+    let handle = thread::spawn(move || {
+        let executor = Box::leak(Box::new(Executor::new()));
+        executor.run(|spawner| spawner.must_spawn(test_task(test, done)));
+    });
+    let started = std::time::Instant::now();
+    loop {
+        match finished.recv_timeout(std::time::Duration::from_millis(10)) {
+            Ok(()) => return,
+            Err(mpsc::RecvTimeoutError::Timeout) if handle.is_finished() => {
+                //Hands the panic from the executor thread on to the test
+                if let Err(panic) = handle.join() {
+                    std::panic::resume_unwind(panic);
+                }
+                panic!("Executor stopped without finishing the test");
+            }
+            Err(_) if started.elapsed() > timeout => panic!("Test timed out"),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Hands out the times it is given in order, then keeps giving the last one
+pub struct MockClock {
+    pub times: VecDeque<DateTime>,
+    pub set_to: Option<DateTime>,
+}
+
+impl MockClock {
+    pub fn new(times: impl IntoIterator<Item = DateTime>) -> Self {
+        Self {
+            times: times.into_iter().collect(),
+            set_to: None,
+        }
+    }
+}
+
+impl Clock for MockClock {
+    type Error = ();
+
+    fn now(&mut self) -> Result<DateTime, ()> {
+        if self.times.len() > 1 {
+            return self.times.pop_front().ok_or(());
+        }
+        self.times.front().cloned().ok_or(())
+    }
+
+    fn set(&mut self, date_time: &DateTime) -> Result<(), ()> {
+        self.set_to = Some(date_time.clone());
+        Ok(())
+    }
+}
+
+/// Fails to start `failed_starts` times, then reads back `co2` going up by one each time
+pub struct MockSensor {
+    pub failed_starts: u8,
+    pub co2: u16,
+}
+
+impl Sensor for MockSensor {
+    type Error = ();
+
+    async fn start(&mut self) -> Result<(), ()> {
+        if self.failed_starts > 0 {
+            self.failed_starts -= 1;
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn measure(&mut self) -> Result<InsideSensorData, ()> {
+        self.co2 += 1;
+        Ok(InsideSensorData {
+            co2: self.co2,
+            temperature: 21.5,
+            humidity: 40.0,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MockError {
+    pub retryable: bool,
+}
+
+/// Answers each fetch with the next scripted result, and fails once the script runs out
+pub struct MockNetworkClient {
+    pub results: VecDeque<Result<Option<GeneralEvents>, MockError>>,
+    pub requests: Vec<WebRequestEvents>,
+}
+
+impl MockNetworkClient {
+    pub fn new(
+        results: impl IntoIterator<Item = Result<Option<GeneralEvents>, MockError>>,
+    ) -> Self {
+        Self {
+            results: results.into_iter().collect(),
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl NetworkClient for MockNetworkClient {
+    type Error = MockError;
+
+    async fn fetch(
+        &mut self,
+        request: WebRequestEvents,
+        _timeout: Duration,
+    ) -> Result<Option<GeneralEvents>, MockError> {
+        self.requests.push(request);
+        self.results
+            .pop_front()
+            .unwrap_or(Err(MockError { retryable: false }))
+    }
+
+    fn is_retryable(error: &MockError) -> bool {
+        error.retryable
+    }
+}
+
+/// Keeps every frame it is shown
+#[derive(Default)]
+pub struct MockDisplaySink {
+    pub frames: Vec<Vec<u8>>,
+}
+
+impl DisplaySink for MockDisplaySink {
+    fn show(&mut self, frame: &[u8]) {
+        self.frames.push(frame.to_vec());
+    }
+}
@@ -0,0 +1,43 @@
+//! The list of web request events that arrays indexed by `event as usize` rely on
+
+use desk_buddy_core::events::WebRequestEvents;
+
+#[test]
+fn all_is_in_declaration_order() {
+    for (index, event) in WebRequestEvents::ALL.iter().enumerate() {
+        assert_eq!(*event as usize, index, "{:?} is out of place", event);
+    }
+}
+
+#[test]
+fn all_has_every_event() {
+    //A new variant stops this match from compiling, so it gets added here and then has to be in ALL too
+    let every = |event: WebRequestEvents| match event {
+        WebRequestEvents::UpdateForecast
+        | WebRequestEvents::UpdateAirQuality
+        | WebRequestEvents::UpdateOfficeStatus
+        | WebRequestEvents::GetTime
+        | WebRequestEvents::CheckBlueSkyNotifications
+        | WebRequestEvents::MarkBlueSkyNotificationsSeen
+        | WebRequestEvents::UpdateFeed
+        | WebRequestEvents::UpdateCalendar => event,
+    };
+    for event in [
+        WebRequestEvents::UpdateForecast,
+        WebRequestEvents::UpdateAirQuality,
+        WebRequestEvents::UpdateOfficeStatus,
+        WebRequestEvents::GetTime,
+        WebRequestEvents::CheckBlueSkyNotifications,
+        WebRequestEvents::MarkBlueSkyNotificationsSeen,
+        WebRequestEvents::UpdateFeed,
+        WebRequestEvents::UpdateCalendar,
+    ]
+    .map(every)
+    {
+        assert!(
+            WebRequestEvents::ALL.contains(&event),
+            "{:?} is missing from ALL",
+            event
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! The orchestrator on its own, one event at a time
+
+mod common;
+
+use common::at;
+use desk_buddy_core::events::{GeneralEvents, StateChanges, WebRequestChannel, WebRequestEvents};
+use desk_buddy_core::feed::{FeedConfig, Screen};
+use desk_buddy_core::office::OfficeSchedule;
+use desk_buddy_core::orchestrator::{Orchestrator, OrchestratorConfig};
+use desk_buddy_core::retry::CircuitState;
+use desk_buddy_ui::display::{
+    BlueSkyNotificationData, FeedPost, InsideSensorData, NotificationEntry, NotificationReason,
+    OfficeBadge,
+};
+use heapless::String;
+
+fn config() -> OrchestratorConfig {
+    OrchestratorConfig {
+        feed: FeedConfig::parse("", "10", "2"),
+        office: None,
+        blue_sky: false,
+        calendar: false,
+        message_minutes: 2,
+    }
+}
+
+fn message(text: &str) -> GeneralEvents {
+    GeneralEvents::ShowMessage(String::try_from(text).unwrap())
+}
+
+/// Every request the orchestrator asked for
+fn requested(web_requests: &WebRequestChannel) -> Vec<WebRequestEvents> {
+    let mut requests = Vec::new();
+    while let Ok(request) = web_requests.try_receive() {
+        requests.push(request);
+    }
+    requests
+}
+
+#[test]
+fn sensor_update_sets_the_state() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    let data = InsideSensorData {
+        co2: 612,
+        temperature: 21.5,
+        humidity: 40.0,
+    };
+    orchestrator.handle(GeneralEvents::SensorUpdate(data), &web_requests);
+
+    let state = &orchestrator.state;
+    assert_eq!(state.state_change, StateChanges::SensorUpdate);
+    assert_eq!(state.sensor_data.as_ref().unwrap().value.co2, 612);
+}
+
+#[test]
+fn message_counts_down_and_clears() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    orchestrator.handle(message("Lunch?"), &web_requests);
+    assert_eq!(orchestrator.state.state_change, StateChanges::ScreenChanged);
+    assert_eq!(orchestrator.state.message.as_ref().unwrap().1, 2);
+
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(12, 0)), &web_requests);
+    assert!(orchestrator.state.message.is_some());
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(12, 1)), &web_requests);
+    assert!(orchestrator.state.message.is_none());
+}
+
+#[test]
+fn zero_message_minutes_clears_on_the_next_minute() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(OrchestratorConfig {
+        message_minutes: 0,
+        ..config()
+    });
+    orchestrator.handle(message("Lunch?"), &web_requests);
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(12, 0)), &web_requests);
+    assert!(orchestrator.state.message.is_none());
+}
+
+#[test]
+fn empty_message_takes_it_down() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    orchestrator.handle(message("Lunch?"), &web_requests);
+    orchestrator.handle(message(""), &web_requests);
+    assert!(orchestrator.state.message.is_none());
+}
+
+#[test]
+fn asking_for_a_screen_takes_down_the_message() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    orchestrator.handle(message("Lunch?"), &web_requests);
+    orchestrator.handle(GeneralEvents::ScreenRequested(Screen::Feed), &web_requests);
+
+    let state = &orchestrator.state;
+    assert!(state.message.is_none());
+    assert!(state.feed_rotation.showing_feed);
+    assert_eq!(state.state_change, StateChanges::ScreenChanged);
+}
+
+#[test]
+fn offline_services_follow_the_circuit() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    orchestrator.handle(
+        GeneralEvents::WebCircuitChanged("Forecast", CircuitState::Open),
+        &web_requests,
+    );
+    orchestrator.handle(
+        GeneralEvents::WebCircuitChanged("Forecast", CircuitState::HalfOpen),
+        &web_requests,
+    );
+    assert_eq!(orchestrator.state.offline_services.as_slice(), ["Forecast"]);
+
+    orchestrator.handle(
+        GeneralEvents::WebCircuitChanged("Forecast", CircuitState::Closed),
+        &web_requests,
+    );
+    assert!(orchestrator.state.offline_services.is_empty());
+}
+
+#[test]
+fn office_is_polled_every_poll_minutes() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(OrchestratorConfig {
+        office: Some(OfficeSchedule::parse("3", "13:00", "17:00")),
+        ..config()
+    });
+    let mut polls = 0;
+    for minute in 0..9 {
+        orchestrator.handle(
+            GeneralEvents::TimeDigitChanged(at(9, minute)),
+            &web_requests,
+        );
+        polls += requested(&web_requests)
+            .iter()
+            .filter(|request| **request == WebRequestEvents::UpdateOfficeStatus)
+            .count();
+    }
+    assert_eq!(polls, 3);
+}
+
+#[test]
+fn forgetting_to_clock_back_in_after_lunch() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(OrchestratorConfig {
+        office: Some(OfficeSchedule::parse("5", "13:00", "17:00")),
+        ..config()
+    });
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(9, 0)), &web_requests);
+    orchestrator.handle(GeneralEvents::OfficeStatusUpdated(true), &web_requests);
+    assert_eq!(
+        orchestrator.state.office_badge,
+        Some(OfficeBadge::ClockedIn)
+    );
+
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(12, 0)), &web_requests);
+    orchestrator.handle(GeneralEvents::OfficeStatusUpdated(false), &web_requests);
+    assert_eq!(
+        orchestrator.state.office_badge,
+        Some(OfficeBadge::ClockedOut)
+    );
+
+    //Past the end of lunch and still out
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(13, 30)), &web_requests);
+    assert_eq!(
+        orchestrator.state.office_badge,
+        Some(OfficeBadge::ForgotToClockIn)
+    );
+}
+
+#[test]
+fn new_day_resets_clocked_in_today() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(at(23, 59)), &web_requests);
+    orchestrator.handle(GeneralEvents::OfficeStatusUpdated(true), &web_requests);
+    assert!(orchestrator.state.clocked_in_today);
+
+    let mut tomorrow = at(0, 0);
+    tomorrow.day += 1;
+    orchestrator.handle(GeneralEvents::TimeDigitChanged(tomorrow), &web_requests);
+    assert!(!orchestrator.state.clocked_in_today);
+}
+
+#[test]
+fn notification_pages_wrap() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(config());
+    let mut notifications = BlueSkyNotificationData {
+        unread_notifications: 3,
+        notifications: heapless::Vec::new(),
+    };
+    for author in ["Sam", "Alex", "Jo"] {
+        let _ = notifications.notifications.push(NotificationEntry {
+            author: String::try_from(author).unwrap(),
+            reason: NotificationReason::Like,
+            is_read: false,
+            indexed_at: None,
+        });
+    }
+    orchestrator.handle(
+        GeneralEvents::BlueSkyNotificationUpdate(notifications),
+        &web_requests,
+    );
+
+    //Three notifications is two pages
+    orchestrator.handle(GeneralEvents::NotificationPageTurned, &web_requests);
+    assert_eq!(orchestrator.state.notification_page, 1);
+    orchestrator.handle(GeneralEvents::NotificationPageTurned, &web_requests);
+    assert_eq!(orchestrator.state.notification_page, 0);
+
+    orchestrator.handle(GeneralEvents::BlueSkyNotificationsSeen, &web_requests);
+    let notifications = &orchestrator
+        .state
+        .blue_sky_notification_data
+        .as_ref()
+        .unwrap();
+    assert_eq!(notifications.value.unread_notifications, 0);
+    assert!(notifications.value.notifications.iter().all(|n| n.is_read));
+}
+
+#[test]
+fn feed_takes_turns_with_the_dashboard() {
+    let web_requests = WebRequestChannel::new();
+    let mut orchestrator = Orchestrator::new(OrchestratorConfig {
+        feed: FeedConfig::parse("timeline", "2", "2"),
+        ..config()
+    });
+    let mut posts = heapless::Vec::new();
+    for text in ["First", "Second"] {
+        let _ = posts.push(FeedPost {
+            author: String::try_from("Sam").unwrap(),
+            handle: String::try_from("sam.bsky.social").unwrap(),
+            text: String::try_from(text).unwrap(),
+            created_at: None,
+        });
+    }
+    orchestrator.handle(GeneralEvents::FeedUpdated(posts), &web_requests);
+
+    let mut showing = Vec::new();
+    for minute in 0..6 {
+        orchestrator.handle(
+            GeneralEvents::TimeDigitChanged(at(10, minute)),
+            &web_requests,
+        );
+        let rotation = orchestrator.state.feed_rotation;
+        showing.push((rotation.showing_feed, rotation.post_index));
+    }
+    assert_eq!(
+        showing,
+        [
+            (false, 0),
+            (true, 0),
+            (true, 1),
+            (false, 0),
+            (false, 0),
+            (true, 0)
+        ]
+    );
+}
@@ -0,0 +1,273 @@
+//! The task loops with mock hardware, on embassy's std executor. The loops never return so each test races one
+//! against its checks
+
+mod common;
+
+use common::{
+    at, block_on, leak, MockClock, MockDisplaySink, MockError, MockNetworkClient, MockSensor,
+};
+use desk_buddy_core::events::{
+    EventChannel, GeneralEvents, StateChanges, StateChannel, WebRequestChannel, WebRequestEvents,
+};
+use desk_buddy_core::retry::{CircuitState, RetryPolicy};
+use desk_buddy_core::screen::{run_display, DisplayConfig, Screens};
+use desk_buddy_core::state::State;
+use desk_buddy_core::tasks::{run_clock, run_sensor, run_web_requests};
+use desk_buddy_ui::annual::AnnualEvents;
+use desk_buddy_ui::display::ForecastConfig;
+use embassy_futures::select::{select, Either};
+use embassy_time::Duration;
+use epd_waveshare::epd4in2_v2::Display4in2;
+use heapless::String;
+
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Quick retries and a circuit that opens after two failures
+fn policy(_request: &WebRequestEvents) -> RetryPolicy {
+    RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(4),
+        timeout: Duration::from_secs(1),
+        failures_to_open: 2,
+        open_for: Duration::from_secs(60),
+    }
+}
+
+fn retryable() -> Result<Option<GeneralEvents>, MockError> {
+    Err(MockError { retryable: true })
+}
+
+fn not_retryable() -> Result<Option<GeneralEvents>, MockError> {
+    Err(MockError { retryable: false })
+}
+
+#[test]
+fn clock_is_set_from_the_api_then_ticks() {
+    block_on(
+        async {
+            let states = leak(StateChannel::new());
+            let events = leak(EventChannel::new());
+            let mut clock = MockClock::new([at(12, 0), at(12, 0), at(12, 1)]);
+
+            let checks = async {
+                //Anything before the time comes in is ignored
+                states.send(State::new()).await;
+                let mut state = State::new();
+                state.date_time_from_api = Some(at(12, 0));
+                state.state_change = StateChanges::TimeSet;
+                states.send(state).await;
+
+                let mut minutes = Vec::new();
+                while minutes.last() != Some(&1) {
+                    match events.receive().await {
+                        GeneralEvents::TimeDigitChanged(time) => minutes.push(time.minute),
+                        other => panic!("Unexpected event {:?}", other.as_str()),
+                    }
+                }
+            };
+            if let Either::First(_) = select(run_clock(&mut clock, states, events), checks).await {
+                unreachable!()
+            }
+            assert_eq!(clock.set_to, Some(at(12, 0)));
+        },
+        TIMEOUT,
+    );
+}
+
+#[test]
+fn sensor_keeps_trying_to_start() {
+    block_on(
+        async {
+            let events = leak(EventChannel::new());
+            let mut sensor = MockSensor {
+                failed_starts: 2,
+                co2: 600,
+            };
+            let checks = async {
+                for co2 in [601, 602] {
+                    match events.receive().await {
+                        GeneralEvents::SensorUpdate(data) => assert_eq!(data.co2, co2),
+                        other => panic!("Unexpected event {:?}", other.as_str()),
+                    }
+                }
+            };
+            let sensor_loop = run_sensor(&mut sensor, events, Duration::from_millis(5));
+            if let Either::First(_) = select(sensor_loop, checks).await {
+                unreachable!()
+            }
+        },
+        TIMEOUT,
+    );
+}
+
+#[test]
+fn web_requests_are_retried() {
+    block_on(
+        async {
+            let web_requests = leak(WebRequestChannel::new());
+            let events = leak(EventChannel::new());
+            let mut client = MockNetworkClient::new([
+                retryable(),
+                retryable(),
+                Ok(Some(GeneralEvents::OfficeStatusUpdated(true))),
+            ]);
+
+            let checks = async {
+                web_requests
+                    .send(WebRequestEvents::UpdateOfficeStatus)
+                    .await;
+                let event = events.receive().await;
+                assert!(matches!(event, GeneralEvents::OfficeStatusUpdated(true)));
+            };
+            let web_loop = run_web_requests(&mut client, web_requests, events, policy, || 7);
+            if let Either::First(_) = select(web_loop, checks).await {
+                unreachable!()
+            }
+            assert_eq!(client.requests.len(), 3);
+        },
+        TIMEOUT,
+    );
+}
+
+#[test]
+fn web_requests_that_can_not_work_are_not_retried() {
+    block_on(
+        async {
+            let web_requests = leak(WebRequestChannel::new());
+            let events = leak(EventChannel::new());
+            let mut client = MockNetworkClient::new([
+                not_retryable(),
+                Ok(Some(GeneralEvents::OfficeStatusUpdated(false))),
+            ]);
+
+            let checks = async {
+                web_requests
+                    .send(WebRequestEvents::UpdateOfficeStatus)
+                    .await;
+                web_requests
+                    .send(WebRequestEvents::UpdateOfficeStatus)
+                    .await;
+                let event = events.receive().await;
+                assert!(matches!(event, GeneralEvents::OfficeStatusUpdated(false)));
+            };
+            let web_loop = run_web_requests(&mut client, web_requests, events, policy, || 7);
+            if let Either::First(_) = select(web_loop, checks).await {
+                unreachable!()
+            }
+            assert_eq!(client.requests.len(), 2);
+        },
+        TIMEOUT,
+    );
+}
+
+#[test]
+fn failing_service_is_skipped_once_the_circuit_opens() {
+    block_on(
+        async {
+            let web_requests = leak(WebRequestChannel::new());
+            let events = leak(EventChannel::new());
+            let mut client = MockNetworkClient::new([
+                not_retryable(),
+                not_retryable(),
+                Ok(Some(GeneralEvents::OfficeStatusUpdated(true))),
+            ]);
+
+            let checks = async {
+                web_requests.send(WebRequestEvents::UpdateForecast).await;
+                web_requests.send(WebRequestEvents::UpdateForecast).await;
+                match events.receive().await {
+                    GeneralEvents::WebCircuitChanged(name, state) => {
+                        assert_eq!(name, "Forecast");
+                        assert_eq!(state, CircuitState::Open);
+                    }
+                    other => panic!("Unexpected event {:?}", other.as_str()),
+                }
+                //Skipped without a fetch, the other services still go through
+                web_requests.send(WebRequestEvents::UpdateForecast).await;
+                web_requests
+                    .send(WebRequestEvents::UpdateOfficeStatus)
+                    .await;
+                let event = events.receive().await;
+                assert!(matches!(event, GeneralEvents::OfficeStatusUpdated(true)));
+            };
+            let web_loop = run_web_requests(&mut client, web_requests, events, policy, || 7);
+            if let Either::First(_) = select(web_loop, checks).await {
+                unreachable!()
+            }
+            assert_eq!(
+                client.requests,
+                [
+                    WebRequestEvents::UpdateForecast,
+                    WebRequestEvents::UpdateForecast,
+                    WebRequestEvents::UpdateOfficeStatus
+                ]
+            );
+        },
+        TIMEOUT,
+    );
+}
+
+fn display_config() -> DisplayConfig {
+    DisplayConfig {
+        forecast: ForecastConfig::parse("5", "boxes"),
+        annual_events: AnnualEvents::parse("", "", 3),
+        latitude: 51.5,
+        longitude: -0.1,
+        fahrenheit: false,
+    }
+}
+
+#[test]
+fn display_puts_up_a_frame_each_minute() {
+    block_on(
+        async {
+            let states = leak(StateChannel::new());
+            let mut feed_display = Display4in2::default();
+            let mut sink = MockDisplaySink::default();
+
+            let checks = async {
+                for minute in 0..2 {
+                    let mut state = State::new();
+                    state.approximately_current_time = Some(at(12, minute));
+                    state.state_change = StateChanges::NewTimeDigit;
+                    states.send(state).await;
+                }
+                //Only a new state after the last one was taken means the last one was drawn
+                states.send(State::new()).await;
+            };
+            let display_loop = run_display(display_config(), &mut feed_display, states, &mut sink);
+            if let Either::First(_) = select(display_loop, checks).await {
+                unreachable!()
+            }
+            //The blank frame it starts with and one a minute
+            assert_eq!(sink.frames.len(), 3);
+            assert_ne!(sink.frames[1], sink.frames[2]);
+        },
+        TIMEOUT,
+    );
+}
+
+#[test]
+fn message_goes_over_the_dashboard() {
+    let mut feed_display = Display4in2::default();
+    let mut screens = Screens::new(display_config(), &mut feed_display);
+    let mut sink = MockDisplaySink::default();
+
+    let mut state = State::new();
+    state.approximately_current_time = Some(at(12, 0));
+    state.state_change = StateChanges::NewTimeDigit;
+    screens.update(&state, &mut sink);
+    assert_eq!(sink.frames.last().unwrap(), screens.display.buffer());
+
+    state.message = Some((String::try_from("Lunch?").unwrap(), 5));
+    state.state_change = StateChanges::ScreenChanged;
+    screens.update(&state, &mut sink);
+    assert_eq!(sink.frames.len(), 2);
+    assert_eq!(sink.frames.last().unwrap(), screens.feed_display.buffer());
+
+    //Sensor readings wait for the next minute
+    state.state_change = StateChanges::SensorUpdate;
+    screens.update(&state, &mut sink);
+    assert_eq!(sink.frames.len(), 2);
+}
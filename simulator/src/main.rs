@@ -0,0 +1,298 @@
+//! Draws a screen the same way the buddy does from the JSON in fixtures/ and saves it as a PNG,
+//! so layouts can be worked on without the Pico or the panel.
+//!
+//!     cargo run -- --screen dashboard --out screen.png
+//!
+//! Only screen.json is needed. Leave out any of the others to see the screen before that data comes in
+
+use desk_buddy_ui::annual::AnnualEvents;
+use desk_buddy_ui::astronomy;
+use desk_buddy_ui::date::{parse_date_time, utc_minutes, DateTime};
+use desk_buddy_ui::display::{
+    draw_air_quality, draw_badge_area, draw_blue_sky_notification, draw_calendar,
+    draw_current_outside_weather, draw_feed_post, draw_forecast, draw_message, draw_moon_phase,
+    draw_scd_data, draw_stale_data, draw_sun_times, draw_time, BlueSkyNotificationData,
+    CalendarEvent, FeedPost, ForecastConfig, InsideSensorData, NotificationEntry,
+    NotificationReason, OfficeBadge, MAX_NOTIFICATIONS,
+};
+use desk_buddy_ui::layout;
+use desk_buddy_ui::weather::{AirQualityResponse, ForecastResponse};
+use embedded_graphics::prelude::*;
+use epd_waveshare::{color::Color, epd4in2_v2::Display4in2};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str =
+    "Usage: desk_buddy_simulator [--fixtures <dir>] [--out <png>] [--screen dashboard|feed|message]";
+
+/// Everything that does not come from a web service. Times are local like the RTC keeps
+#[derive(Deserialize)]
+struct Screen {
+    /// Like 2025-01-14T18:42
+    time: heapless::String<16>,
+    #[serde(default)]
+    utc_offset_seconds: i64,
+    #[serde(default)]
+    latitude: f64,
+    #[serde(default)]
+    longitude: f64,
+    #[serde(default)]
+    fahrenheit: bool,
+    /// FORECAST_DAYS and FORECAST_LAYOUT like in the .env
+    #[serde(default)]
+    forecast_days: String,
+    #[serde(default)]
+    forecast_layout: String,
+    office_badge: Option<OfficeBadge>,
+    /// BIRTHDAYS and ANNIVERSARIES like in the .env
+    #[serde(default)]
+    birthdays: String,
+    #[serde(default)]
+    anniversaries: String,
+    annual_reminder_days: Option<u16>,
+    #[serde(default)]
+    notification_page: usize,
+    /// What is stale and how many minutes old it is, like ["Air", 75]. Shown instead of the calendar
+    #[serde(default)]
+    stale: Vec<(String, u64)>,
+    #[serde(default)]
+    offline: Vec<String>,
+    /// For --screen message
+    #[serde(default)]
+    message: String,
+}
+
+/// notifications.json
+#[derive(Deserialize)]
+struct Notifications {
+    unread_notifications: i32,
+    notifications: Vec<Notification>,
+}
+
+#[derive(Deserialize)]
+struct Notification {
+    author: heapless::String<64>,
+    reason: NotificationReason,
+    #[serde(default)]
+    is_read: bool,
+    minutes_ago: Option<f64>,
+}
+
+/// One of feed.json
+#[derive(Deserialize)]
+struct Post {
+    author: heapless::String<48>,
+    handle: heapless::String<48>,
+    text: heapless::String<320>,
+    minutes_ago: Option<f64>,
+}
+
+/// One of calendar.json
+#[derive(Deserialize)]
+struct Event {
+    summary: heapless::String<48>,
+    /// Local times like 2025-01-14T19:00. All day events start and end at midnight
+    start: heapless::String<16>,
+    end: heapless::String<16>,
+    #[serde(default)]
+    all_day: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut fixtures = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"));
+    let mut out = PathBuf::from("screen.png");
+    let mut screen_name = String::from("dashboard");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--fixtures", Some(value)) => fixtures = value.into(),
+            ("--out", Some(value)) => out = value.into(),
+            ("--screen", Some(value)) => screen_name = value,
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let screen: Screen =
+        read(&fixtures.join("screen.json"))?.ok_or("screen.json is needed for the time")?;
+    let mut display = Display4in2::default();
+    display.clear(Color::White).ok();
+    match screen_name.as_str() {
+        "dashboard" => draw_dashboard(&fixtures, &screen, &mut display)?,
+        "feed" => draw_feed(&fixtures, &screen, &mut display)?,
+        "message" => draw_message(&screen.message, &mut display),
+        _ => return Err(USAGE.into()),
+    }
+
+    save_png(&out, display.bounding_box().size, display.buffer())?;
+    println!("Wrote {}", out.display());
+    Ok(())
+}
+
+/// Everything in the same places as the display task puts it
+fn draw_dashboard(
+    fixtures: &Path,
+    screen: &Screen,
+    display: &mut Display4in2,
+) -> Result<(), Box<dyn Error>> {
+    let time = local_time(&screen.time)?;
+    let utc_offset_seconds = screen.utc_offset_seconds;
+    let now = astronomy::julian_day(&time, utc_offset_seconds);
+    let annual_events = AnnualEvents::parse(
+        screen.birthdays.clone().leak(),
+        screen.anniversaries.clone().leak(),
+        screen.annual_reminder_days.unwrap_or(3),
+    );
+
+    draw_time(time.clone(), display);
+    draw_badge_area(
+        layout::BADGE,
+        screen.office_badge,
+        &annual_events,
+        Some(&time),
+        display,
+    );
+
+    if let Some(fixture) = read::<Notifications>(&fixtures.join("notifications.json"))? {
+        let notification_data = BlueSkyNotificationData {
+            unread_notifications: fixture.unread_notifications,
+            notifications: fixture
+                .notifications
+                .into_iter()
+                .take(MAX_NOTIFICATIONS)
+                .map(|notification| NotificationEntry {
+                    author: notification.author,
+                    reason: notification.reason,
+                    is_read: notification.is_read,
+                    indexed_at: notification
+                        .minutes_ago
+                        .map(|minutes| now - minutes / 1440.0),
+                })
+                .collect(),
+        };
+        draw_blue_sky_notification(
+            layout::NOTIFICATIONS,
+            &notification_data,
+            screen.notification_page,
+            Some(now),
+            display,
+        );
+    }
+
+    if let Some(sensor_data) = read::<InsideSensorData>(&fixtures.join("sensors.json"))? {
+        draw_scd_data(layout::SENSORS, sensor_data, screen.fahrenheit, display);
+    }
+
+    if let Some(air_quality) = read::<AirQualityResponse>(&fixtures.join("air_quality.json"))? {
+        draw_air_quality(layout::AIR_QUALITY, air_quality.current, display);
+    }
+
+    let sun_times =
+        astronomy::sun_times(&time, screen.latitude, screen.longitude, utc_offset_seconds);
+    match read::<ForecastResponse>(&fixtures.join("forecast.json"))? {
+        Some(forecast) => {
+            draw_forecast(
+                layout::FORECAST,
+                layout::FORECAST_SIZE,
+                &forecast,
+                ForecastConfig::parse(&screen.forecast_days, &screen.forecast_layout),
+                &annual_events,
+                Some(time.clone()),
+                display,
+            );
+            let daytime = sun_times.is_daytime(&time);
+            draw_current_outside_weather(
+                layout::CURRENT_WEATHER,
+                forecast.current.clone(),
+                forecast.current_units.clone(),
+                daytime,
+                display,
+            );
+            let possible_moon =
+                (!daytime).then(|| astronomy::moon_info(&time, forecast.utc_offset_seconds));
+            draw_moon_phase(layout::MOON, possible_moon, screen.latitude < 0.0, display);
+        }
+        None => draw_sun_times(layout::FORECAST, sun_times, display),
+    }
+
+    //Like on the buddy the calendar only gets the spot when nothing is stale or offline
+    if !screen.stale.is_empty() || !screen.offline.is_empty() {
+        let stale: Vec<(&str, u64)> = screen
+            .stale
+            .iter()
+            .map(|(name, minutes)| (name.as_str(), *minutes))
+            .collect();
+        let offline: Vec<&str> = screen.offline.iter().map(String::as_str).collect();
+        draw_stale_data(layout::STATUS, &stale, &offline, display);
+    } else if let Some(fixture) = read::<Vec<Event>>(&fixtures.join("calendar.json"))? {
+        let events = fixture
+            .into_iter()
+            .map(|event| {
+                Ok(CalendarEvent {
+                    summary: event.summary,
+                    start: utc_minutes(&local_time(&event.start)?, utc_offset_seconds),
+                    end: utc_minutes(&local_time(&event.end)?, utc_offset_seconds),
+                    all_day: event.all_day,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        draw_calendar(
+            layout::STATUS,
+            &events,
+            utc_minutes(&time, utc_offset_seconds),
+            utc_offset_seconds / 60,
+            display,
+        );
+    }
+    Ok(())
+}
+
+/// The first post in feed.json
+fn draw_feed(
+    fixtures: &Path,
+    screen: &Screen,
+    display: &mut Display4in2,
+) -> Result<(), Box<dyn Error>> {
+    let posts: Vec<Post> = read(&fixtures.join("feed.json"))?.ok_or("feed.json is missing")?;
+    let post_count = posts.len();
+    let post = posts.into_iter().next().ok_or("feed.json has no posts")?;
+    let now = astronomy::julian_day(&local_time(&screen.time)?, screen.utc_offset_seconds);
+    let post = FeedPost {
+        author: post.author,
+        handle: post.handle,
+        text: post.text,
+        created_at: post.minutes_ago.map(|minutes| now - minutes / 1440.0),
+    };
+    draw_feed_post(&post, 0, post_count, Some(now), display);
+    Ok(())
+}
+
+/// 2025-01-14T18:42
+fn local_time(time: &str) -> Result<DateTime, Box<dyn Error>> {
+    let timestamp = parse_date_time(time).map_err(|e| format!("{}: {:?}", time, e))?;
+    Ok(timestamp.date_time)
+}
+
+/// None if the fixture is not there
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Box<dyn Error>> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// The frame is already 1 bit a pixel a row at a time with 1 as white, so it goes in as it is
+fn save_png(path: &Path, size: Size, frame: &[u8]) -> Result<(), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, size.width, size.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    encoder.write_header()?.write_image_data(frame)?;
+    Ok(())
+}
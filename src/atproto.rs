@@ -0,0 +1,246 @@
+use crate::env::env_value_or;
+use crate::http_service::HostSession;
+use crate::storage::{Storage, StorageSlot};
+use crate::web_requests::{CreateSessionRequest, SessionResponse, WebCallError, WebRequest};
+use core::fmt::Write;
+use defmt::*;
+use embassy_time::{Duration, Instant};
+use heapless::String;
+use serde::{Deserialize, Serialize};
+
+/// Longest JWT we keep. Bluesky's are around 300 bytes
+const JWT_LENGTH: usize = 1_024;
+/// Scratch space for the session as JSON when it goes to and from flash
+const SESSION_JSON_SIZE: usize = 2_304;
+/// Bluesky access tokens last 2 hours, refresh a bit before then
+const ACCESS_TOKEN_LIFETIME: Duration = Duration::from_secs(100 * 60);
+/// How long to leave the PDS alone after a 429
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// What is kept in flash so a reboot does not need a new login
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSession {
+    /// The HANDLE it was made for, so changing account in the .env logs in again
+    identifier: String<64>,
+    did: String<64>,
+    access_jwt: String<JWT_LENGTH>,
+    refresh_jwt: String<JWT_LENGTH>,
+}
+
+/// Just enough AT Protocol to log in and stay logged in. The session is saved to flash and refreshed
+/// with com.atproto.server.refreshSession, so the app password is only sent when the refresh token stops working
+pub struct AtprotoClient {
+    identifier: &'static str,
+    app_password: &'static str,
+    session: Option<StoredSession>,
+    /// "Bearer <access jwt>" ready for the Authorization header
+    authorization: String<{ JWT_LENGTH + 7 }>,
+    /// None when the token came from flash since we do not know how old it is
+    access_issued_at: Option<Instant>,
+    rate_limited_until: Option<Instant>,
+}
+
+impl AtprotoClient {
+    /// Picks up the session saved by the last boot if it is for the same account
+    pub fn new(storage: &mut Storage) -> Self {
+        let identifier = env_value_or("HANDLE", "");
+        let mut buffer = [0u8; SESSION_JSON_SIZE];
+        let session = storage
+            .load::<StoredSession>(StorageSlot::BlueSkySession, &mut buffer)
+            .filter(|session| session.identifier == identifier);
+        if session.is_some() {
+            info!("Loaded the Bluesky session from flash");
+        }
+
+        let mut client = Self {
+            identifier,
+            app_password: app_password(),
+            session,
+            authorization: String::new(),
+            access_issued_at: None,
+            rate_limited_until: None,
+        };
+        client.update_authorization();
+        client
+    }
+
+    /// The Authorization header for requests that need to be logged in. Call authorize first
+    pub fn authorization(&self) -> &str {
+        &self.authorization
+    }
+
+    /// Makes sure there is an access token that should work. Refreshes it if it is old or unknown,
+    /// and only logs in with the app password if there is no session or the refresh token was rejected
+    pub async fn authorize(
+        &mut self,
+        host_session: &mut HostSession<'_>,
+        storage: &mut Storage,
+        timeout: Duration,
+    ) -> Result<(), WebCallError> {
+        if let Some(until) = self.rate_limited_until {
+            if Instant::now() < until {
+                warn!(
+                    "Still rate limited by the PDS for {}s",
+                    (until - Instant::now()).as_secs()
+                );
+                return Err(WebCallError::RateLimited);
+            }
+            self.rate_limited_until = None;
+        }
+
+        let access_is_fresh = self
+            .access_issued_at
+            .is_some_and(|issued_at| issued_at.elapsed() < ACCESS_TOKEN_LIFETIME);
+        if self.session.is_some() && access_is_fresh {
+            return Ok(());
+        }
+
+        if self.session.is_some() {
+            match self.refresh_session(host_session, timeout).await {
+                Ok(()) => {
+                    self.save(storage);
+                    return Ok(());
+                }
+                Err(e) if is_auth_error(&e) => {
+                    warn!("The refresh token was rejected, logging in again");
+                    self.session = None;
+                }
+                Err(e) => return Err(self.check_error(e)),
+            }
+        }
+
+        if let Err(e) = self.create_session(host_session, timeout).await {
+            //Only a 401 means the handle or password is wrong, a 400 is a bad request and says nothing about the session
+            if e == WebCallError::HttpError(401) {
+                error!("Bluesky login failed, check HANDLE and APP_PASSWORD in the .env");
+                let _ = storage.clear(StorageSlot::BlueSkySession);
+            }
+            return Err(self.check_error(e));
+        }
+        self.save(storage);
+        Ok(())
+    }
+
+    /// Call when a logged in request comes back unauthorized, so the next authorize gets a new access token
+    pub fn access_token_rejected(&mut self) {
+        self.access_issued_at = None;
+    }
+
+    /// Starts the rate limit wait on a 429. Pass every error from a logged in request through this
+    pub fn check_error(&mut self, e: WebCallError) -> WebCallError {
+        if e == WebCallError::HttpError(429) {
+            warn!(
+                "Rate limited by the PDS, waiting {} minutes",
+                RATE_LIMIT_BACKOFF.as_secs() / 60
+            );
+            self.rate_limited_until = Some(Instant::now() + RATE_LIMIT_BACKOFF);
+            return WebCallError::RateLimited;
+        }
+        e
+    }
+
+    async fn create_session(
+        &mut self,
+        host_session: &mut HostSession<'_>,
+        timeout: Duration,
+    ) -> Result<(), WebCallError> {
+        info!("Logging in to Bluesky");
+        let body = CreateSessionRequest {
+            identifier: self.identifier,
+            password: self.app_password,
+        };
+        let request = WebRequest::post("/xrpc/com.atproto.server.createSession")
+            .json(&body)
+            .timeout(timeout);
+        let response = host_session.send::<_, SessionResponse>(request).await?;
+        self.session = Some(stored_session(self.identifier, &response)?);
+        self.access_issued_at = Some(Instant::now());
+        self.update_authorization();
+        Ok(())
+    }
+
+    async fn refresh_session(
+        &mut self,
+        host_session: &mut HostSession<'_>,
+        timeout: Duration,
+    ) -> Result<(), WebCallError> {
+        let Some(session) = &self.session else {
+            return Err(WebCallError::HttpError(401));
+        };
+        info!("Refreshing the Bluesky session");
+        let mut refresh_authorization: String<{ JWT_LENGTH + 7 }> = String::new();
+        let _ = core::write!(refresh_authorization, "Bearer {}", session.refresh_jwt);
+
+        let request = WebRequest::post("/xrpc/com.atproto.server.refreshSession")
+            .header("Authorization", &refresh_authorization)
+            .timeout(timeout);
+        let response = host_session.send::<_, SessionResponse>(request).await?;
+        self.session = Some(stored_session(self.identifier, &response)?);
+        self.access_issued_at = Some(Instant::now());
+        self.update_authorization();
+        Ok(())
+    }
+
+    fn update_authorization(&mut self) {
+        self.authorization.clear();
+        if let Some(session) = &self.session {
+            let _ = core::write!(self.authorization, "Bearer {}", session.access_jwt);
+        }
+    }
+
+    /// Losing this is not the end of the world, it just means logging in again after a reboot
+    fn save(&self, storage: &mut Storage) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let mut buffer = [0u8; SESSION_JSON_SIZE];
+        if let Err(e) = storage.save(StorageSlot::BlueSkySession, session, &mut buffer) {
+            error!("Failed to save the Bluesky session: {:?}", e);
+        }
+    }
+}
+
+/// Expired or bad tokens. Other 400s, like a bad request, are not
+pub fn is_auth_error(e: &WebCallError) -> bool {
+    matches!(
+        e,
+        WebCallError::TokenRejected | WebCallError::HttpError(401)
+    )
+}
+
+fn stored_session(
+    identifier: &str,
+    response: &SessionResponse,
+) -> Result<StoredSession, WebCallError> {
+    let too_long = |_| {
+        error!("Session from the PDS is too long to keep");
+        WebCallError::FailedToReadResponse
+    };
+    Ok(StoredSession {
+        identifier: String::try_from(identifier).map_err(too_long)?,
+        did: String::try_from(response.did).map_err(too_long)?,
+        access_jwt: String::try_from(response.access_jwt).map_err(too_long)?,
+        refresh_jwt: String::try_from(response.refresh_jwt).map_err(too_long)?,
+    })
+}
+
+/// App passwords look like xxxx-xxxx-xxxx-xxxx and can be revoked on their own, so we ask for one instead of the
+/// account password. Falls back to the old PASSWORD key so older .env files keep working
+fn app_password() -> &'static str {
+    let password = match env_value_or("APP_PASSWORD", "") {
+        "" => {
+            warn!("APP_PASSWORD is not set, using PASSWORD");
+            env_value_or("PASSWORD", "")
+        }
+        password => password,
+    };
+    let looks_like_app_password = password.len() == 19
+        && password.split('-').count() == 4
+        && password
+            .split('-')
+            .all(|group| group.len() == 4 && group.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !looks_like_app_password {
+        warn!("The Bluesky password does not look like an app password. Make one in Settings > Privacy and security > App passwords");
+    }
+    password
+}
@@ -0,0 +1,94 @@
+use crate::env::env_value_or;
+use defmt::*;
+use desk_buddy_ui::date::{utc_minutes, DateTime};
+use embassy_time::Instant;
+use heapless::String;
+
+/// Where the calendar comes from. Any url that answers a GET with an iCalendar file works, like the secret ics link
+/// Google and Outlook give you or a CalDAV calendar collection (Radicale, or Nextcloud with ?export on the end)
+#[derive(Debug, Clone, Copy, Format)]
+pub struct CalendarConfig {
+    pub url: &'static str,
+    /// Sent with basic auth if set, CalDAV servers usually want it
+    pub user: &'static str,
+    pub password: &'static str,
+}
+
+impl CalendarConfig {
+    /// None if CALENDAR_URL is not set
+    pub fn from_env() -> Option<Self> {
+        let url = env_value_or("CALENDAR_URL", "");
+        if url.is_empty() {
+            return None;
+        }
+        Some(Self {
+            url,
+            user: env_value_or("CALENDAR_USER", ""),
+            password: env_value_or("CALENDAR_PASSWORD", ""),
+        })
+    }
+
+    /// Basic auth header value. None if there is no user set or it does not fit
+    pub fn authorization(&self) -> Option<String<256>> {
+        if self.user.is_empty() {
+            return None;
+        }
+        let mut credentials: String<128> = String::new();
+        if credentials.push_str(self.user).is_err()
+            || credentials.push(':').is_err()
+            || credentials.push_str(self.password).is_err()
+        {
+            error!("CALENDAR_USER and CALENDAR_PASSWORD are too long");
+            return None;
+        }
+        let mut authorization: String<256> = String::new();
+        authorization.push_str("Basic ").ok()?;
+        push_base64(&mut authorization, credentials.as_bytes()).ok()?;
+        Some(authorization)
+    }
+}
+
+/// The time for the wireless task, which does not have the RTC. Set from the time API and moved along with Instant
+#[derive(Debug, Clone, Copy)]
+pub struct WallClock {
+    utc_minutes: i64,
+    utc_offset_minutes: i64,
+    set_at: Instant,
+}
+
+impl WallClock {
+    pub fn new(local_time: &DateTime, utc_offset_seconds: i64) -> Self {
+        Self {
+            utc_minutes: utc_minutes(local_time, utc_offset_seconds),
+            utc_offset_minutes: utc_offset_seconds / 60,
+            set_at: Instant::now(),
+        }
+    }
+
+    /// Minutes since 1970 in UTC
+    pub fn now(&self) -> i64 {
+        self.utc_minutes + (self.set_at.elapsed().as_secs() / 60) as i64
+    }
+
+    pub fn utc_offset_minutes(&self) -> i64 {
+        self.utc_offset_minutes
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn push_base64<const N: usize>(encoded: &mut String<N>, bytes: &[u8]) -> Result<(), ()> {
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | ((byte as u32) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            let character = match i <= chunk.len() {
+                true => BASE64[((group >> (18 - 6 * i)) & 0x3f) as usize] as char,
+                false => '=',
+            };
+            encoded.push(character)?;
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,28 @@
-use heapless::Vec;
-
 const _ENV_DATA: &str = include_str!("../.env");
 
 pub fn env_value(key: &str) -> &'static str {
+    match find_env_value(key) {
+        Some(value) => value,
+        None => panic!("Key: {:?} not found in .env file. May also need to provide your own .env from a copy of .env.save", key),
+    }
+}
+
+/// Same as env_value but for optional settings so older .env files keep working
+pub fn env_value_or(key: &str, default: &'static str) -> &'static str {
+    find_env_value(key).unwrap_or(default)
+}
+
+fn find_env_value(key: &str) -> Option<&'static str> {
     for line in _ENV_DATA.lines() {
-        let parts: Vec<&str, 2> = line.split('=').collect();
-        if parts.len() == 2 {
-            if parts[0].trim() == key {
-                let mut value = parts[1].trim().chars();
+        //Only the first = splits, so values like base64 keys can have their own
+        if let Some((name, value)) = line.split_once('=') {
+            if name.trim() == key {
+                let mut value = value.trim().chars();
                 value.next();
                 value.next_back();
-                return value.as_str();
+                return Some(value.as_str());
             }
         }
     }
-    panic!("Key: {:?} not found in .env file. May also need to provide your own .env from a copy of .env.save", key);
+    None
 }
@@ -0,0 +1,438 @@
+use crate::tls::{host_from_url, HostTrust, TrustStore};
+use crate::web_requests::{
+    parse_json, send_web_request, send_web_request_discarding_body, send_web_request_raw,
+    split_url, stream_lines_web_request, stream_web_request, tls_error, WebCallError, WebRequest,
+    DEFAULT_TIMEOUT,
+};
+use core::net::SocketAddr;
+use defmt::*;
+use embassy_net::dns::DnsSocket;
+use embassy_net::tcp::client::{TcpClient, TcpClientState, TcpConnection};
+use embassy_net::Stack;
+use embassy_rp::clocks::RoscRng;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{with_timeout, Duration, Instant};
+use embedded_nal_async::{AddrType, Dns, TcpConnect};
+use embedded_tls::{Aes128GcmSha256, NoVerify, TlsConnection, TlsContext};
+use heapless::String;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use reqwless::client::{HttpConnection, HttpResource, TlsVerify};
+use reqwless::request::RequestBody;
+use serde::de::DeserializeOwned;
+use static_cell::{ConstStaticCell, StaticCell};
+
+/// TLS records can be up to 16KB so the TLS buffers need to be at least that
+const TLS_BUFFER_SIZE: usize = 16_640;
+/// Biggest response body we can take. The forecast is the largest at the moment
+const RX_BUFFER_SIZE: usize = 8_320;
+/// Room for the response headers when streaming. Feed posts with embeds are big so they get the rest
+const STREAM_HEADER_SIZE: usize = 2_048;
+/// Longest scheme, host and port we keep a connection to
+const BASE_URL_LENGTH: usize = 128;
+/// A kept alive connection that has not been used for this long is closed instead of reused. Servers drop idle
+/// connections after a minute or so, and the Bluesky events that come one after the other are seconds apart
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+//The buffer pool. These used to be made on the stack for every web request.
+//ConstStaticCell so they are not built on the stack first either
+static TLS_READ_BUFFER: ConstStaticCell<[u8; TLS_BUFFER_SIZE]> =
+    ConstStaticCell::new([0; TLS_BUFFER_SIZE]);
+static TLS_WRITE_BUFFER: ConstStaticCell<[u8; TLS_BUFFER_SIZE]> =
+    ConstStaticCell::new([0; TLS_BUFFER_SIZE]);
+static RX_BUFFER: ConstStaticCell<[u8; RX_BUFFER_SIZE]> = ConstStaticCell::new([0; RX_BUFFER_SIZE]);
+
+static TCP_CLIENT_STATE: StaticCell<TcpClientState<4, 1024, 1024>> = StaticCell::new();
+static TCP_CLIENT: StaticCell<TcpClient<'static, 4>> = StaticCell::new();
+static DNS_SOCKET: StaticCell<DnsSocket<'static>> = StaticCell::new();
+static HTTP_SERVICE: StaticCell<SharedHttpService> = StaticCell::new();
+
+type Socket = TcpConnection<'static, 4, 1024, 1024>;
+
+/// The HttpService for any task that needs to make a web request. Lock it for as long as a group of calls takes.
+/// Every task runs on the one executor so it does not need a critical section, like the I2C bus
+pub type SharedHttpService = Mutex<NoopRawMutex, HttpService>;
+
+/// Long lived HTTP service. Made once by the wireless_task when the network is up and owns all the
+/// buffers for the web requests. The wireless_task makes the requests from the WEB_REQUEST_EVENT_CHANNEL
+/// with it, and other tasks can lock it to make their own.
+///
+/// Keeps the last connection open between calls, so events for the same host like checking Bluesky notifications
+/// and then the feed only do the TLS handshake once. There is only room for one pair of TLS buffers,
+/// so going to another host closes it first.
+pub struct HttpService {
+    tcp_client: &'static TcpClient<'static, 4>,
+    dns_client: &'static DnsSocket<'static>,
+    /// Lent to the kept alive connection, see tls_buffers
+    tls_read_buffer: *mut [u8; TLS_BUFFER_SIZE],
+    tls_write_buffer: *mut [u8; TLS_BUFFER_SIZE],
+    rx_buffer: &'static mut [u8; RX_BUFFER_SIZE],
+    trust_store: TrustStore,
+    rng: RoscRng,
+    /// None when there is no connection open
+    connection: Option<Connection>,
+}
+
+/// The connection kept open between calls
+struct Connection {
+    /// Scheme, host and port like https://bsky.social, so the next call can tell if it can use this
+    base_url: String<BASE_URL_LENGTH>,
+    resource: HttpResource<'static, Socket>,
+    last_used: Instant,
+}
+
+impl HttpService {
+    /// Can only be called once since it takes the static buffers
+    pub fn init(stack: Stack<'static>) -> &'static SharedHttpService {
+        let client_state = TCP_CLIENT_STATE.init(TcpClientState::new());
+
+        HTTP_SERVICE.init(Mutex::new(Self {
+            tcp_client: TCP_CLIENT.init(TcpClient::new(stack, client_state)),
+            dns_client: DNS_SOCKET.init(DnsSocket::new(stack)),
+            tls_read_buffer: TLS_READ_BUFFER.take(),
+            tls_write_buffer: TLS_WRITE_BUFFER.take(),
+            rx_buffer: RX_BUFFER.take(),
+            trust_store: TrustStore::from_env(),
+            rng: RoscRng,
+            connection: None,
+        }))
+    }
+
+    /// Sends a request to the full url in it. The response borrows the rx buffer so drop it before the next request
+    pub async fn send<'a, B, ResponseType>(
+        &'a mut self,
+        web_request: WebRequest<'_, B>,
+    ) -> Result<ResponseType, WebCallError>
+    where
+        B: RequestBody,
+        ResponseType: serde::Deserialize<'a>,
+    {
+        let body = self.send_raw(web_request).await?;
+        parse_json(body)
+    }
+
+    /// Like send but gives back the body as text without parsing it
+    pub async fn send_raw<'a, B: RequestBody>(
+        &'a mut self,
+        web_request: WebRequest<'_, B>,
+    ) -> Result<&'a str, WebCallError> {
+        let base_url: String<BASE_URL_LENGTH> =
+            String::try_from(split_url(&web_request.head.url).0)
+                .map_err(|_| WebCallError::InvalidUrl)?;
+        self.session(&base_url).await?.send_raw(web_request).await
+    }
+
+    /// A session on the connection to `base_url` (like https://bsky.social) for a group of calls, like the unread count
+    /// and then the notifications. Reuses the kept alive connection if it can, see connect_to
+    pub async fn session(&mut self, base_url: &str) -> Result<HostSession<'_>, WebCallError> {
+        let base_url = String::try_from(base_url).map_err(|_| WebCallError::InvalidUrl)?;
+        self.connect_to(&base_url).await?;
+        Ok(HostSession {
+            service: self,
+            base_url,
+        })
+    }
+
+    /// Makes sure the kept alive connection goes to `base_url`. One to another host, or that has sat idle too long,
+    /// is closed and a new one opened
+    async fn connect_to(&mut self, base_url: &str) -> Result<&mut Self, WebCallError> {
+        let reusable = self.connection.as_ref().is_some_and(|connection| {
+            connection.base_url == base_url && connection.last_used.elapsed() < IDLE_TIMEOUT
+        });
+        if !reusable {
+            //Dropping it closes the socket and gives the TLS buffers back before the new connection takes them
+            self.connection = None;
+            let resource = timed(DEFAULT_TIMEOUT, self.connect(base_url)).await??;
+            self.connection = Some(Connection {
+                base_url: String::try_from(base_url).map_err(|_| WebCallError::InvalidUrl)?,
+                resource,
+                last_used: Instant::now(),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Opens a connection the same way reqwless does, but with the TLS buffers lent for good so it can outlive the call
+    async fn connect(
+        &mut self,
+        base_url: &str,
+    ) -> Result<HttpResource<'static, Socket>, WebCallError> {
+        let https = match base_url.split_once("://") {
+            Some(("https", _)) => true,
+            Some(("http", _)) => false,
+            _ => return Err(WebCallError::InvalidUrl),
+        };
+        let host = host_from_url(base_url);
+        let port = match base_url.rsplit_once(':') {
+            Some((_, port)) if !port.starts_with('/') => {
+                port.parse().map_err(|_| WebCallError::InvalidUrl)?
+            }
+            _ if https => 443,
+            _ => 80,
+        };
+        //Checked before anything is sent, plain http to a host that gets credentials is refused too
+        let verify = tls_verify(&self.trust_store, host)?;
+
+        let address = self
+            .dns_client
+            .get_host_by_name(host, AddrType::Either)
+            .await
+            .map_err(|e| {
+                error!("Could not look up {}: {:?}", host, e);
+                WebCallError::Dns
+            })?;
+        let socket = self
+            .tcp_client
+            .connect(SocketAddr::new(address, port))
+            .await
+            .map_err(|e| {
+                error!("Could not connect to {}: {:?}", host, e);
+                WebCallError::Connect
+            })?;
+        if !https {
+            return Ok(resource(HttpConnection::Plain(socket)));
+        }
+
+        //New seed for every connection so the TLS keys are never reused
+        let mut rng = ChaCha8Rng::seed_from_u64(self.rng.next_u64());
+        let mut config = embedded_tls::TlsConfig::new().with_server_name(host);
+        if let TlsVerify::Psk { identity, psk } = verify {
+            config = config.with_psk(psk, &[identity]);
+        }
+        let (read_buffer, write_buffer) = self.tls_buffers();
+        let mut tls: TlsConnection<'static, Socket, Aes128GcmSha256> =
+            TlsConnection::new(socket, read_buffer, write_buffer);
+        tls.open::<_, NoVerify>(TlsContext::new(&config, &mut rng))
+            .await
+            .map_err(|e| {
+                error!("TLS handshake with {} failed: {:?}", host, e);
+                tls_error(e)
+            })?;
+        Ok(resource(HttpConnection::Tls(tls)))
+    }
+
+    /// Lends the TLS buffers to a new connection for as long as it is kept open
+    fn tls_buffers(&self) -> (&'static mut [u8], &'static mut [u8]) {
+        core::debug_assert!(self.connection.is_none());
+        //SAFETY: the buffers came from ConstStaticCell::take so only this service has them. The one connection that
+        //could still be using them is dropped in session before connect is called, so this is the only borrow
+        unsafe { (&mut *self.tls_read_buffer, &mut *self.tls_write_buffer) }
+    }
+}
+
+/// Requests are always built with the host and full path, see WebRequestHead::build, so these are left empty
+fn resource(conn: HttpConnection<'static, Socket>) -> HttpResource<'static, Socket> {
+    HttpResource {
+        conn,
+        host: "",
+        base_path: "",
+    }
+}
+
+/// PSK is the only verified connection we can make for now. reqwless 0.13 always opens embedded-tls with NoVerify,
+/// and embedded-tls 0.17 keeps the certificate types a verifier would need private. Its own webpki verifier needs ring,
+/// which does not build for the Cortex-M0. reqwless 0.14 can check certificates but needs a newer embassy.
+/// Until then hosts we send credentials to are refused instead of getting them over an unchecked connection
+fn tls_verify<'a>(trust_store: &'a TrustStore, host: &str) -> Result<TlsVerify<'a>, WebCallError> {
+    match trust_store.trust_for_host(host) {
+        HostTrust::Psk { identity, key } => Ok(TlsVerify::Psk { identity, psk: key }),
+        HostTrust::Unverified => Ok(TlsVerify::None),
+        HostTrust::RequiresVerification => {
+            error!(
+                "Refusing {}, it gets credentials over TLS we can not verify",
+                host
+            );
+            Err(WebCallError::TlsNotVerifiable)
+        }
+    }
+}
+
+/// Calls on the HttpService's kept alive connection. Any error closes the connection, since the response
+/// may not have been read to the end, and the next call opens a new one
+pub struct HostSession<'a> {
+    service: &'a mut HttpService,
+    base_url: String<BASE_URL_LENGTH>,
+}
+
+impl<'a> HostSession<'a> {
+    /// Sends a request on the open connection. The request's url can be only the path, like /xrpc/app.bsky.notification.getUnreadCount
+    pub async fn send<'s, B, ResponseType>(
+        &'s mut self,
+        web_request: WebRequest<'_, B>,
+    ) -> Result<ResponseType, WebCallError>
+    where
+        B: RequestBody,
+        ResponseType: serde::Deserialize<'s>,
+    {
+        let timeout = web_request.head.timeout;
+        let HttpService {
+            connection,
+            rx_buffer,
+            ..
+        } = self.service.connect_to(&self.base_url).await?;
+        let current = open(connection)?;
+        let result = timed(
+            timeout,
+            send_web_request(
+                &mut current.resource,
+                host_from_url(&current.base_url),
+                web_request,
+                &mut rx_buffer[..],
+            ),
+        )
+        .await;
+        used(connection, result)
+    }
+
+    /// Like send but gives back the body as text. Takes the session so the text can borrow the rx buffer for as long
+    /// as the HttpService is borrowed
+    pub async fn send_raw<B: RequestBody>(
+        self,
+        web_request: WebRequest<'_, B>,
+    ) -> Result<&'a str, WebCallError> {
+        let timeout = web_request.head.timeout;
+        let HttpService {
+            connection,
+            rx_buffer,
+            ..
+        } = self.service.connect_to(&self.base_url).await?;
+        let current = open(connection)?;
+        let result = timed(
+            timeout,
+            send_web_request_raw(
+                &mut current.resource,
+                host_from_url(&current.base_url),
+                web_request,
+                &mut rx_buffer[..],
+            ),
+        )
+        .await;
+        used(connection, result)
+    }
+
+    /// For calls that only answer with a status, like app.bsky.notification.updateSeen
+    pub async fn send_discarding_body<B: RequestBody>(
+        &mut self,
+        web_request: WebRequest<'_, B>,
+    ) -> Result<(), WebCallError> {
+        let timeout = web_request.head.timeout;
+        let HttpService {
+            connection,
+            rx_buffer,
+            ..
+        } = self.service.connect_to(&self.base_url).await?;
+        let current = open(connection)?;
+        let result = timed(
+            timeout,
+            send_web_request_discarding_body(
+                &mut current.resource,
+                host_from_url(&current.base_url),
+                web_request,
+                &mut rx_buffer[..],
+            ),
+        )
+        .await;
+        used(connection, result)
+    }
+
+    /// Like send, but hands over each element of the array under `array_key` as it is read instead of
+    /// holding the whole response. The start of the rx buffer is for the response headers and the rest for the element
+    pub async fn stream<B, T, F>(
+        &mut self,
+        web_request: WebRequest<'_, B>,
+        array_key: &str,
+        on_element: F,
+    ) -> Result<usize, WebCallError>
+    where
+        B: RequestBody,
+        T: DeserializeOwned,
+        F: FnMut(T) -> bool,
+    {
+        let timeout = web_request.head.timeout;
+        let HttpService {
+            connection,
+            rx_buffer,
+            ..
+        } = self.service.connect_to(&self.base_url).await?;
+        let current = open(connection)?;
+        let (header_buffer, element_buffer) = rx_buffer.split_at_mut(STREAM_HEADER_SIZE);
+        let result = timed(
+            timeout,
+            stream_web_request(
+                &mut current.resource,
+                host_from_url(&current.base_url),
+                web_request,
+                header_buffer,
+                array_key,
+                element_buffer,
+                on_element,
+            ),
+        )
+        .await;
+        used(connection, result)
+    }
+
+    /// Like stream, but for text bodies read a line at a time, like a calendar. Lines get the same room elements do
+    pub async fn stream_lines<B, F>(
+        &mut self,
+        web_request: WebRequest<'_, B>,
+        on_line: F,
+    ) -> Result<usize, WebCallError>
+    where
+        B: RequestBody,
+        F: FnMut(&str) -> bool,
+    {
+        let timeout = web_request.head.timeout;
+        let HttpService {
+            connection,
+            rx_buffer,
+            ..
+        } = self.service.connect_to(&self.base_url).await?;
+        let current = open(connection)?;
+        let (header_buffer, line_buffer) = rx_buffer.split_at_mut(STREAM_HEADER_SIZE);
+        let result = timed(
+            timeout,
+            stream_lines_web_request(
+                &mut current.resource,
+                host_from_url(&current.base_url),
+                web_request,
+                header_buffer,
+                line_buffer,
+                on_line,
+            ),
+        )
+        .await;
+        used(connection, result)
+    }
+}
+
+/// The connection connect_to made sure of
+fn open(connection: &mut Option<Connection>) -> Result<&mut Connection, WebCallError> {
+    connection.as_mut().ok_or(WebCallError::Connect)
+}
+
+/// Closes the connection if the call failed, otherwise starts its idle time again
+fn used<T>(
+    connection: &mut Option<Connection>,
+    result: Result<Result<T, WebCallError>, WebCallError>,
+) -> Result<T, WebCallError> {
+    let result = result.and_then(|result| result);
+    match (&result, connection.as_mut()) {
+        (Ok(_), Some(connection)) => connection.last_used = Instant::now(),
+        (Err(_), _) => *connection = None,
+        _ => {}
+    }
+    result
+}
+
+/// Gives up on `future` after `timeout` so a dead connection can not hang the wireless_task
+async fn timed<F: core::future::Future>(
+    timeout: Duration,
+    future: F,
+) -> Result<F::Output, WebCallError> {
+    with_timeout(timeout, future).await.map_err(|_| {
+        error!("Web request timed out after {}s", timeout.as_secs());
+        WebCallError::Timeout
+    })
+}
@@ -0,0 +1,322 @@
+use crate::env::env_value_or;
+use core::cell::RefCell;
+use core::fmt::Write as _;
+use defmt::*;
+use desk_buddy_core::events::MESSAGE_SIZE;
+use desk_buddy_ui::display::CalendarEvent;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embedded_io_async::{Read, Write};
+use heapless::{String, Vec};
+use serde::Serialize;
+
+pub const JSON: &str = "application/json";
+pub const TEXT: &str = "text/plain";
+
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
+pub enum HttpError {
+    Network,
+    /// The client hung up before sending a whole request
+    Closed,
+    /// Request line, headers or body did not fit in the request buffer
+    TooLarge,
+    BadRequest,
+}
+
+/// Settings for the little HTTP server on the buddy
+#[derive(Debug, Clone, Copy, Format)]
+pub struct ApiConfig {
+    pub port: u16,
+    /// If set every request needs it as a Bearer token
+    pub token: &'static str,
+}
+
+impl ApiConfig {
+    /// None if API_PORT is not set
+    pub fn from_env() -> Option<Self> {
+        let port = env_value_or("API_PORT", "");
+        if port.is_empty() {
+            return None;
+        }
+        let Ok(port) = port.parse() else {
+            error!("API_PORT should be a port number like 80, not {}", port);
+            return None;
+        };
+        let token = env_value_or("API_TOKEN", "");
+        if token.is_empty() {
+            warn!(
+                "API_TOKEN is not set, anyone on the network can use the local API on port {}",
+                port
+            );
+        }
+        Some(Self { port, token })
+    }
+
+    pub fn authorized(&self, authorization: Option<&str>) -> bool {
+        if self.token.is_empty() {
+            return true;
+        }
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), self.token.as_bytes()))
+    }
+}
+
+/// Looks at every byte no matter where the first difference is, so timing the replies does not give the token away.
+/// Only the length can leak
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a
+        .iter()
+        .zip(b)
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+    core::hint::black_box(difference) == 0
+}
+
+/// A request read off the socket. Everything borrows the request buffer
+#[derive(Debug, Format)]
+pub struct Request<'a> {
+    pub method: &'a str,
+    /// Without the query
+    pub path: &'a str,
+    /// What came after the ?, empty if there was none
+    pub query: &'a str,
+    pub authorization: Option<&'a str>,
+    pub body: &'a [u8],
+}
+
+/// Reads the request line, headers and body into `buffer`. Only HTTP/1.x with a Content-Length, no chunked uploads
+pub async fn read_request<'b, R: Read>(
+    reader: &mut R,
+    buffer: &'b mut [u8],
+) -> Result<Request<'b>, HttpError> {
+    let mut length = 0;
+    let head_end = loop {
+        if let Some(position) = buffer[..length]
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+        {
+            break position + 4;
+        }
+        if length == buffer.len() {
+            return Err(HttpError::TooLarge);
+        }
+        let read = reader
+            .read(&mut buffer[length..])
+            .await
+            .map_err(|_| HttpError::Network)?;
+        if read == 0 {
+            return Err(HttpError::Closed);
+        }
+        length += read;
+    };
+
+    //Worked out as positions first so the buffer is free for reading in the rest of the body
+    let (method, path, query, authorization, content_length) = {
+        let head = core::str::from_utf8(&buffer[..head_end]).map_err(|_| HttpError::BadRequest)?;
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or("").split(' ');
+        let (Some(method), Some(target), Some(version)) = (
+            request_line.next(),
+            request_line.next(),
+            request_line.next(),
+        ) else {
+            return Err(HttpError::BadRequest);
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(HttpError::BadRequest);
+        }
+        //The empty query is cut from the end of the target so it is still part of the head
+        let (path, query) = target
+            .split_once('?')
+            .unwrap_or((target, &target[target.len()..]));
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().map_err(|_| HttpError::BadRequest)?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value);
+            }
+        }
+        let span = |part: &str| {
+            let start = part.as_ptr() as usize - head.as_ptr() as usize;
+            start..start + part.len()
+        };
+        (
+            span(method),
+            span(path),
+            span(query),
+            authorization.map(span),
+            content_length,
+        )
+    };
+
+    let body_end = match head_end.checked_add(content_length) {
+        Some(body_end) if body_end <= buffer.len() => body_end,
+        _ => return Err(HttpError::TooLarge),
+    };
+    while length < body_end {
+        let read = reader
+            .read(&mut buffer[length..body_end])
+            .await
+            .map_err(|_| HttpError::Network)?;
+        if read == 0 {
+            return Err(HttpError::Closed);
+        }
+        length += read;
+    }
+
+    let head = core::str::from_utf8(&buffer[..head_end]).map_err(|_| HttpError::BadRequest)?;
+    Ok(Request {
+        method: &head[method],
+        path: &head[path],
+        query: &head[query],
+        authorization: authorization.map(|authorization| &head[authorization]),
+        body: &buffer[head_end..body_end],
+    })
+}
+
+/// The value of `key` in a query like format=bmp&x=1. No percent decoding, the keys and values we take do not need it
+pub fn query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// Status line and headers. The connection is closed after every response
+pub async fn write_head<W: Write>(
+    writer: &mut W,
+    status: u16,
+    content_type: &str,
+    content_length: usize,
+) -> Result<(), HttpError> {
+    let mut head: String<160> = String::new();
+    let _ = core::write!(
+        head,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        status_text(status),
+        content_type,
+        content_length
+    );
+    write_body(writer, head.as_bytes()).await
+}
+
+/// More of a body after write_head
+pub async fn write_body<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), HttpError> {
+    writer
+        .write_all(bytes)
+        .await
+        .map_err(|_| HttpError::Network)
+}
+
+pub async fn write_response<W: Write>(
+    writer: &mut W,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), HttpError> {
+    write_head(writer, status, content_type, body.len()).await?;
+    write_body(writer, body).await?;
+    writer.flush().await.map_err(|_| HttpError::Network)
+}
+
+/// Sensor readings with how old they are
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorReading {
+    pub co2: u16,
+    pub temperature: f32,
+    pub humidity: f32,
+    /// As of the last state update, which is at least every minute
+    pub age_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NextEvent {
+    pub summary: String<48>,
+    /// Negative once it has started
+    pub starts_in_minutes: i64,
+    pub all_day: bool,
+}
+
+impl NextEvent {
+    pub fn new(event: &CalendarEvent, now: i64) -> Self {
+        Self {
+            summary: event.summary.clone(),
+            starts_in_minutes: event.start - now,
+            all_day: event.all_day,
+        }
+    }
+}
+
+/// What GET /state answers with. A summary of the State that orchestrate keeps up to date
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiState {
+    /// Local time like 2026-10-19T14:05
+    pub time: Option<String<16>>,
+    pub sensors: Option<SensorReading>,
+    pub outside_temperature: Option<f64>,
+    pub weather_code: Option<u8>,
+    pub us_aqi: Option<u16>,
+    pub clocked_in: Option<bool>,
+    pub unread_notifications: Option<i32>,
+    pub next_event: Option<NextEvent>,
+    /// dashboard, feed or message
+    pub screen: &'static str,
+    pub message: Option<String<MESSAGE_SIZE>>,
+    /// Web services that are failing and skipped for now
+    pub offline_services: Vec<&'static str, 8>,
+}
+
+impl ApiState {
+    pub const fn new() -> Self {
+        Self {
+            time: None,
+            sensors: None,
+            outside_temperature: None,
+            weather_code: None,
+            us_aqi: None,
+            clocked_in: None,
+            unread_notifications: None,
+            next_event: None,
+            screen: "dashboard",
+            message: None,
+            offline_services: Vec::new(),
+        }
+    }
+}
+
+static API_STATE: Mutex<CriticalSectionRawMutex, RefCell<ApiState>> =
+    Mutex::new(RefCell::new(ApiState::new()));
+
+/// Called by orchestrate after every change
+pub fn publish_state(state: ApiState) {
+    API_STATE.lock(|api_state| *api_state.borrow_mut() = state);
+}
+
+pub fn current_state() -> ApiState {
+    API_STATE.lock(|api_state| api_state.borrow().clone())
+}
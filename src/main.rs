@@ -4,95 +4,96 @@
 #![allow(non_local_definitions)]
 
 use assign_resources::assign_resources;
+use atproto::{is_auth_error, AtprotoClient};
+use calendar::{CalendarConfig, WallClock};
 use core::cell::RefCell;
+use core::fmt::Write;
 use cyw43::JoinOptions;
 use cyw43_driver::{net_task, setup_cyw43};
 use defmt::*;
-use display::{
-    draw_blue_sky_notification, draw_current_outside_weather, draw_scd_data, draw_time,
-    draw_weather_forecast_box, BlueSkyNotificationData, InsideSensorData,
+use desk_buddy_core::events::{
+    EventChannel, GeneralEvents, StateChanges, StateChannel, WebRequestChannel, WebRequestEvents,
 };
+use desk_buddy_core::feed::{FeedConfig, FeedSource};
+use desk_buddy_core::hal::{Clock, DisplaySink, NetworkClient, Sensor};
+use desk_buddy_core::orchestrator::{run_orchestrator, OrchestratorConfig};
+use desk_buddy_core::retry::RetryPolicy;
+use desk_buddy_core::screen::{run_display, DisplayConfig};
+use desk_buddy_core::state::State;
+use desk_buddy_core::tasks::{run_clock, run_sensor, run_web_requests};
+use desk_buddy_ui::annual::AnnualEvents;
+use desk_buddy_ui::astronomy;
+use desk_buddy_ui::date::{parse_date_time, utc_minutes, DateTime, DayOfWeek};
+use desk_buddy_ui::display::{
+    BlueSkyNotificationData, FeedPost, ForecastConfig, InsideSensorData, NotificationEntry,
+    NotificationReason, MAX_FEED_POSTS, MAX_NOTIFICATIONS,
+};
+use desk_buddy_ui::ics::IcsParser;
+use desk_buddy_ui::io::{easy_format, easy_format_str};
+use desk_buddy_ui::text::to_ascii;
+use desk_buddy_ui::weather::{AirQualityResponse, ForecastResponse};
 use embassy_embedded_hal::shared_bus::blocking::i2c::I2cDevice;
 use embassy_executor::Spawner;
-use embassy_futures::select::{select, Either};
-use embassy_net::dns::DnsSocket;
-use embassy_net::tcp::client::{TcpClient, TcpClientState};
-use embassy_net::{Config, StackResources};
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_net::dns::DnsQueryType;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{Config, Stack, StackResources};
 use embassy_rp::clocks::RoscRng;
 use embassy_rp::i2c::I2c;
 use embassy_rp::i2c::{self};
 use embassy_rp::peripherals::{self, I2C0};
-use embassy_rp::rtc::{DateTime, RtcError};
+use embassy_rp::rtc::RtcError;
 use embassy_rp::{
     gpio::{Input, Level, Output, Pull},
     spi::{self, Spi},
 };
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::blocking_mutex::NoopMutex;
 use embassy_sync::signal;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel};
-use embassy_time::{Delay, Duration, Timer};
-use embedded_graphics::prelude::*;
+use embassy_time::{with_timeout, Delay, Duration, Instant, Timer};
 use embedded_hal_bus::spi::ExclusiveDevice;
-use env::env_value;
+use env::{env_value, env_value_or};
 use epd_waveshare::{
-    color::*,
     epd4in2_v2::{Display4in2, Epd4in2},
     prelude::*,
 };
 use heapless::String;
-use io::{easy_format, easy_format_str, format_long_datetime, format_short_datetime};
+use http_service::{HttpService, SharedHttpService};
+use local_api::{
+    current_state, publish_state, query_value, read_request, write_body, write_head,
+    write_response, ApiConfig, ApiState, HttpError, NextEvent, SensorReading, JSON, TEXT,
+};
+use mqtt::{
+    discovery_config, sensors_payload, Command, MqttClient, MqttConfig, MqttError, Packet,
+    ENTITIES, KEEP_ALIVE_SECONDS,
+};
+use office::OfficeConfig;
 use rand::RngCore;
-use reqwless::client::{HttpClient, TlsConfig, TlsVerify};
-use reqwless::request::{Request, RequestBuilder};
-use scd4x::types::SensorData;
 use scd4x::Scd4x;
+use screenshot::{read_screenshot, save_frame, ImageFormat};
 use static_cell::StaticCell;
+use storage::Storage;
 use web_requests::{
-    get_web_request, send_request, CreateSessionRequest, CreateSessionResponse, ForecastResponse,
-    GetUnreadCountResponse, ListNotificationsResponse, TimeApiResponse, WebRequestBody,
+    split_url, Author, FeedViewPost, GetUnreadCountResponse, Notification, TimeApiResponse,
+    UpdateSeenRequest, WebCallError, WebRequest,
 };
 use {defmt_rtt as _, panic_probe as _};
 
+mod atproto;
+mod calendar;
 mod cyw43_driver;
-mod display;
 mod env;
-mod io;
-mod weather_icons;
+mod http_service;
+mod local_api;
+mod mqtt;
+mod office;
+mod screenshot;
+mod storage;
+mod tls;
 mod web_requests;
 
 type I2c0Bus = NoopMutex<RefCell<I2c<'static, I2C0, i2c::Blocking>>>;
 
-#[allow(dead_code)]
-#[derive(Debug, Format)]
-/// These are events that trigger web requests.
-enum WebRequestEvents {
-    UpdateForecast,
-    UpdateOfficeStatus,
-    GetTime,
-    CheckBlueSkyNotifications,
-}
-
-enum GeneralEvents {
-    ForecastUpdated(ForecastResponse),
-    TimeFromApi(DateTime),
-    //TODO also pass what was changed? Like hour, minute etc
-    TimeDigitChanged(DateTime),
-    SensorUpdate(SensorData),
-    BlueSkyNotificationUpdate(BlueSkyNotificationData),
-}
-
-impl GeneralEvents {
-    fn as_str(&self) -> &str {
-        match self {
-            GeneralEvents::ForecastUpdated(_) => "ForecastUpdated",
-            GeneralEvents::TimeFromApi(_) => "TimeFromApi",
-            GeneralEvents::TimeDigitChanged(_) => "TimeDigitChanged",
-            GeneralEvents::SensorUpdate(_) => "SensorUpdate",
-            GeneralEvents::BlueSkyNotificationUpdate(_) => "BlueSkyNotificationUpdate",
-        }
-    }
-}
-
 ///TODO not used currently but from the example
 #[allow(dead_code)]
 enum Commands {
@@ -100,51 +101,17 @@ enum Commands {
     Stop,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Format)]
-enum StateChanges {
-    None,
-    ForecastUpdated,
-    OfficeStatusUpdated,
-    TimeSet,
-    NewTimeDigit,
-    SensorUpdate,
-    BlueSkyNotificationUpdate,
-}
-
-#[derive(Debug, Clone)]
-struct State {
-    forecast: Option<ForecastResponse>,
-    date_time_from_api: Option<DateTime>,
-    approximately_current_time: Option<DateTime>,
-    sensor_data: Option<InsideSensorData>,
-    blue_sky_notification_data: Option<BlueSkyNotificationData>,
-    state_change: StateChanges,
-}
-
-impl State {
-    fn new() -> Self {
-        Self {
-            forecast: None,
-            date_time_from_api: None,
-            approximately_current_time: None,
-            sensor_data: None,
-            blue_sky_notification_data: None,
-            state_change: StateChanges::None,
-        }
-    }
-}
-
 ///Channel to tell the wirless_task to make this web request
-static WEB_REQUEST_EVENT_CHANNEL: channel::Channel<CriticalSectionRawMutex, WebRequestEvents, 10> =
-    channel::Channel::new();
+static WEB_REQUEST_EVENT_CHANNEL: WebRequestChannel = WebRequestChannel::new();
 
 ///Events for orchestrate to react to and update state
-static GENERAL_EVENT_CHANNEL: channel::Channel<CriticalSectionRawMutex, GeneralEvents, 10> =
-    channel::Channel::new();
+static GENERAL_EVENT_CHANNEL: EventChannel = EventChannel::new();
+
+static CONSUMER_CHANNEL: StateChannel = StateChannel::new();
 
-static CONSUMER_CHANNEL: channel::Channel<CriticalSectionRawMutex, State, 1> =
-    channel::Channel::new();
+///Latest sensor readings for the mqtt_task to publish. Only the newest matters
+static MQTT_SENSOR_SIGNAL: signal::Signal<CriticalSectionRawMutex, InsideSensorData> =
+    signal::Signal::new();
 
 /// Signal for stopping the first random signal task. We use a signal here, because we need no queue. It is suffiient to have one signal active.
 static STOP_FIRST_RANDOM_SIGNAL: signal::Signal<CriticalSectionRawMutex, Commands> =
@@ -170,6 +137,14 @@ assign_resources! {
     },
     rtc: ClockPeripherals {
         rtc: RTC,
+    },
+    storage: StoragePeripherals {
+        flash: FLASH,
+    },
+    //The two keys on the Pico-ePaper-4.2
+    buttons: ButtonPeripherals {
+        next_page: PIN_15,
+        mark_seen: PIN_17,
     }
 }
 
@@ -184,11 +159,12 @@ async fn main(spawner: Spawner) {
     let i2c_bus = I2C_BUS.init(i2c_bus);
 
     spawner.must_spawn(orchestrate(spawner));
-    spawner.must_spawn(wireless_task(spawner, r.cyw43_peripherals));
+    spawner.must_spawn(wireless_task(spawner, r.cyw43_peripherals, r.storage));
 
     //Sensors/RTC tasks
     spawner.must_spawn(rtc_task(spawner, r.rtc));
     spawner.must_spawn(scd_task(spawner, i2c_bus));
+    spawner.must_spawn(button_task(r.buttons));
 
     //Timings tasks? Poc but plan on having like 1min, 5min, 24hr, etc
     spawner.must_spawn(random_10s(spawner));
@@ -208,108 +184,95 @@ async fn main(spawner: Spawner) {
 
 #[embassy_executor::task]
 async fn orchestrate(_spawner: Spawner) {
-    let mut state = State::new();
-
-    let receiver = GENERAL_EVENT_CHANNEL.receiver();
-    let state_sender = CONSUMER_CHANNEL.sender();
+    let config = OrchestratorConfig {
+        feed: feed_config(),
+        office: OfficeConfig::from_env().map(|office_config| office_config.schedule),
+        blue_sky: !env_value_or("HANDLE", "").is_empty(),
+        calendar: CalendarConfig::from_env().is_some(),
+        message_minutes: env_value_or("MESSAGE_MINUTES", "10")
+            .parse()
+            .unwrap_or(10)
+            .max(1),
+    };
 
-    loop {
-        //Wait for an event
-        let event = receiver.receive().await;
-        info!("Event received: {:?}", event.as_str());
-        match event {
-            GeneralEvents::ForecastUpdated(forecast_response) => {
-                state.forecast = Some(forecast_response);
-                state.state_change = StateChanges::ForecastUpdated;
-            }
-            GeneralEvents::TimeFromApi(time) => {
-                info!("Time received from API");
-                state.date_time_from_api = Some(time);
-                state.state_change = StateChanges::TimeSet;
-            }
-            GeneralEvents::TimeDigitChanged(time) => {
-                info!("Time digit changed");
-                state.approximately_current_time = Some(time);
-                state.state_change = StateChanges::NewTimeDigit;
-            }
-            GeneralEvents::SensorUpdate(sensor_data) => {
-                state.sensor_data = Some(InsideSensorData {
-                    co2: sensor_data.co2,
-                    temperature: sensor_data.temperature,
-                    humidity: sensor_data.humidity,
-                });
-                state.state_change = StateChanges::SensorUpdate;
-            }
-            GeneralEvents::BlueSkyNotificationUpdate(notification_data) => {
-                state.blue_sky_notification_data = Some(notification_data);
-                state.state_change = StateChanges::BlueSkyNotificationUpdate;
+    run_orchestrator(
+        config,
+        &GENERAL_EVENT_CHANNEL,
+        &CONSUMER_CHANNEL,
+        &WEB_REQUEST_EVENT_CHANNEL,
+        |state| {
+            publish_state(api_state(state));
+            if state.state_change == StateChanges::SensorUpdate {
+                if let Some(sensor_data) = &state.sensor_data {
+                    MQTT_SENSOR_SIGNAL.signal(sensor_data.value);
+                }
             }
-        }
-        info!("State change: {:?}", state.state_change);
-        state_sender.send(state.clone()).await;
-    }
+        },
+    )
+    .await
 }
 
 #[embassy_executor::task]
 async fn rtc_task(_spawner: Spawner, rtc_peripheral: ClockPeripherals) {
-    let mut rtc = embassy_rp::rtc::Rtc::new(rtc_peripheral.rtc);
+    let mut clock = RtcClock(embassy_rp::rtc::Rtc::new(rtc_peripheral.rtc));
+    run_clock(&mut clock, &CONSUMER_CHANNEL, &GENERAL_EVENT_CHANNEL).await
+}
 
-    let receiver = CONSUMER_CHANNEL.receiver();
-    let sender = GENERAL_EVENT_CHANNEL.sender();
+/// The RP2040's RTC for run_clock
+struct RtcClock(embassy_rp::rtc::Rtc<'static, peripherals::RTC>);
 
-    loop {
-        //TODO This task needs to break before the display starts listening to the same channel so currently just have a 30sec delay before display init
-        let state = receiver.receive().await;
-        info!("State received RTC: {:?}", state.state_change);
-        match state.state_change {
-            StateChanges::TimeSet => {
-                if let Some(time) = state.date_time_from_api {
-                    let result = rtc.set_datetime(time);
-                    match result {
-                        Ok(_) => {
-                            let time_now = rtc.now();
-                            if let Ok(time) = time_now {
-                                sender.send(GeneralEvents::TimeDigitChanged(time)).await;
-                            }
-                            info!("Time received and set");
-                            break;
-                        }
-                        Err(e) => {
-                            print_rtc_error(e);
-                        }
-                    }
-                }
-            }
+impl Clock for RtcClock {
+    type Error = RtcError;
 
-            _ => {}
-        }
+    fn now(&mut self) -> Result<DateTime, RtcError> {
+        self.0
+            .now()
+            .map(from_rtc_date_time)
+            .inspect_err(print_rtc_error)
+    }
+
+    fn set(&mut self, date_time: &DateTime) -> Result<(), RtcError> {
+        self.0
+            .set_datetime(to_rtc_date_time(date_time))
+            .inspect_err(print_rtc_error)
     }
+}
 
-    let mut hour = 0;
-    let mut minute = 0;
+/// The display crate has its own DateTime so it builds off the Pico. Same fields, so it is a straight copy
+fn to_rtc_date_time(date_time: &DateTime) -> embassy_rp::rtc::DateTime {
+    embassy_rp::rtc::DateTime {
+        year: date_time.year,
+        month: date_time.month,
+        day: date_time.day,
+        day_of_week: match date_time.day_of_week {
+            DayOfWeek::Sunday => embassy_rp::rtc::DayOfWeek::Sunday,
+            DayOfWeek::Monday => embassy_rp::rtc::DayOfWeek::Monday,
+            DayOfWeek::Tuesday => embassy_rp::rtc::DayOfWeek::Tuesday,
+            DayOfWeek::Wednesday => embassy_rp::rtc::DayOfWeek::Wednesday,
+            DayOfWeek::Thursday => embassy_rp::rtc::DayOfWeek::Thursday,
+            DayOfWeek::Friday => embassy_rp::rtc::DayOfWeek::Friday,
+            DayOfWeek::Saturday => embassy_rp::rtc::DayOfWeek::Saturday,
+        },
+        hour: date_time.hour,
+        minute: date_time.minute,
+        second: date_time.second,
+    }
+}
 
-    loop {
-        //TODO need a loop that watches for event to set time as well for daily for time drift?
-        let possible_time = rtc.now();
-        match possible_time {
-            Ok(time) => {
-                if time.hour != hour || time.minute != minute {
-                    info!("Time: {}:{} {}", time.hour, time.minute, time.second);
-                    hour = time.hour;
-                    minute = time.minute;
-                    sender.send(GeneralEvents::TimeDigitChanged(time)).await;
-                }
-            }
-            Err(e) => {
-                print_rtc_error(e);
-            }
-        }
-        Timer::after(Duration::from_secs(1)).await;
+fn from_rtc_date_time(date_time: embassy_rp::rtc::DateTime) -> DateTime {
+    DateTime {
+        year: date_time.year,
+        month: date_time.month,
+        day: date_time.day,
+        day_of_week: DayOfWeek::from_number(date_time.day_of_week as u8),
+        hour: date_time.hour,
+        minute: date_time.minute,
+        second: date_time.second,
     }
 }
 
 //HACK probably a better way to print this
-fn print_rtc_error(e: RtcError) {
+fn print_rtc_error(e: &RtcError) {
     match e {
         embassy_rp::rtc::RtcError::NotRunning => {
             error!("RTC not running");
@@ -333,23 +296,34 @@ fn print_rtc_error(e: RtcError) {
 
 #[embassy_executor::task]
 async fn scd_task(_spawner: Spawner, i2c_bus: &'static I2c0Bus) {
-    let sender = GENERAL_EVENT_CHANNEL.sender();
-
     let i2c_dev = I2cDevice::new(i2c_bus);
-    let mut sensor = Scd4x::new(i2c_dev, Delay);
+    let mut sensor = Scd40(Scd4x::new(i2c_dev, Delay));
+    run_sensor(&mut sensor, &GENERAL_EVENT_CHANNEL, Duration::from_secs(30)).await
+}
 
-    sensor.stop_periodic_measurement().unwrap();
-    sensor.reinit().unwrap();
+/// The scd40 on the shared I2C bus for run_sensor
+struct Scd40<I2C>(Scd4x<I2C, Delay>);
 
-    sensor.start_periodic_measurement().unwrap();
+impl<I2C: embedded_hal_1::i2c::I2c> Sensor for Scd40<I2C> {
+    type Error = scd4x::Error<I2C::Error>;
 
-    //Need to wait 5 seconds before first measurement
-    Timer::after(Duration::from_secs(5)).await;
-    loop {
-        let data = sensor.measurement().unwrap();
+    async fn start(&mut self) -> Result<(), Self::Error> {
+        self.0.stop_periodic_measurement()?;
+        self.0.reinit()?;
+        self.0.start_periodic_measurement()?;
 
-        sender.send(GeneralEvents::SensorUpdate(data)).await;
-        Timer::after(Duration::from_secs(30)).await;
+        //Need to wait 5 seconds before first measurement
+        Timer::after(Duration::from_secs(5)).await;
+        Ok(())
+    }
+
+    fn measure(&mut self) -> Result<InsideSensorData, Self::Error> {
+        let data = self.0.measurement()?;
+        Ok(InsideSensorData {
+            co2: data.co2,
+            temperature: data.temperature,
+            humidity: data.humidity,
+        })
     }
 }
 
@@ -378,130 +352,60 @@ pub async fn display_task(display_pins: DisplayPeripherals) {
         .set_refresh(&mut spi_dev, &mut Delay, RefreshLut::Quick)
         .unwrap();
 
-    let mut display = Display4in2::default();
-    //TODO need to come back and look at the epd driver I think there should be a cleaner clear function
-    display.clear(Color::White).ok();
-
-    // epd4in2.clear_frame(&mut spi_dev, &mut Delay);
-    let _ = epd4in2.update_and_display_frame(&mut spi_dev, display.buffer(), &mut Delay);
+    //The feed screen gets its own frame so the dashboard does not need to be redrawn from scratch after it
+    static FEED_FRAME: StaticCell<Display4in2> = StaticCell::new();
+    let feed_display = FEED_FRAME.init_with(Display4in2::default);
+
+    let config = DisplayConfig {
+        forecast: ForecastConfig::parse(
+            env_value_or("FORECAST_DAYS", "5"),
+            env_value_or("FORECAST_LAYOUT", "boxes"),
+        ),
+        annual_events: AnnualEvents::parse(
+            env_value_or("BIRTHDAYS", ""),
+            env_value_or("ANNIVERSARIES", ""),
+            env_value_or("ANNUAL_REMINDER_DAYS", "3")
+                .parse()
+                .unwrap_or(3),
+        ),
+        latitude: env_value("LAT").parse::<f64>().unwrap_or(0.0),
+        longitude: env_value("LON").parse::<f64>().unwrap_or(0.0),
+        fahrenheit: env_value("UNIT") == "fahrenheit",
+    };
 
-    epd4in2.sleep(&mut spi_dev, &mut Delay).unwrap();
+    let mut sink = EpdSink {
+        epd: epd4in2,
+        spi: spi_dev,
+    };
+    run_display(config, feed_display, &CONSUMER_CHANNEL, &mut sink).await
+}
 
-    let receiver = CONSUMER_CHANNEL.receiver();
+type DisplaySpi =
+    ExclusiveDevice<Spi<'static, peripherals::SPI1, spi::Blocking>, Output<'static>, Delay>;
 
-    loop {
-        let state = receiver.receive().await;
-        info!("State received Display: {:?}", state.state_change);
-        match state.state_change {
-            StateChanges::None => {}
-            StateChanges::ForecastUpdated => {
-                if let Some(forecast) = state.forecast {
-                    let mut forecast_starting_point = Point::new(0, 145);
-                    let forecast_box_width = 80;
-                    let mut todays_sunset: String<16> = String::new();
-                    //Only have room for a 5 day forecast
-                    for i in 0..5 {
-                        let daily_date = &forecast.daily.time[i];
-                        let daily_max_temp = &forecast.daily.temperature_2m_max[i];
-                        let daily_min_temp = &forecast.daily.temperature_2m_min[i];
-                        let daily_weather_code = &forecast.daily.weather_code[i];
-                        let sunrise = &forecast.daily.sunrise[i];
-                        let sunset = &forecast.daily.sunset[i];
-                        //I think all units are the same so just going to use this one
-                        let unit = &forecast.daily_units.temperature_2m_max;
-                        if i == 0 {
-                            todays_sunset = sunset.clone();
-                        }
-
-                        draw_weather_forecast_box(
-                            forecast_starting_point,
-                            forecast_box_width,
-                            daily_date,
-                            &unit,
-                            *daily_max_temp,
-                            *daily_min_temp,
-                            *daily_weather_code,
-                            sunrise.clone(),
-                            sunset.clone(),
-                            state.approximately_current_time.clone(),
-                            i as u8,
-                            &mut display,
-                        );
-                        forecast_starting_point.x += forecast_box_width as i32;
-                    }
+/// The Pico-ePaper-4.2 for run_display. Sleeps between frames, it only needs power to change
+struct EpdSink {
+    epd: Epd4in2<DisplaySpi, Input<'static>, Output<'static>, Output<'static>, Delay>,
+    spi: DisplaySpi,
+}
 
-                    //Current forecast
-                    let sunset_datetime = format_short_datetime(todays_sunset);
-                    let mut daytime = true;
-                    if let Some(current_time) = state.approximately_current_time {
-                        info!(
-                            "Current time: {}:{}:{} ",
-                            current_time.hour, current_time.minute, current_time.second
-                        );
-                        if current_time.hour > sunset_datetime.hour
-                            || (sunset_datetime.hour == current_time.hour
-                                && current_time.minute > sunset_datetime.minute)
-                        {
-                            daytime = false;
-                        }
-                    }
-                    let current_weather_starting_point = Point::new(85, 50);
-
-                    draw_current_outside_weather(
-                        current_weather_starting_point,
-                        forecast.current,
-                        forecast.current_units,
-                        daytime,
-                        &mut display,
-                    );
-                    let _ = epd4in2.wake_up(&mut spi_dev, &mut Delay);
-                    let _ = epd4in2.update_and_display_frame(
-                        &mut spi_dev,
-                        display.buffer(),
-                        &mut Delay,
-                    );
-                    epd4in2.sleep(&mut spi_dev, &mut Delay).unwrap();
-                }
-            }
-            StateChanges::OfficeStatusUpdated => {}
-            StateChanges::TimeSet => {
-                //Ignoring this event and it should hopefully not get hit since RTC loads first
-                //All time updates for display will come via the time digit change event
-            }
-            StateChanges::NewTimeDigit => {
-                if let Some(date_time) = state.approximately_current_time {
-                    draw_time(date_time, &mut display);
-                }
-                let _ = epd4in2.wake_up(&mut spi_dev, &mut Delay);
-                let _ =
-                    epd4in2.update_and_display_frame(&mut spi_dev, display.buffer(), &mut Delay);
-                epd4in2.sleep(&mut spi_dev, &mut Delay).unwrap();
-            }
-            StateChanges::SensorUpdate => {
-                //TODO not updating the display and just let another like digit change update it
-                if let Some(sensor_data) = state.sensor_data {
-                    draw_scd_data(Point::new(5, 50), sensor_data, &mut display);
-                }
-            }
-            StateChanges::BlueSkyNotificationUpdate => {
-                if let Some(notification_data) = state.blue_sky_notification_data {
-                    draw_blue_sky_notification(Point::new(160, 0), notification_data, &mut display);
-                    //TODO when this is a timer task just let the new digit update the display
-                    let _ = epd4in2.wake_up(&mut spi_dev, &mut Delay);
-                    let _ = epd4in2.update_and_display_frame(
-                        &mut spi_dev,
-                        display.buffer(),
-                        &mut Delay,
-                    );
-                    epd4in2.sleep(&mut spi_dev, &mut Delay).unwrap();
-                }
-            }
-        }
+impl DisplaySink for EpdSink {
+    fn show(&mut self, frame: &[u8]) {
+        save_frame(frame);
+        let _ = self.epd.wake_up(&mut self.spi, &mut Delay);
+        let _ = self
+            .epd
+            .update_and_display_frame(&mut self.spi, frame, &mut Delay);
+        self.epd.sleep(&mut self.spi, &mut Delay).unwrap();
     }
 }
 
 #[embassy_executor::task]
-async fn wireless_task(spawner: Spawner, cyw43_peripherals: Cyw43Peripherals) {
+async fn wireless_task(
+    spawner: Spawner,
+    cyw43_peripherals: Cyw43Peripherals,
+    storage_peripherals: StoragePeripherals,
+) {
     let mut rng: RoscRng = RoscRng;
     let (net_device, mut control) = setup_cyw43(
         cyw43_peripherals.pio,
@@ -522,7 +426,8 @@ async fn wireless_task(spawner: Spawner, cyw43_peripherals: Cyw43Peripherals) {
     let seed = rng.next_u64();
 
     // Init network stack
-    static RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
+    //One more socket each for MQTT and the local API
+    static RESOURCES: StaticCell<StackResources<5>> = StaticCell::new();
     let (stack, runner) = embassy_net::new(
         net_device,
         config,
@@ -565,216 +470,847 @@ async fn wireless_task(spawner: Spawner, cyw43_peripherals: Cyw43Peripherals) {
     //Turns LED on so I know it's connected and ready
     control.gpio_set(0, true).await;
 
-    let receiver = WEB_REQUEST_EVENT_CHANNEL.receiver();
-    let sender = GENERAL_EVENT_CHANNEL.sender();
+    if let Some(mqtt_config) = MqttConfig::from_env() {
+        spawner.must_spawn(mqtt_task(stack, mqtt_config));
+    }
+    if let Some(api_config) = ApiConfig::from_env() {
+        spawner.must_spawn(api_task(stack, api_config));
+    }
 
-    loop {
-        //Wait for an event
-        let event = receiver.receive().await;
-        info!("Display Event received: {:?}", event);
-        //Build the http client
-        let mut tls_read_buffer = [0; 16640];
-        let mut tls_write_buffer = [0; 16640];
-
-        let client_state = TcpClientState::<4, 1024, 1024>::new();
-        let tcp_client = TcpClient::new(stack, &client_state);
-        let dns_client = DnsSocket::new(stack);
-        let tls_config = TlsConfig::new(
-            seed,
-            &mut tls_read_buffer,
-            &mut tls_write_buffer,
-            TlsVerify::None,
-        );
+    let mut storage = Storage::new(storage_peripherals.flash);
+    let atproto_client = AtprotoClient::new(&mut storage);
+    let mut client = WebClient {
+        //Made once and shared with any task that needs it. It keeps the last connection open between events
+        http_service: HttpService::init(stack),
+        atproto_client,
+        storage,
+        newest_notification_at: String::new(),
+        feed_config: feed_config(),
+        office_config: OfficeConfig::from_env(),
+        calendar_config: CalendarConfig::from_env(),
+        wall_clock: None,
+    };
+    run_web_requests(
+        &mut client,
+        &WEB_REQUEST_EVENT_CHANNEL,
+        &GENERAL_EVENT_CHANNEL,
+        WebRequestEvents::retry_policy,
+        || rng.next_u32(),
+    )
+    .await
+}
 
-        let mut http_client = HttpClient::new_with_tls(&tcp_client, &dns_client, tls_config);
-        // let mut http_conn = HttpConnection::Tls(())
-        match event {
-            WebRequestEvents::UpdateForecast => {
-                let mut rx_buffer = [0; 8_320];
-                let lat = env_value("LAT");
-                let long = env_value("LON");
-                let unit = env_value("UNIT");
-                let timezone = env_value("TIMEZONE");
-
-                let mut url_buffer = [0u8; 1_028];
-
-                let formatted_url = easy_format_str(format_args!("https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=temperature_2m,relative_humidity_2m,weather_code&daily=weather_code,temperature_2m_max,temperature_2m_min,sunrise,sunset,precipitation_probability_max&temperature_unit={}&timezone={}",
-                lat, long, unit, timezone), &mut url_buffer);
-
-                let result = get_web_request::<ForecastResponse>(
-                    &mut http_client,
-                    formatted_url.unwrap(),
-                    &mut rx_buffer,
-                )
-                .await;
+/// Everything the web requests need between calls, for run_web_requests
+struct WebClient {
+    http_service: &'static SharedHttpService,
+    atproto_client: AtprotoClient,
+    storage: Storage,
+    //updateSeen marks everything up to this as seen
+    newest_notification_at: String<32>,
+    feed_config: FeedConfig,
+    office_config: Option<OfficeConfig>,
+    calendar_config: Option<CalendarConfig>,
+    //Events are picked by the time, which the rtc_task has. Kept here from the time API for the calendar
+    wall_clock: Option<WallClock>,
+}
 
-                match result {
-                    Ok(forecast) => {
-                        sender.send(GeneralEvents::ForecastUpdated(forecast)).await;
-                    }
-                    Err(e) => {
-                        error!("Failed to get forecast: {:?}", e);
-                    }
+impl NetworkClient for WebClient {
+    type Error = WebCallError;
+
+    async fn fetch(
+        &mut self,
+        request: WebRequestEvents,
+        timeout: Duration,
+    ) -> Result<Option<GeneralEvents>, WebCallError> {
+        let mut http_service = self.http_service.lock().await;
+        let http_service = &mut *http_service;
+        let result = match request {
+            WebRequestEvents::UpdateForecast => update_forecast(http_service, timeout).await,
+            WebRequestEvents::UpdateAirQuality => update_air_quality(http_service, timeout).await,
+            WebRequestEvents::UpdateOfficeStatus => match &self.office_config {
+                Some(office_config) => {
+                    update_office_status(http_service, office_config, timeout).await
+                }
+                None => Ok(None),
+            },
+            WebRequestEvents::GetTime => get_time(http_service, timeout).await,
+            WebRequestEvents::CheckBlueSkyNotifications => {
+                check_blue_sky_notifications(
+                    http_service,
+                    &mut self.atproto_client,
+                    &mut self.storage,
+                    &mut self.newest_notification_at,
+                    timeout,
+                )
+                .await
+            }
+            WebRequestEvents::UpdateFeed => {
+                update_feed(
+                    http_service,
+                    &mut self.atproto_client,
+                    &mut self.storage,
+                    self.feed_config.source,
+                    timeout,
+                )
+                .await
+            }
+            WebRequestEvents::UpdateCalendar => match (&self.calendar_config, &self.wall_clock) {
+                (Some(calendar_config), Some(wall_clock)) => {
+                    update_calendar(http_service, calendar_config, wall_clock, timeout).await
+                }
+                (Some(_), None) => {
+                    warn!("No time yet to pick the next calendar events by");
+                    Ok(None)
                 }
+                (None, _) => Ok(None),
+            },
+            WebRequestEvents::MarkBlueSkyNotificationsSeen => {
+                mark_blue_sky_notifications_seen(
+                    http_service,
+                    &mut self.atproto_client,
+                    &mut self.storage,
+                    &self.newest_notification_at,
+                    timeout,
+                )
+                .await
             }
-            WebRequestEvents::UpdateOfficeStatus => {
-                //Call the office status update web request when implemented
+        };
+
+        match &result {
+            Ok(Some(GeneralEvents::TimeFromApi(time, utc_offset_seconds))) => {
+                self.wall_clock = Some(WallClock::new(time, *utc_offset_seconds));
             }
-            WebRequestEvents::GetTime => {
-                let mut rx_buffer = [0; 8_320];
-                let timezone = env_value("TIMEZONE");
+            Err(e) => warn!("{:?} failed with {:?}", request, e),
+            _ => {}
+        }
+        result
+    }
 
-                let mut url_buffer = [0u8; 1_028]; // im sure this can be much smaller
+    fn is_retryable(error: &WebCallError) -> bool {
+        error.is_retryable()
+    }
+}
 
-                let formatted_url = easy_format_str(
-                    format_args!("https://worldtimeapi.org/api/timezone/{}", timezone),
-                    &mut url_buffer,
-                );
+async fn update_forecast(
+    http_service: &mut HttpService,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let unit = env_value("UNIT");
+    //Keep the wind speed in the same system as the temperature
+    let wind_speed_unit = if unit == "fahrenheit" { "mph" } else { "kmh" };
+
+    let request = WebRequest::get("https://api.open-meteo.com/v1/forecast")
+        .query("latitude", env_value("LAT"))
+        .query("longitude", env_value("LON"))
+        .query("current", "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,wind_speed_10m,wind_direction_10m,wind_gusts_10m,uv_index")
+        .query("daily", "weather_code,temperature_2m_max,temperature_2m_min,sunrise,sunset,precipitation_probability_max")
+        .query("temperature_unit", unit)
+        .query("wind_speed_unit", wind_speed_unit)
+        .query("timezone", env_value("TIMEZONE"))
+        .timeout(timeout);
+
+    let forecast = http_service.send::<_, ForecastResponse>(request).await?;
+    Ok(Some(GeneralEvents::ForecastUpdated(forecast)))
+}
 
-                let result = get_web_request::<TimeApiResponse>(
-                    &mut http_client,
-                    formatted_url.unwrap(),
-                    &mut rx_buffer,
-                )
-                .await;
+async fn update_air_quality(
+    http_service: &mut HttpService,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let request = WebRequest::get("https://air-quality-api.open-meteo.com/v1/air-quality")
+        .query("latitude", env_value("LAT"))
+        .query("longitude", env_value("LON"))
+        .query("current", "us_aqi,european_aqi,pm10,pm2_5,ozone,alder_pollen,birch_pollen,grass_pollen,mugwort_pollen,olive_pollen,ragweed_pollen")
+        .query("timezone", env_value("TIMEZONE"))
+        .timeout(timeout);
+
+    let air_quality = http_service.send::<_, AirQualityResponse>(request).await?;
+    Ok(Some(GeneralEvents::AirQualityUpdated(air_quality)))
+}
 
-                if let Ok(response) = result {
-                    let rtc_time =
-                        format_long_datetime(response.datetime, Some(response.day_of_week));
-                    info!("sending time to rtc");
-                    sender.send(GeneralEvents::TimeFromApi(rtc_time)).await;
-                }
+async fn update_office_status(
+    http_service: &mut HttpService,
+    office_config: &OfficeConfig,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let mut authorization: String<512> = String::new();
+    let mut request = WebRequest::get(office_config.url)
+        .max_response_size(4_096)
+        .timeout(timeout);
+    if !office_config.token.is_empty() {
+        if core::write!(authorization, "Bearer {}", office_config.token).is_err() {
+            error!("OFFICE_STATUS_TOKEN is too long");
+            return Err(WebCallError::InvalidUrl);
+        }
+        request = request.header("Authorization", &authorization);
+    }
+
+    let body = http_service.send_raw(request).await?;
+    let clocked_in = office_config.clocked_in(body).ok_or(WebCallError::Json)?;
+    info!("Clocked in: {}", clocked_in);
+    Ok(Some(GeneralEvents::OfficeStatusUpdated(clocked_in)))
+}
+
+/// Reads through the calendar a line at a time and keeps the next few events
+async fn update_calendar(
+    http_service: &mut HttpService,
+    calendar_config: &CalendarConfig,
+    wall_clock: &WallClock,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let authorization = calendar_config.authorization();
+    let mut request = WebRequest::get(calendar_config.url).timeout(timeout);
+    if let Some(authorization) = &authorization {
+        request = request.header("Authorization", authorization);
+    }
+
+    let (base_url, _) = split_url(calendar_config.url);
+    let mut session = http_service.session(base_url).await?;
+    let mut parser = IcsParser::new(wall_clock.now(), wall_clock.utc_offset_minutes());
+    let lines = session
+        .stream_lines(request, |line| {
+            parser.parse_line(line);
+            true
+        })
+        .await?;
+
+    let events = parser.finish();
+    info!(
+        "Read {} calendar lines, {} events coming up",
+        lines,
+        events.len()
+    );
+    Ok(Some(GeneralEvents::CalendarUpdated(events)))
+}
+
+async fn get_time(
+    http_service: &mut HttpService,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let timezone = env_value("TIMEZONE");
+
+    let mut url_buffer = [0u8; 1_028]; // im sure this can be much smaller
+
+    let Ok(formatted_url) = easy_format_str(
+        format_args!("https://worldtimeapi.org/api/timezone/{}", timezone),
+        &mut url_buffer,
+    ) else {
+        error!("Failed to format url");
+        return Err(WebCallError::InvalidUrl);
+    };
+
+    let request = WebRequest::get(formatted_url)
+        //Small response, so anything big is not the time api
+        .max_response_size(2_048)
+        .timeout(timeout);
+    let response = http_service.send::<_, TimeApiResponse>(request).await?;
+
+    //Already local time, the offset is worked out from the raw and dst offsets below
+    let rtc_time = parse_date_time(response.datetime)
+        .map_err(|e| {
+            error!("Could not read the time {}: {:?}", response.datetime, e);
+            WebCallError::Json
+        })?
+        .date_time;
+    info!("sending time to rtc");
+    let utc_offset_seconds = response.raw_offset + response.dst_offset;
+    Ok(Some(GeneralEvents::TimeFromApi(
+        rtc_time,
+        utc_offset_seconds,
+    )))
+}
+
+/// https:// and the PDS_HOST from the .env
+fn pds_base_url(buffer: &mut [u8]) -> Result<&str, WebCallError> {
+    easy_format_str(format_args!("https://{}", env_value("PDS_HOST")), buffer).map_err(|_| {
+        error!("Failed to format url");
+        WebCallError::InvalidUrl
+    })
+}
+
+async fn check_blue_sky_notifications(
+    http_service: &mut HttpService,
+    atproto_client: &mut AtprotoClient,
+    storage: &mut Storage,
+    newest_notification_at: &mut String<32>,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let mut url_buffer = [0u8; 1_028];
+    let base_url = pds_base_url(&mut url_buffer)?;
+
+    //All the calls go over the same connection so there is only one TLS handshake
+    let mut session = http_service.session(base_url).await?;
+
+    //If the access token is turned down even though we thought it was good, get a new one and try once more
+    let mut possible_unread_count = None;
+    for _ in 0..2 {
+        atproto_client
+            .authorize(&mut session, storage, timeout)
+            .await?;
+        let get_notification_count_request =
+            WebRequest::get("/xrpc/app.bsky.notification.getUnreadCount")
+                .header("Authorization", atproto_client.authorization())
+                .timeout(timeout);
+        match session
+            .send::<_, GetUnreadCountResponse>(get_notification_count_request)
+            .await
+        {
+            Ok(response) => {
+                possible_unread_count = Some(response.count);
+                break;
             }
-            WebRequestEvents::CheckBlueSkyNotifications => {
-                //TODO Write a better implementation probably a "diet" blue sky client. This is just for POC
-                //But do not need to be getting a new JWT for every call. Probably should be saving to flash
-                //Then refreshing
-
-                //Also just whole thing can be cleaned up
-                let mut rx_buffer = [0; 8_320];
-                let pds_host = env_value("PDS_HOST");
-
-                let body = WebRequestBody {
-                    body: &CreateSessionRequest {
-                        identifier: env_value("HANDLE"),
-                        password: env_value("PASSWORD"),
-                    },
-                };
-
-                let create_session_request =
-                    Request::post("/xrpc/com.atproto.server.createSession")
-                        .host(pds_host)
-                        .content_type(reqwless::headers::ContentType::ApplicationJson)
-                        .body(body)
-                        .build();
-                let mut url_buffer = [0u8; 1_028];
-                let mut formatting_jwt_buffer = [0u8; 1_028];
-                let mut possible_jwt: Option<&str> = None;
-                let attempted_url_format =
-                    easy_format_str(format_args!("https://{}", pds_host), &mut url_buffer);
-                if let Err(_e) = attempted_url_format {
-                    error!("Failed to format url");
-                    continue;
+            Err(e) if is_auth_error(&e) => atproto_client.access_token_rejected(),
+            Err(e) => return Err(atproto_client.check_error(e)),
+        }
+    }
+    let Some(unread_count) = possible_unread_count else {
+        error!("Bluesky turned down a fresh access token");
+        return Err(WebCallError::HttpError(401));
+    };
+
+    let limit: String<4> = easy_format(format_args!("{}", MAX_NOTIFICATIONS));
+    let list_notifications_request =
+        WebRequest::get("/xrpc/app.bsky.notification.listNotifications")
+            .query("limit", &limit)
+            .header("Authorization", atproto_client.authorization())
+            .timeout(timeout);
+    //Streamed so the size of the response does not matter, only the size of one notification
+    let mut notifications: heapless::Vec<NotificationEntry, MAX_NOTIFICATIONS> =
+        heapless::Vec::new();
+    let mut possible_newest_at: Option<String<32>> = None;
+    session
+        .stream(
+            list_notifications_request,
+            "notifications",
+            |notification: Notification| {
+                if possible_newest_at.is_none() {
+                    possible_newest_at = Some(notification.indexed_at.clone());
                 }
-                let formatted_base_url = attempted_url_format.unwrap();
-
-                let result =
-                    send_request::<WebRequestBody<CreateSessionRequest>, CreateSessionResponse>(
-                        &mut http_client,
-                        formatted_base_url,
-                        create_session_request,
-                        &mut rx_buffer,
-                    )
-                    .await;
-                match result {
-                    Ok(response) => {
-                        let formatted_jwt = easy_format_str(
-                            format_args!("Bearer {}", response.access_jwt),
-                            &mut formatting_jwt_buffer,
-                        );
-                        if let Ok(jwt) = formatted_jwt {
-                            possible_jwt = Some(jwt);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to get session: {:?}", e);
+                let _ = notifications.push(notification_entry(&notification));
+                !notifications.is_full()
+            },
+        )
+        .await
+        .map_err(|e| atproto_client.check_error(e))?;
+
+    if let Some(newest_at) = possible_newest_at {
+        *newest_notification_at = newest_at;
+    }
+    info!(
+        "{} unread Bluesky notifications, got the last {}",
+        unread_count,
+        notifications.len()
+    );
+
+    Ok(Some(GeneralEvents::BlueSkyNotificationUpdate(
+        BlueSkyNotificationData {
+            unread_notifications: unread_count,
+            notifications,
+        },
+    )))
+}
+
+async fn update_feed(
+    http_service: &mut HttpService,
+    atproto_client: &mut AtprotoClient,
+    storage: &mut Storage,
+    source: FeedSource,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    let mut url_buffer = [0u8; 1_028];
+    let base_url = pds_base_url(&mut url_buffer)?;
+    let mut session = http_service.session(base_url).await?;
+
+    let limit: String<4> = easy_format(format_args!("{}", MAX_FEED_POSTS));
+    let mut posts: heapless::Vec<FeedPost, MAX_FEED_POSTS> = heapless::Vec::new();
+    for _ in 0..2 {
+        atproto_client
+            .authorize(&mut session, storage, timeout)
+            .await?;
+        //The PDS passes these on to the app view for us
+        let request = match source {
+            FeedSource::Off => return Ok(None),
+            FeedSource::Timeline => WebRequest::get("/xrpc/app.bsky.feed.getTimeline"),
+            FeedSource::Feed(uri) => {
+                WebRequest::get("/xrpc/app.bsky.feed.getFeed").query("feed", uri)
+            }
+        }
+        .query("limit", &limit)
+        .header("Authorization", atproto_client.authorization())
+        .timeout(timeout);
+
+        //Posts with embeds can be a few KB each, streaming means only one is held at a time
+        posts.clear();
+        let result = session
+            .stream(request, "feed", |feed_view_post: FeedViewPost| {
+                let _ = posts.push(feed_post(&feed_view_post));
+                !posts.is_full()
+            })
+            .await;
+        match result {
+            Ok(_) => {
+                info!("Got {} posts for the feed screen", posts.len());
+                return Ok(Some(GeneralEvents::FeedUpdated(posts)));
+            }
+            Err(e) if is_auth_error(&e) => atproto_client.access_token_rejected(),
+            Err(e) => return Err(atproto_client.check_error(e)),
+        }
+    }
+    error!("Bluesky turned down a fresh access token");
+    Err(WebCallError::HttpError(401))
+}
+
+fn feed_post(feed_view_post: &FeedViewPost) -> FeedPost {
+    let post = &feed_view_post.post;
+    FeedPost {
+        author: to_ascii(author_name(&post.author)),
+        handle: to_ascii(&post.author.handle),
+        text: to_ascii(&post.record.text),
+        created_at: julian_day(&post.record.created_at),
+    }
+}
+
+/// Julian day in UTC for a Bluesky timestamp like 2024-12-10T18:03:59.253Z. None if we can not read it
+fn julian_day(timestamp: &str) -> Option<f64> {
+    let timestamp = parse_date_time(timestamp).ok()?;
+    Some(astronomy::julian_day(&timestamp.utc(), 0))
+}
+
+/// Display name, or the handle when they have not set one
+fn author_name(author: &Author) -> &str {
+    match &author.display_name {
+        Some(display_name) if !display_name.trim().is_empty() => display_name.as_str(),
+        _ => author.handle.as_str(),
+    }
+}
+
+/// What the display needs from a notification
+fn notification_entry(notification: &Notification) -> NotificationEntry {
+    NotificationEntry {
+        author: to_ascii(author_name(&notification.author)),
+        reason: NotificationReason::from_reason(&notification.reason),
+        is_read: notification.is_read,
+        indexed_at: julian_day(&notification.indexed_at),
+    }
+}
+
+/// Marks everything up to the newest notification we have shown as seen, so the unread count goes back to 0
+async fn mark_blue_sky_notifications_seen(
+    http_service: &mut HttpService,
+    atproto_client: &mut AtprotoClient,
+    storage: &mut Storage,
+    newest_notification_at: &str,
+    timeout: Duration,
+) -> Result<Option<GeneralEvents>, WebCallError> {
+    if newest_notification_at.is_empty() {
+        info!("No Bluesky notifications to mark as seen yet");
+        return Ok(None);
+    }
+
+    let mut url_buffer = [0u8; 1_028];
+    let base_url = pds_base_url(&mut url_buffer)?;
+    let mut session = http_service.session(base_url).await?;
+
+    let update_seen_body = UpdateSeenRequest {
+        seen_at: newest_notification_at,
+    };
+    for _ in 0..2 {
+        atproto_client
+            .authorize(&mut session, storage, timeout)
+            .await?;
+        let update_seen_request = WebRequest::post("/xrpc/app.bsky.notification.updateSeen")
+            .json(&update_seen_body)
+            .header("Authorization", atproto_client.authorization())
+            .timeout(timeout);
+        match session.send_discarding_body(update_seen_request).await {
+            Ok(()) => return Ok(Some(GeneralEvents::BlueSkyNotificationsSeen)),
+            Err(e) if is_auth_error(&e) => atproto_client.access_token_rejected(),
+            Err(e) => return Err(atproto_client.check_error(e)),
+        }
+    }
+    error!("Bluesky turned down a fresh access token");
+    Err(WebCallError::HttpError(401))
+}
+
+/// Reconnecting to the MQTT broker. Only the delays are used, it keeps trying forever
+const MQTT_RECONNECT: RetryPolicy = RetryPolicy {
+    attempts: u8::MAX,
+    base_delay: Duration::from_secs(2),
+    max_delay: Duration::from_secs(300),
+    timeout: Duration::from_secs(10),
+    failures_to_open: 0,
+    open_for: Duration::from_secs(0),
+};
+
+/// Publishes the sensors to an MQTT broker with Home Assistant discovery and listens for commands.
+/// Has its own socket so it does not get in the way of the web requests
+#[embassy_executor::task]
+async fn mqtt_task(stack: Stack<'static>, config: MqttConfig) {
+    let mut rng = RoscRng;
+    let mut socket_rx_buffer = [0u8; 1024];
+    let mut socket_tx_buffer = [0u8; 1024];
+    let mut packet_tx_buffer = [0u8; 640];
+    let mut packet_rx_buffer = [0u8; 512];
+    let mut attempt: u8 = 0;
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut socket_rx_buffer, &mut socket_tx_buffer);
+        let result = mqtt_session(
+            stack,
+            &config,
+            &mut socket,
+            &mut packet_tx_buffer,
+            &mut packet_rx_buffer,
+            &mut attempt,
+        )
+        .await;
+        socket.abort();
+        let _ = socket.flush().await;
+        if let Err(e) = result {
+            error!("MQTT connection lost: {:?}", e);
+        }
+
+        let delay = MQTT_RECONNECT.backoff(attempt, rng.next_u32());
+        attempt = attempt.saturating_add(1);
+        info!("Reconnecting to MQTT in {}s", delay.as_secs());
+        Timer::after(delay).await;
+    }
+}
+
+/// One connection to the broker. Only comes back when it drops. `attempt` is reset once the broker takes us
+async fn mqtt_session(
+    stack: Stack<'static>,
+    config: &MqttConfig,
+    socket: &mut TcpSocket<'_>,
+    tx_buffer: &mut [u8],
+    rx_buffer: &mut [u8],
+    attempt: &mut u8,
+) -> Result<(), MqttError> {
+    //Works with an IP too
+    let address = match stack.dns_query(config.broker, DnsQueryType::A).await {
+        Ok(addresses) => *addresses.first().ok_or(MqttError::Network)?,
+        Err(e) => {
+            error!(
+                "Could not look up the MQTT broker {}: {:?}",
+                config.broker, e
+            );
+            return Err(MqttError::Network);
+        }
+    };
+    //The broker drops us after a keep alive and a half of quiet, do the same to it
+    socket.set_timeout(Some(Duration::from_secs(KEEP_ALIVE_SECONDS as u64 * 3 / 2)));
+    match with_timeout(
+        MQTT_RECONNECT.timeout,
+        socket.connect((address, config.port)),
+    )
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            error!("Could not connect to the MQTT broker: {:?}", e);
+            return Err(MqttError::Network);
+        }
+        Err(_) => return Err(MqttError::Network),
+    }
+
+    let mut client = MqttClient::connect(socket, config, tx_buffer, rx_buffer).await?;
+    info!("Connected to the MQTT broker {}", config.broker);
+    *attempt = 0;
+
+    //Retained so Home Assistant picks the buddy up whenever it starts
+    let mut discovery_buffer = [0u8; 512];
+    for entity in ENTITIES.iter() {
+        if let Some((topic, payload)) = discovery_config(config, entity, &mut discovery_buffer) {
+            client.publish(&topic, payload, true).await?;
+        }
+    }
+    client
+        .publish(&config.status_topic(), b"online", true)
+        .await?;
+    client.subscribe(&config.command_filter()).await?;
+
+    let sensors_topic = config.sensors_topic();
+    let mut last_sent = Instant::now();
+    loop {
+        let ping_at = last_sent + Duration::from_secs(KEEP_ALIVE_SECONDS as u64 / 2);
+        let event = select3(
+            client.next_packet(),
+            MQTT_SENSOR_SIGNAL.wait(),
+            Timer::at(ping_at),
+        )
+        .await;
+        match event {
+            Either3::First(packet) => match packet? {
+                Packet::Publish { topic, payload } => {
+                    if let Some(command) = Command::from_publish(config, topic, payload) {
+                        info!("MQTT command: {:?}", command);
+                        handle_command(command).await;
                     }
                 }
+                Packet::SubAck => info!("Listening for MQTT commands"),
+                Packet::PingResp => {}
+                other => debug!("Ignoring MQTT packet {:?}", other),
+            },
+            Either3::Second(sensor_data) => {
+                client
+                    .publish(
+                        &sensors_topic,
+                        sensors_payload(&sensor_data).as_bytes(),
+                        false,
+                    )
+                    .await?;
+                last_sent = Instant::now();
+            }
+            Either3::Third(_) => {
+                client.ping().await?;
+                last_sent = Instant::now();
+            }
+        }
+    }
+}
 
-                if let Some(jwt) = possible_jwt {
-                    let auth_headers = [("Authorization", jwt)];
+/// A tiny HTTP server for scripts and dashboards on the network. One request at a time, anyone
+/// connecting while one is being answered is turned away
+#[embassy_executor::task]
+async fn api_task(stack: Stack<'static>, config: ApiConfig) {
+    let mut socket_rx_buffer = [0u8; 1024];
+    let mut socket_tx_buffer = [0u8; 2048];
+    let mut request_buffer = [0u8; 1024];
+    info!("Local API listening on port {}", config.port);
 
-                    let mut rx_buffer = [0; 8_320];
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut socket_rx_buffer, &mut socket_tx_buffer);
+        //Keeps a client that goes quiet from holding up the next one
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if let Err(e) = socket.accept(config.port).await {
+            warn!("Local API accept failed: {:?}", e);
+            continue;
+        }
+        if let Err(e) = serve_api_request(&mut socket, &config, &mut request_buffer).await {
+            warn!("Local API request failed: {:?}", e);
+        }
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
 
-                    let get_notification_count_request =
-                        Request::get("/xrpc/app.bsky.notification.getUnreadCount")
-                            .headers(&auth_headers)
-                            .host(pds_host)
-                            .content_type(reqwless::headers::ContentType::ApplicationJson)
-                            .build();
+async fn serve_api_request(
+    socket: &mut TcpSocket<'_>,
+    config: &ApiConfig,
+    request_buffer: &mut [u8],
+) -> Result<(), HttpError> {
+    let request = match read_request(socket, request_buffer).await {
+        Ok(request) => request,
+        Err(HttpError::TooLarge) => {
+            return write_response(socket, 413, TEXT, b"Request too large").await
+        }
+        Err(HttpError::BadRequest) => {
+            return write_response(socket, 400, TEXT, b"Bad request").await
+        }
+        Err(e) => return Err(e),
+    };
+    info!("Local API {} {}", request.method, request.path);
+    if !config.authorized(request.authorization) {
+        return write_response(socket, 401, TEXT, b"Missing or wrong API_TOKEN").await;
+    }
 
-                    let unread_count = send_request::<(), GetUnreadCountResponse>(
-                        &mut http_client,
-                        formatted_base_url,
-                        get_notification_count_request,
-                        &mut rx_buffer,
-                    )
-                    .await;
-
-                    // if let Ok(response) = unread_count {
-                    //     info!("Unread count: {}", response.count);
-                    // }
-                    let mut rx_buffer = [0; 8_320];
-
-                    let get_notification_count_request =
-                        Request::get("/xrpc/app.bsky.notification.listNotifications?limit=1")
-                            .headers(&auth_headers)
-                            .host(pds_host)
-                            .content_type(reqwless::headers::ContentType::ApplicationJson)
-                            .build();
-
-                    let result = send_request::<(), ListNotificationsResponse>(
-                        &mut http_client,
-                        formatted_base_url,
-                        get_notification_count_request,
-                        &mut rx_buffer,
-                    )
-                    .await;
-
-                    if let Ok(response) = result {
-                        let last_notification_blur = match response.notifications[0].reason {
-                            "like" => "\nhas liked your post",
-                            "repost" => "\nhas reposted your post",
-                            "follow" => "\nhas followed you",
-                            "mention" => "\nhas mentioned you",
-                            "reply" => "\nhas replied to you",
-                            "quote" => "\nhas quoted you",
-                            _ => " Not sure what happened here",
-                        };
-
-                        info!(
-                            "Last notification: {}{}",
-                            response.notifications[0].author.display_name, last_notification_blur
-                        );
-                        //TODO expected display name to have a value like I'm Bailey Townsend, but my handle is baileytownsend.dev
-                        //Will do some curls later and see whats up
-                        let last_notification_string = easy_format(format_args!(
-                            "{}{}",
-                            response.notifications[0].author.handle, last_notification_blur
-                        ));
-
-                        sender
-                            .send(GeneralEvents::BlueSkyNotificationUpdate(
-                                BlueSkyNotificationData {
-                                    unread_notifications: unread_count.unwrap().count,
-                                    last_notification: last_notification_string,
-                                },
-                            ))
-                            .await;
+    match (request.method, request.path) {
+        ("GET", "/state") => {
+            let mut body = [0u8; 1024];
+            match serde_json_core::to_slice(&current_state(), &mut body) {
+                Ok(length) => write_response(socket, 200, JSON, &body[..length]).await,
+                Err(_) => write_response(socket, 503, TEXT, b"State did not fit").await,
+            }
+        }
+        ("GET", "/sensors") => match current_state().sensors {
+            Some(sensors) => {
+                let mut body = [0u8; 128];
+                let length = serde_json_core::to_slice(&sensors, &mut body)
+                    .map_err(|_| HttpError::TooLarge)?;
+                write_response(socket, 200, JSON, &body[..length]).await
+            }
+            None => write_response(socket, 503, TEXT, b"No sensor readings yet").await,
+        },
+        ("POST", "/refresh") => {
+            handle_command(Command::Refresh).await;
+            write_response(socket, 202, TEXT, b"Refreshing").await
+        }
+        ("POST", "/message") => {
+            //The body is the text, empty takes the message down
+            let Ok(text) = core::str::from_utf8(request.body) else {
+                return write_response(socket, 400, TEXT, b"Message should be UTF-8 text").await;
+            };
+            handle_command(Command::message(text.trim())).await;
+            write_response(socket, 202, TEXT, b"Showing").await
+        }
+        ("GET", "/screenshot") => {
+            //PBM unless ?format=bmp
+            let format = match query_value(request.query, "format") {
+                None => ImageFormat::Pbm,
+                Some(name) => match ImageFormat::from_name(name) {
+                    Some(format) => format,
+                    None => {
+                        return write_response(socket, 400, TEXT, b"format is pbm or bmp").await
                     }
+                },
+            };
+            //Streamed out a piece at a time instead of copying the whole frame
+            write_head(socket, 200, format.content_type(), format.size()).await?;
+            let mut chunk = [0u8; 512];
+            let mut offset = 0;
+            loop {
+                let length = read_screenshot(format, offset, &mut chunk);
+                if length == 0 {
+                    break;
                 }
+                write_body(socket, &chunk[..length]).await?;
+                offset += length;
+            }
+            socket.flush().await.map_err(|_| HttpError::Network)
+        }
+        (_, "/state" | "/sensors" | "/refresh" | "/message" | "/screenshot") => {
+            write_response(socket, 405, TEXT, b"Method not allowed").await
+        }
+        _ => write_response(socket, 404, TEXT, b"Not found").await,
+    }
+}
+
+/// What GET /state shows, worked out each time the State changes
+fn api_state(state: &State) -> ApiState {
+    let utc_offset_seconds = state.utc_offset_seconds.unwrap_or(0);
+    let mut time = None;
+    let mut next_event = None;
+    if let Some(current_time) = &state.approximately_current_time {
+        let mut formatted: String<16> = String::new();
+        let _ = core::write!(
+            formatted,
+            "{:04}-{:02}-{:02}T{:02}:{:02}",
+            current_time.year,
+            current_time.month,
+            current_time.day,
+            current_time.hour,
+            current_time.minute
+        );
+        time = Some(formatted);
+
+        let now = utc_minutes(current_time, utc_offset_seconds);
+        next_event = state.calendar.as_ref().and_then(|calendar| {
+            calendar
+                .value
+                .iter()
+                .find(|event| event.end > now)
+                .map(|event| NextEvent::new(event, now))
+        });
+    }
+
+    ApiState {
+        time,
+        sensors: state.sensor_data.as_ref().map(|sensor_data| SensorReading {
+            co2: sensor_data.value.co2,
+            temperature: sensor_data.value.temperature,
+            humidity: sensor_data.value.humidity,
+            age_seconds: sensor_data.fetched_at.elapsed().as_secs(),
+        }),
+        outside_temperature: state
+            .forecast
+            .as_ref()
+            .map(|forecast| forecast.value.current.temperature_2m),
+        weather_code: state
+            .forecast
+            .as_ref()
+            .map(|forecast| forecast.value.current.weather_code),
+        us_aqi: state
+            .air_quality
+            .as_ref()
+            .and_then(|air_quality| air_quality.value.current.us_aqi),
+        clocked_in: state.clocked_in.as_ref().map(|clocked_in| clocked_in.value),
+        unread_notifications: state
+            .blue_sky_notification_data
+            .as_ref()
+            .map(|notification_data| notification_data.value.unread_notifications),
+        next_event,
+        screen: if state.message.is_some() {
+            "message"
+        } else if state.feed_rotation.showing_feed {
+            "feed"
+        } else {
+            "dashboard"
+        },
+        message: state.message.as_ref().map(|(message, _)| message.clone()),
+        offline_services: state.offline_services.iter().copied().collect(),
+    }
+}
+
+/// Commands from MQTT and the local API
+async fn handle_command(command: Command) {
+    match command {
+        Command::Refresh => request_refresh(),
+        Command::Screen(screen) => {
+            GENERAL_EVENT_CHANNEL
+                .send(GeneralEvents::ScreenRequested(screen))
+                .await
+        }
+        Command::Message(message) => {
+            GENERAL_EVENT_CHANNEL
+                .send(GeneralEvents::ShowMessage(message))
+                .await
+        }
+    }
+}
+
+/// Asks for everything that is turned on again right away.
+/// try_send so a backed up wireless task just skips some, the next regular update gets them
+fn request_refresh() {
+    let sender = WEB_REQUEST_EVENT_CHANNEL.sender();
+    let _ = sender.try_send(WebRequestEvents::UpdateForecast);
+    let _ = sender.try_send(WebRequestEvents::UpdateAirQuality);
+    if OfficeConfig::from_env().is_some() {
+        let _ = sender.try_send(WebRequestEvents::UpdateOfficeStatus);
+    }
+    if !env_value_or("HANDLE", "").is_empty() {
+        let _ = sender.try_send(WebRequestEvents::CheckBlueSkyNotifications);
+        if feed_config().source != FeedSource::Off {
+            let _ = sender.try_send(WebRequestEvents::UpdateFeed);
+        }
+    }
+    if CalendarConfig::from_env().is_some() {
+        let _ = sender.try_send(WebRequestEvents::UpdateCalendar);
+    }
+}
+
+/// From BLUESKY_FEED, FEED_EVERY_MINUTES and FEED_SHOW_MINUTES in the .env
+fn feed_config() -> FeedConfig {
+    FeedConfig::parse(
+        env_value_or("BLUESKY_FEED", ""),
+        env_value_or("FEED_EVERY_MINUTES", "10"),
+        env_value_or("FEED_SHOW_MINUTES", "2"),
+    )
+}
+
+/// Key 0 shows the next page of notifications and key 1 marks them as seen on Bluesky
+#[embassy_executor::task]
+async fn button_task(buttons: ButtonPeripherals) {
+    let mut next_page = Input::new(buttons.next_page, Pull::Up);
+    let mut mark_seen = Input::new(buttons.mark_seen, Pull::Up);
+    let sender = GENERAL_EVENT_CHANNEL.sender();
+    let web_request_sender = WEB_REQUEST_EVENT_CHANNEL.sender();
+
+    loop {
+        match select(
+            next_page.wait_for_falling_edge(),
+            mark_seen.wait_for_falling_edge(),
+        )
+        .await
+        {
+            Either::First(_) => sender.send(GeneralEvents::NotificationPageTurned).await,
+            Either::Second(_) => {
+                web_request_sender
+                    .send(WebRequestEvents::MarkBlueSkyNotificationsSeen)
+                    .await
             }
         }
+        //Cheap debounce, nobody is pressing these that fast
+        Timer::after_millis(250).await;
     }
 }
 
@@ -790,12 +1326,29 @@ async fn random_10s(_spawner: Spawner) {
 
     Timer::after(Duration::from_secs(30)).await;
     sender.send(WebRequestEvents::UpdateForecast).await;
+    sender.send(WebRequestEvents::UpdateAirQuality).await;
+
+    //Bluesky is optional, leave HANDLE empty to turn it off
+    let blue_sky_enabled = !env_value_or("HANDLE", "").is_empty();
+    if blue_sky_enabled {
+        Timer::after(Duration::from_secs(10)).await;
+        sender
+            .send(WebRequestEvents::CheckBlueSkyNotifications)
+            .await;
+    }
+    //The feed screen needs the Bluesky login too
+    let feed_enabled = blue_sky_enabled && feed_config().source != FeedSource::Off;
+    if feed_enabled {
+        sender.send(WebRequestEvents::UpdateFeed).await;
+    }
+
+    let calendar_enabled = CalendarConfig::from_env().is_some();
+    if calendar_enabled {
+        sender.send(WebRequestEvents::UpdateCalendar).await;
+    }
 
-    //TODO pausing bluesky notifications for now till i can write a proper client
-    // Timer::after(Duration::from_secs(10)).await;
-    // sender
-    //     .send(WebRequestEvents::CheckBlueSkyNotifications)
-    //     .await;
+    //Air quality is only updated hourly by Open-Meteo so only ask every 4th forecast update
+    let mut forecast_updates_since_air_quality: u8 = 0;
 
     loop {
         // we either await on the timer or the signal, whichever comes first.
@@ -811,10 +1364,23 @@ async fn random_10s(_spawner: Spawner) {
             Either::First(_) => {
                 sender.send(WebRequestEvents::UpdateForecast).await;
 
-                // Timer::after(Duration::from_secs(10)).await;
-                // sender
-                //     .send(WebRequestEvents::CheckBlueSkyNotifications)
-                //     .await;
+                forecast_updates_since_air_quality += 1;
+                if forecast_updates_since_air_quality >= 4 {
+                    forecast_updates_since_air_quality = 0;
+                    sender.send(WebRequestEvents::UpdateAirQuality).await;
+                }
+
+                if blue_sky_enabled {
+                    sender
+                        .send(WebRequestEvents::CheckBlueSkyNotifications)
+                        .await;
+                }
+                if feed_enabled {
+                    sender.send(WebRequestEvents::UpdateFeed).await;
+                }
+                if calendar_enabled {
+                    sender.send(WebRequestEvents::UpdateCalendar).await;
+                }
             }
             Either::Second(_) => {
                 // we received the signal to stop
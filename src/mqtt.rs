@@ -0,0 +1,617 @@
+use crate::env::env_value_or;
+use core::fmt::Write as _;
+use defmt::*;
+use desk_buddy_core::events::MESSAGE_SIZE;
+use desk_buddy_core::feed::Screen;
+use desk_buddy_ui::display::InsideSensorData;
+use embedded_io_async::{Read, Write};
+use heapless::String;
+use serde::Serialize;
+
+/// MQTT 3.1.1
+const PROTOCOL_LEVEL: u8 = 4;
+pub const KEEP_ALIVE_SECONDS: u16 = 60;
+/// Longest topic we build, like homeassistant/sensor/desk-buddy/temperature/config
+pub const TOPIC_SIZE: usize = 96;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 0xE0;
+/// Most bytes a fixed header can take, 1 for the type and up to 4 for the length
+const MAX_HEADER_SIZE: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
+pub enum MqttError {
+    Network,
+    /// The broker hung up
+    Closed,
+    /// Did not fit in the packet buffer
+    TooLarge,
+    /// The broker sent something that is not MQTT 3.1.1
+    Protocol,
+    /// CONNACK return code, 4 is a bad user or password and 5 is not authorized
+    Refused(u8),
+}
+
+/// Broker settings from the .env
+#[derive(Debug, Clone, Copy, Format)]
+pub struct MqttConfig {
+    pub broker: &'static str,
+    pub port: u16,
+    pub client_id: &'static str,
+    pub user: &'static str,
+    pub password: &'static str,
+    /// Everything the buddy publishes or listens to goes under this, like desk-buddy/sensors
+    pub base_topic: &'static str,
+    /// Where Home Assistant looks for new devices
+    pub discovery_prefix: &'static str,
+}
+
+impl MqttConfig {
+    /// None if MQTT_BROKER is not set
+    pub fn from_env() -> Option<Self> {
+        let broker = env_value_or("MQTT_BROKER", "");
+        if broker.is_empty() {
+            return None;
+        }
+        let client_id = env_value_or("MQTT_CLIENT_ID", "desk-buddy");
+        Some(Self {
+            broker,
+            port: env_value_or("MQTT_PORT", "1883").parse().unwrap_or(1883),
+            client_id,
+            user: env_value_or("MQTT_USER", ""),
+            password: env_value_or("MQTT_PASSWORD", ""),
+            base_topic: env_value_or("MQTT_TOPIC", client_id),
+            discovery_prefix: env_value_or("MQTT_DISCOVERY_PREFIX", "homeassistant"),
+        })
+    }
+
+    /// A topic under the base topic, like desk-buddy/status
+    pub fn topic(&self, name: &str) -> String<TOPIC_SIZE> {
+        let mut topic = String::new();
+        if core::write!(topic, "{}/{}", self.base_topic, name).is_err() {
+            error!("MQTT topic {}/{} is too long", self.base_topic, name);
+        }
+        topic
+    }
+
+    /// Availability topic. The broker sets it to offline for us if the buddy drops off
+    pub fn status_topic(&self) -> String<TOPIC_SIZE> {
+        self.topic("status")
+    }
+
+    /// The three sensor readings go out together as JSON on this
+    pub fn sensors_topic(&self) -> String<TOPIC_SIZE> {
+        self.topic("sensors")
+    }
+
+    /// Subscribed to for all the commands, the last level is the command
+    pub fn command_filter(&self) -> String<TOPIC_SIZE> {
+        self.topic("command/+")
+    }
+}
+
+/// Things Home Assistant (or anything else) can ask for on {base}/command/..., the local API takes them too
+#[derive(Debug, Clone, PartialEq, Format)]
+pub enum Command {
+    /// Fetch everything again
+    Refresh,
+    Screen(Screen),
+    /// Shown full screen for a while. Empty takes it down
+    Message(String<MESSAGE_SIZE>),
+}
+
+impl Command {
+    /// Keeps as much of `text` as fits, cut on a character
+    pub fn message(text: &str) -> Self {
+        let mut message = String::new();
+        for character in text.chars() {
+            if message.push(character).is_err() {
+                break;
+            }
+        }
+        Command::Message(message)
+    }
+
+    pub fn from_publish(config: &MqttConfig, topic: &str, payload: &[u8]) -> Option<Self> {
+        let name = topic
+            .strip_prefix(config.base_topic)?
+            .strip_prefix("/command/")?;
+        let payload = core::str::from_utf8(payload).ok()?.trim();
+        match name {
+            "refresh" => Some(Command::Refresh),
+            "screen" => Screen::from_name(payload).map(Command::Screen),
+            "message" => Some(Command::message(payload)),
+            _ => {
+                warn!("Unknown MQTT command {}", name);
+                None
+            }
+        }
+    }
+}
+
+/// Packets we read from the broker. Publishes borrow the client's read buffer
+#[derive(Debug, PartialEq, Format)]
+pub enum Packet<'a> {
+    ConnAck {
+        return_code: u8,
+    },
+    Publish {
+        topic: &'a str,
+        payload: &'a [u8],
+    },
+    SubAck,
+    PingResp,
+    /// Anything else, by its packet type
+    Other(u8),
+}
+
+/// Reads one packet from the start of `buffer`. Ok(None) means it needs more bytes.
+/// Also gives back the full length of the packet so it can be skipped if it will never fit
+pub fn decode(buffer: &[u8]) -> Result<(Option<Packet<'_>>, usize), MqttError> {
+    let Some(&header) = buffer.first() else {
+        return Ok((None, 0));
+    };
+    let mut remaining_length = 0usize;
+    let mut header_size = 1;
+    loop {
+        let Some(&byte) = buffer.get(header_size) else {
+            return Ok((None, 0));
+        };
+        remaining_length |= ((byte & 0x7F) as usize) << (7 * (header_size - 1));
+        header_size += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if header_size == MAX_HEADER_SIZE {
+            return Err(MqttError::Protocol);
+        }
+    }
+    let total = header_size + remaining_length;
+    let Some(body) = buffer.get(header_size..total) else {
+        return Ok((None, total));
+    };
+
+    let packet = match header >> 4 {
+        CONNACK => Packet::ConnAck {
+            return_code: *body.get(1).ok_or(MqttError::Protocol)?,
+        },
+        PUBLISH => {
+            let topic_length = u16::from_be_bytes([
+                *body.first().ok_or(MqttError::Protocol)?,
+                *body.get(1).ok_or(MqttError::Protocol)?,
+            ]) as usize;
+            let topic = body.get(2..2 + topic_length).ok_or(MqttError::Protocol)?;
+            let topic = core::str::from_utf8(topic).map_err(|_| MqttError::Protocol)?;
+            //QoS 1 and 2 have a packet id after the topic. We subscribe at QoS 0 so it should not come up
+            let qos = (header >> 1) & 0b11;
+            let payload_start = 2 + topic_length + if qos > 0 { 2 } else { 0 };
+            Packet::Publish {
+                topic,
+                payload: body.get(payload_start..).ok_or(MqttError::Protocol)?,
+            }
+        }
+        SUBACK => Packet::SubAck,
+        PINGRESP => Packet::PingResp,
+        other => Packet::Other(other),
+    };
+    Ok((Some(packet), total))
+}
+
+/// Builds a packet's body after room for the fixed header, then puts the header right in front of it
+struct PacketWriter<'b> {
+    buffer: &'b mut [u8],
+    length: usize,
+}
+
+impl<'b> PacketWriter<'b> {
+    fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            buffer,
+            length: MAX_HEADER_SIZE,
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), MqttError> {
+        let end = self.length + bytes.len();
+        self.buffer
+            .get_mut(self.length..end)
+            .ok_or(MqttError::TooLarge)?
+            .copy_from_slice(bytes);
+        self.length = end;
+        Ok(())
+    }
+
+    fn u16(&mut self, value: u16) -> Result<(), MqttError> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    /// Length prefixed, how MQTT sends strings and binary data
+    fn string(&mut self, bytes: &[u8]) -> Result<(), MqttError> {
+        let length = u16::try_from(bytes.len()).map_err(|_| MqttError::TooLarge)?;
+        self.u16(length)?;
+        self.bytes(bytes)
+    }
+
+    /// The finished packet
+    fn finish(self, first_byte: u8) -> Result<&'b [u8], MqttError> {
+        let mut remaining_length = self.length - MAX_HEADER_SIZE;
+        if remaining_length >= 1 << 28 {
+            return Err(MqttError::TooLarge);
+        }
+        let mut header = [first_byte, 0, 0, 0, 0];
+        let mut header_size = 1;
+        loop {
+            let mut byte = (remaining_length & 0x7F) as u8;
+            remaining_length >>= 7;
+            if remaining_length > 0 {
+                byte |= 0x80;
+            }
+            header[header_size] = byte;
+            header_size += 1;
+            if remaining_length == 0 {
+                break;
+            }
+        }
+        let start = MAX_HEADER_SIZE - header_size;
+        self.buffer[start..MAX_HEADER_SIZE].copy_from_slice(&header[..header_size]);
+        Ok(&self.buffer[start..self.length])
+    }
+}
+
+pub fn encode_connect<'b>(
+    buffer: &'b mut [u8],
+    config: &MqttConfig,
+    will_topic: &str,
+) -> Result<&'b [u8], MqttError> {
+    //Clean session and a retained will of "offline"
+    let mut flags = 0b0010_0110;
+    if !config.user.is_empty() {
+        flags |= 0b1000_0000;
+        if !config.password.is_empty() {
+            flags |= 0b0100_0000;
+        }
+    }
+    let mut writer = PacketWriter::new(buffer);
+    writer.string(b"MQTT")?;
+    writer.bytes(&[PROTOCOL_LEVEL, flags])?;
+    writer.u16(KEEP_ALIVE_SECONDS)?;
+    writer.string(config.client_id.as_bytes())?;
+    writer.string(will_topic.as_bytes())?;
+    writer.string(b"offline")?;
+    if !config.user.is_empty() {
+        writer.string(config.user.as_bytes())?;
+        if !config.password.is_empty() {
+            writer.string(config.password.as_bytes())?;
+        }
+    }
+    writer.finish(CONNECT)
+}
+
+/// QoS 0 so there is nothing to wait for
+pub fn encode_publish<'b>(
+    buffer: &'b mut [u8],
+    topic: &str,
+    payload: &[u8],
+    retain: bool,
+) -> Result<&'b [u8], MqttError> {
+    let mut writer = PacketWriter::new(buffer);
+    writer.string(topic.as_bytes())?;
+    writer.bytes(payload)?;
+    writer.finish(PUBLISH << 4 | retain as u8)
+}
+
+/// One topic filter at QoS 0
+pub fn encode_subscribe<'b>(
+    buffer: &'b mut [u8],
+    packet_id: u16,
+    topic_filter: &str,
+) -> Result<&'b [u8], MqttError> {
+    let mut writer = PacketWriter::new(buffer);
+    writer.u16(packet_id)?;
+    writer.string(topic_filter.as_bytes())?;
+    writer.bytes(&[0])?;
+    writer.finish(SUBSCRIBE)
+}
+
+/// A small MQTT 3.1.1 client, just what the buddy needs: QoS 0 publishes and one subscription.
+/// Works over anything that reads and writes, an embassy-net TcpSocket on the Pico
+pub struct MqttClient<'b, S> {
+    socket: S,
+    tx_buffer: &'b mut [u8],
+    rx_buffer: &'b mut [u8],
+    rx_length: usize,
+    /// Bytes at the start of rx_buffer the last packet handed out took up, cleared on the next read
+    consumed: usize,
+    /// Left over bytes of a packet too big for rx_buffer that are being thrown away
+    discarding: usize,
+    next_packet_id: u16,
+}
+
+impl<'b, S: Read + Write> MqttClient<'b, S> {
+    /// Sends CONNECT and waits for the broker to accept it
+    pub async fn connect(
+        socket: S,
+        config: &MqttConfig,
+        tx_buffer: &'b mut [u8],
+        rx_buffer: &'b mut [u8],
+    ) -> Result<Self, MqttError> {
+        let mut client = Self {
+            socket,
+            tx_buffer,
+            rx_buffer,
+            rx_length: 0,
+            consumed: 0,
+            discarding: 0,
+            next_packet_id: 1,
+        };
+        let packet = encode_connect(client.tx_buffer, config, &config.status_topic())?;
+        write_all(&mut client.socket, packet).await?;
+        match client.next_packet().await? {
+            Packet::ConnAck { return_code: 0 } => Ok(client),
+            Packet::ConnAck { return_code } => Err(MqttError::Refused(return_code)),
+            _ => Err(MqttError::Protocol),
+        }
+    }
+
+    pub async fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        retain: bool,
+    ) -> Result<(), MqttError> {
+        let packet = encode_publish(self.tx_buffer, topic, payload, retain)?;
+        write_all(&mut self.socket, packet).await
+    }
+
+    /// The SUBACK comes back through next_packet
+    pub async fn subscribe(&mut self, topic_filter: &str) -> Result<(), MqttError> {
+        let packet_id = self.next_packet_id;
+        self.next_packet_id = self.next_packet_id.wrapping_add(1).max(1);
+        let packet = encode_subscribe(self.tx_buffer, packet_id, topic_filter)?;
+        write_all(&mut self.socket, packet).await
+    }
+
+    /// Keeps the connection alive. The PINGRESP comes back through next_packet
+    pub async fn ping(&mut self) -> Result<(), MqttError> {
+        write_all(&mut self.socket, &[PINGREQ, 0]).await
+    }
+
+    /// Lets the broker know this is on purpose so it does not send the will
+    pub async fn disconnect(&mut self) -> Result<(), MqttError> {
+        write_all(&mut self.socket, &[DISCONNECT, 0]).await
+    }
+
+    /// Waits for the next packet from the broker. Safe to drop part way, like in a select
+    pub async fn next_packet(&mut self) -> Result<Packet<'_>, MqttError> {
+        if self.consumed > 0 {
+            self.rx_buffer.copy_within(self.consumed..self.rx_length, 0);
+            self.rx_length -= self.consumed;
+            self.consumed = 0;
+        }
+        loop {
+            if self.discarding == 0 {
+                match decode(&self.rx_buffer[..self.rx_length])? {
+                    (Some(_), total) => {
+                        self.consumed = total;
+                        break;
+                    }
+                    (None, total) if total > self.rx_buffer.len() => {
+                        warn!("Skipping a {} byte MQTT packet that does not fit", total);
+                        self.discarding = total - self.rx_length;
+                        self.rx_length = 0;
+                    }
+                    (None, _) => {}
+                }
+            }
+
+            let read = self
+                .socket
+                .read(&mut self.rx_buffer[self.rx_length..])
+                .await
+                .map_err(|_| MqttError::Network)?;
+            if read == 0 {
+                return Err(MqttError::Closed);
+            }
+            if self.discarding > 0 {
+                let skipped = read.min(self.discarding);
+                self.discarding -= skipped;
+                self.rx_buffer.copy_within(skipped..read, 0);
+                self.rx_length = read - skipped;
+            } else {
+                self.rx_length += read;
+            }
+        }
+        //Decoded again out here so the packet can borrow the buffer for the caller
+        decode(&self.rx_buffer[..self.consumed])?
+            .0
+            .ok_or(MqttError::Protocol)
+    }
+}
+
+async fn write_all<S: Write>(socket: &mut S, bytes: &[u8]) -> Result<(), MqttError> {
+    socket
+        .write_all(bytes)
+        .await
+        .map_err(|_| MqttError::Network)?;
+    socket.flush().await.map_err(|_| MqttError::Network)
+}
+
+/// The readings as one JSON object so Home Assistant can pick each one out with a value_template
+pub fn sensors_payload(sensor_data: &InsideSensorData) -> String<96> {
+    let mut payload = String::new();
+    let _ = core::write!(
+        payload,
+        "{{\"co2\":{},\"temperature\":{:.1},\"humidity\":{:.1}}}",
+        sensor_data.co2,
+        sensor_data.temperature,
+        sensor_data.humidity
+    );
+    payload
+}
+
+/// Which Home Assistant entity a discovery config makes
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
+pub enum EntityKind {
+    Sensor,
+    Button,
+    Select,
+    Text,
+}
+
+impl EntityKind {
+    fn component(&self) -> &'static str {
+        match self {
+            EntityKind::Sensor => "sensor",
+            EntityKind::Button => "button",
+            EntityKind::Select => "select",
+            EntityKind::Text => "text",
+        }
+    }
+}
+
+/// One entity for Home Assistant's MQTT discovery
+#[derive(Debug, Clone, Copy, Format)]
+pub struct Entity {
+    pub kind: EntityKind,
+    /// Used for the unique id and the topics
+    pub id: &'static str,
+    pub name: &'static str,
+    pub unit: Option<&'static str>,
+    pub device_class: Option<&'static str>,
+}
+
+/// What the buddy shows up as in Home Assistant. Sensors read {base}/sensors, the rest write to {base}/command/{id}
+pub const ENTITIES: [Entity; 6] = [
+    Entity {
+        kind: EntityKind::Sensor,
+        id: "co2",
+        name: "CO2",
+        unit: Some("ppm"),
+        device_class: Some("carbon_dioxide"),
+    },
+    Entity {
+        kind: EntityKind::Sensor,
+        id: "temperature",
+        name: "Temperature",
+        unit: Some("°C"),
+        device_class: Some("temperature"),
+    },
+    Entity {
+        kind: EntityKind::Sensor,
+        id: "humidity",
+        name: "Humidity",
+        unit: Some("%"),
+        device_class: Some("humidity"),
+    },
+    Entity {
+        kind: EntityKind::Button,
+        id: "refresh",
+        name: "Refresh",
+        unit: None,
+        device_class: None,
+    },
+    Entity {
+        kind: EntityKind::Select,
+        id: "screen",
+        name: "Screen",
+        unit: None,
+        device_class: None,
+    },
+    Entity {
+        kind: EntityKind::Text,
+        id: "message",
+        name: "Message",
+        unit: None,
+        device_class: None,
+    },
+];
+
+#[derive(Serialize)]
+struct DiscoveryConfig<'a> {
+    name: &'a str,
+    unique_id: &'a str,
+    availability_topic: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_topic: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_template: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_of_measurement: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_class: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command_topic: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<[&'a str; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<usize>,
+    device: Device<'a>,
+}
+
+#[derive(Serialize)]
+struct Device<'a> {
+    identifiers: [&'a str; 1],
+    name: &'a str,
+    model: &'a str,
+}
+
+/// The retained config topic and payload that tells Home Assistant about `entity`
+pub fn discovery_config<'b>(
+    config: &MqttConfig,
+    entity: &Entity,
+    buffer: &'b mut [u8],
+) -> Option<(String<TOPIC_SIZE>, &'b [u8])> {
+    let mut topic: String<TOPIC_SIZE> = String::new();
+    core::write!(
+        topic,
+        "{}/{}/{}/{}/config",
+        config.discovery_prefix,
+        entity.kind.component(),
+        config.client_id,
+        entity.id
+    )
+    .ok()?;
+    let mut unique_id: String<64> = String::new();
+    core::write!(unique_id, "{}_{}", config.client_id, entity.id).ok()?;
+    let mut value_template: String<48> = String::new();
+    core::write!(value_template, "{{{{ value_json.{} }}}}", entity.id).ok()?;
+    let status_topic = config.status_topic();
+    let state_topic = config.sensors_topic();
+    let mut command_topic: String<TOPIC_SIZE> = config.topic("command/");
+    command_topic.push_str(entity.id).ok()?;
+    let is_sensor = entity.kind == EntityKind::Sensor;
+
+    let discovery = DiscoveryConfig {
+        name: entity.name,
+        unique_id: &unique_id,
+        availability_topic: &status_topic,
+        state_topic: is_sensor.then_some(state_topic.as_str()),
+        value_template: is_sensor.then_some(value_template.as_str()),
+        unit_of_measurement: entity.unit,
+        device_class: entity.device_class,
+        state_class: is_sensor.then_some("measurement"),
+        command_topic: (!is_sensor).then_some(command_topic.as_str()),
+        options: (entity.kind == EntityKind::Select).then_some(["dashboard", "feed"]),
+        max: (entity.kind == EntityKind::Text).then_some(MESSAGE_SIZE),
+        device: Device {
+            identifiers: [config.client_id],
+            name: "Desk Buddy",
+            model: "RP2040 Desk Buddy",
+        },
+    };
+    match serde_json_core::to_slice(&discovery, buffer) {
+        Ok(length) => Some((topic, &buffer[..length])),
+        Err(_) => {
+            error!("Home Assistant config for {} did not fit", entity.id);
+            None
+        }
+    }
+}
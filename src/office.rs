@@ -0,0 +1,47 @@
+use crate::env::env_value_or;
+use defmt::*;
+use desk_buddy_core::office::OfficeSchedule;
+use desk_buddy_ui::json_path::{find_value, unquote};
+
+/// Settings for the clock in status. Works with anything that answers a GET with JSON,
+/// the path says where the status is and `clocked_in_value` what it looks like when clocked in
+#[derive(Debug, Clone, Copy, Format)]
+pub struct OfficeConfig {
+    pub url: &'static str,
+    /// Dotted path to the status in the response like data.clocked_in, see json_path::find_value
+    pub status_path: &'static str,
+    pub clocked_in_value: &'static str,
+    /// Sent as a Bearer token if it is set
+    pub token: &'static str,
+    pub schedule: OfficeSchedule,
+}
+
+impl OfficeConfig {
+    /// None if OFFICE_STATUS_URL is not set
+    pub fn from_env() -> Option<Self> {
+        let url = env_value_or("OFFICE_STATUS_URL", "");
+        if url.is_empty() {
+            return None;
+        }
+        Some(Self {
+            url,
+            status_path: env_value_or("OFFICE_STATUS_PATH", "clocked_in"),
+            clocked_in_value: env_value_or("OFFICE_CLOCKED_IN_VALUE", "true"),
+            token: env_value_or("OFFICE_STATUS_TOKEN", ""),
+            schedule: OfficeSchedule::parse(
+                env_value_or("OFFICE_POLL_MINUTES", "5"),
+                env_value_or("LUNCH_END", "13:00"),
+                env_value_or("WORK_END", "17:00"),
+            ),
+        })
+    }
+
+    /// Reads the status out of a response. None if the path is not in it
+    pub fn clocked_in(&self, body: &str) -> Option<bool> {
+        let Some(value) = find_value(body, self.status_path) else {
+            error!("{} is not in the office status response", self.status_path);
+            return None;
+        };
+        Some(unquote(value) == self.clocked_in_value)
+    }
+}
@@ -0,0 +1,143 @@
+use core::cell::RefCell;
+use defmt::*;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
+pub const WIDTH: usize = 400;
+pub const HEIGHT: usize = 300;
+/// One bit a pixel a row at a time, laid out the same as the Display4in2 buffer. 1 is white
+pub const FRAME_SIZE: usize = WIDTH / 8 * HEIGHT;
+
+/// A copy of what was last sent to the panel. The display_task frames can not be shared while it draws into them
+static LAST_FRAME: Mutex<CriticalSectionRawMutex, RefCell<[u8; FRAME_SIZE]>> =
+    Mutex::new(RefCell::new([0xFF; FRAME_SIZE]));
+
+/// Call with each frame as it goes to the panel
+pub fn save_frame(frame: &[u8]) {
+    let length = frame.len().min(FRAME_SIZE);
+    LAST_FRAME
+        .lock(|last_frame| last_frame.borrow_mut()[..length].copy_from_slice(&frame[..length]));
+}
+
+/// Copies the part of a screenshot of the saved frame starting at `offset` into `out`,
+/// a piece at a time so the lock is short. Gives back how many bytes were copied, 0 at the end
+pub fn read_screenshot(format: ImageFormat, offset: usize, out: &mut [u8]) -> usize {
+    LAST_FRAME.lock(|last_frame| encode(format, &last_frame.borrow()[..], offset, out))
+}
+
+/// 1 bit image formats the frame can be saved as
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
+pub enum ImageFormat {
+    /// Binary PBM, the simplest image format there is
+    Pbm,
+    /// Opens in a browser
+    Bmp,
+}
+
+/// Every row is padded to 4 bytes in a BMP
+const BMP_ROW_SIZE: usize = (WIDTH / 8 + 3) & !3;
+const PBM_HEADER: &[u8] = b"P4\n400 300\n";
+const BMP_HEADER: [u8; 62] = bmp_header();
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pbm" => Some(ImageFormat::Pbm),
+            "bmp" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "image/x-portable-bitmap",
+            ImageFormat::Bmp => "image/bmp",
+        }
+    }
+
+    /// The whole file in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            ImageFormat::Pbm => PBM_HEADER.len() + FRAME_SIZE,
+            ImageFormat::Bmp => BMP_HEADER.len() + BMP_ROW_SIZE * HEIGHT,
+        }
+    }
+
+    fn header(&self) -> &'static [u8] {
+        match self {
+            ImageFormat::Pbm => PBM_HEADER,
+            ImageFormat::Bmp => &BMP_HEADER,
+        }
+    }
+
+    /// The byte at `position` in the file
+    fn byte_at(&self, frame: &[u8], position: usize) -> u8 {
+        let header = self.header();
+        if let Some(byte) = header.get(position) {
+            return *byte;
+        }
+        let position = position - header.len();
+        match self {
+            //PBM has 1 as black so the bits are flipped
+            ImageFormat::Pbm => !frame.get(position).copied().unwrap_or(0xFF),
+            //Palette has 1 as white like the panel, but the rows go bottom up
+            ImageFormat::Bmp => {
+                let column = position % BMP_ROW_SIZE;
+                if column >= WIDTH / 8 {
+                    return 0;
+                }
+                let row = HEIGHT - 1 - position / BMP_ROW_SIZE;
+                frame
+                    .get(row * (WIDTH / 8) + column)
+                    .copied()
+                    .unwrap_or(0xFF)
+            }
+        }
+    }
+}
+
+/// Writes the part of `frame` as an image file starting at `offset` into `out`.
+/// Gives back how many bytes were written, 0 once the whole file is out
+pub fn encode(format: ImageFormat, frame: &[u8], offset: usize, out: &mut [u8]) -> usize {
+    let end = format.size().min(offset + out.len());
+    let length = end.saturating_sub(offset);
+    for (position, out) in (offset..end).zip(out.iter_mut()) {
+        *out = format.byte_at(frame, position);
+    }
+    length
+}
+
+/// File header, info header and a black and white palette
+const fn bmp_header() -> [u8; 62] {
+    let mut header = [0u8; 62];
+    header[0] = b'B';
+    header[1] = b'M';
+    header = put_u32(header, 2, (62 + BMP_ROW_SIZE * HEIGHT) as u32);
+    header = put_u32(header, 10, 62);
+    header = put_u32(header, 14, 40);
+    header = put_u32(header, 18, WIDTH as u32);
+    //Positive height means the rows go bottom up
+    header = put_u32(header, 22, HEIGHT as u32);
+    //Planes then bits per pixel
+    header[26] = 1;
+    header[28] = 1;
+    header = put_u32(header, 34, (BMP_ROW_SIZE * HEIGHT) as u32);
+    //72 DPI
+    header = put_u32(header, 38, 2835);
+    header = put_u32(header, 42, 2835);
+    header = put_u32(header, 46, 2);
+    header = put_u32(header, 50, 2);
+    //Palette, 0 is black and 1 is white
+    header[58] = 0xFF;
+    header[59] = 0xFF;
+    header[60] = 0xFF;
+    header
+}
+
+const fn put_u32(mut header: [u8; 62], at: usize, value: u32) -> [u8; 62] {
+    let bytes = value.to_le_bytes();
+    header[at] = bytes[0];
+    header[at + 1] = bytes[1];
+    header[at + 2] = bytes[2];
+    header[at + 3] = bytes[3];
+    header
+}
@@ -0,0 +1,119 @@
+use defmt::*;
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::FLASH;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+/// Marks a sector we wrote. Bump the last byte if the layout of a record changes
+const MAGIC: [u8; 4] = *b"PIW1";
+/// Magic then the length of the JSON as a little endian u16
+const HEADER_SIZE: usize = 6;
+
+/// Each record gets its own sector at the end of the flash, counting back from the last one.
+/// memory.x keeps these out of the firmware, so add a sector there when adding a slot
+#[derive(Debug, Clone, Copy, Format)]
+pub enum StorageSlot {
+    BlueSkySession = 1,
+}
+
+impl StorageSlot {
+    fn offset(&self) -> u32 {
+        (FLASH_SIZE - *self as usize * ERASE_SIZE) as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
+pub enum StorageError {
+    Flash,
+    /// The record did not fit in the buffer or the sector
+    TooLarge,
+}
+
+/// Small records kept in flash between reboots. They are saved as JSON since we already have serde for the web requests
+pub struct Storage {
+    flash: Flash<'static, FLASH, Blocking, FLASH_SIZE>,
+}
+
+impl Storage {
+    pub fn new(flash: FLASH) -> Self {
+        Self {
+            flash: Flash::new_blocking(flash),
+        }
+    }
+
+    /// None if nothing was saved in the slot yet, or it was saved by an older layout
+    pub fn load<T: DeserializeOwned>(&mut self, slot: StorageSlot, buffer: &mut [u8]) -> Option<T> {
+        let offset = slot.offset();
+        let mut header = [0u8; HEADER_SIZE];
+        if let Err(e) = self.flash.blocking_read(offset, &mut header) {
+            error!("Failed to read {:?} from flash: {:?}", slot, e);
+            return None;
+        }
+        if header[..4] != MAGIC {
+            info!("Nothing saved in {:?} yet", slot);
+            return None;
+        }
+
+        let length = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let Some(buffer) = buffer.get_mut(..length) else {
+            error!("{:?} is {} bytes, too big for the buffer", slot, length);
+            return None;
+        };
+        if let Err(e) = self
+            .flash
+            .blocking_read(offset + HEADER_SIZE as u32, buffer)
+        {
+            error!("Failed to read {:?} from flash: {:?}", slot, e);
+            return None;
+        }
+        match serde_json_core::from_slice::<T>(buffer) {
+            Ok((value, _)) => Some(value),
+            Err(_) => {
+                warn!("{:?} in flash did not parse, ignoring it", slot);
+                None
+            }
+        }
+    }
+
+    /// Writes over whatever was in the slot. `buffer` is scratch space for the JSON
+    pub fn save<T: Serialize>(
+        &mut self,
+        slot: StorageSlot,
+        value: &T,
+        buffer: &mut [u8],
+    ) -> Result<(), StorageError> {
+        if buffer.len() < HEADER_SIZE {
+            return Err(StorageError::TooLarge);
+        }
+        let length = serde_json_core::to_slice(value, &mut buffer[HEADER_SIZE..])
+            .map_err(|_| StorageError::TooLarge)?;
+        if HEADER_SIZE + length > ERASE_SIZE {
+            return Err(StorageError::TooLarge);
+        }
+        buffer[..4].copy_from_slice(&MAGIC);
+        buffer[4..HEADER_SIZE].copy_from_slice(&(length as u16).to_le_bytes());
+
+        self.erase(slot)?;
+        self.flash
+            .blocking_write(slot.offset(), &buffer[..HEADER_SIZE + length])
+            .map_err(|e| {
+                error!("Failed to write {:?} to flash: {:?}", slot, e);
+                StorageError::Flash
+            })
+    }
+
+    pub fn clear(&mut self, slot: StorageSlot) -> Result<(), StorageError> {
+        self.erase(slot)
+    }
+
+    fn erase(&mut self, slot: StorageSlot) -> Result<(), StorageError> {
+        let offset = slot.offset();
+        self.flash
+            .blocking_erase(offset, offset + ERASE_SIZE as u32)
+            .map_err(|e| {
+                error!("Failed to erase {:?}: {:?}", slot, e);
+                StorageError::Flash
+            })
+    }
+}
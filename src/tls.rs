@@ -0,0 +1,118 @@
+use crate::env::env_value_or;
+use defmt::*;
+use heapless::Vec;
+
+/// Longest PSK we take. 32 bytes is what stunnel and mosquitto generate
+const MAX_PSK_LENGTH: usize = 64;
+
+/// How much we trust the TLS connection to a host
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HostTrust<'a> {
+    /// Local server set up with a pre shared key. Both sides prove they know the key so this is verified
+    Psk { identity: &'a [u8], key: &'a [u8] },
+    /// Public APIs we send nothing secret to. Still encrypted, but the certificate is not checked, see tls_verify in http_service
+    Unverified,
+    /// Hosts we send credentials to, like the PDS. Refused since we can not check their certificate,
+    /// unless ALLOW_UNVERIFIED_TLS is "true"
+    RequiresVerification,
+}
+
+/// Trust settings read from the .env once at start up
+pub struct TrustStore {
+    psk_host: Option<&'static str>,
+    psk_identity: &'static str,
+    psk_key: Vec<u8, MAX_PSK_LENGTH>,
+    /// Hosts that get a password or token. Empty when that service is not set up
+    credential_hosts: [&'static str; 3],
+    allow_unverified: bool,
+}
+
+impl TrustStore {
+    pub fn from_env() -> Self {
+        let psk_host = match env_value_or("TLS_PSK_HOST", "") {
+            "" => None,
+            host => Some(host),
+        };
+        let psk_key = decode_hex(env_value_or("TLS_PSK_KEY", "")).unwrap_or_else(|| {
+            error!("TLS_PSK_KEY is not valid hex, PSK connections will fail");
+            Vec::new()
+        });
+
+        let allow_unverified = env_value_or("ALLOW_UNVERIFIED_TLS", "false") == "true";
+        let credential_hosts = [
+            credential_host("PDS_HOST", "HANDLE"),
+            credential_host("OFFICE_STATUS_URL", "OFFICE_STATUS_TOKEN"),
+            credential_host("CALENDAR_URL", "CALENDAR_USER"),
+        ];
+        //Say so once at start up for every host that gets a password or token without being checked
+        for host in credential_hosts {
+            if host.is_empty() || psk_host == Some(host) {
+                continue;
+            }
+            if allow_unverified {
+                warn!(
+                    "ALLOW_UNVERIFIED_TLS is on, {} gets credentials over TLS that is encrypted but not verified",
+                    host
+                );
+            } else {
+                error!(
+                    "{} gets credentials and its certificate can not be checked, so it is refused. Set ALLOW_UNVERIFIED_TLS=\"true\" in the .env to connect anyway",
+                    host
+                );
+            }
+        }
+
+        Self {
+            psk_host,
+            psk_identity: env_value_or("TLS_PSK_IDENTITY", ""),
+            psk_key,
+            credential_hosts,
+            allow_unverified,
+        }
+    }
+
+    pub fn trust_for_host(&self, host: &str) -> HostTrust<'_> {
+        if self.psk_host == Some(host) {
+            return HostTrust::Psk {
+                identity: self.psk_identity.as_bytes(),
+                key: &self.psk_key,
+            };
+        }
+        if !self.allow_unverified && !host.is_empty() && self.credential_hosts.contains(&host) {
+            return HostTrust::RequiresVerification;
+        }
+        HostTrust::Unverified
+    }
+}
+
+/// The host of the url under `url_key`, but only when `secret_key` is set and it gets sent credentials
+fn credential_host(url_key: &str, secret_key: &str) -> &'static str {
+    match env_value_or(secret_key, "") {
+        "" => "",
+        _ => host_from_url(env_value_or(url_key, "")),
+    }
+}
+
+/// Pulls the host out of a url like https://api.open-meteo.com/v1/forecast?latitude=1
+pub fn host_from_url(url: &str) -> &str {
+    let without_scheme = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    let end = without_scheme
+        .find(|c| c == '/' || c == ':' || c == '?')
+        .unwrap_or(without_scheme.len());
+    &without_scheme[..end]
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8, MAX_PSK_LENGTH>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    for pair in hex.as_bytes().chunks(2) {
+        let pair = core::str::from_utf8(pair).ok()?;
+        bytes.push(u8::from_str_radix(pair, 16).ok()?).ok()?;
+    }
+    Some(bytes)
+}
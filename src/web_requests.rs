@@ -1,92 +1,29 @@
 use core::str::from_utf8;
 use defmt::Format;
 use defmt::*;
-use embassy_net::{dns::DnsSocket, tcp::client::TcpClient};
+use desk_buddy_ui::json_stream::{JsonArrayStream, StreamError};
+use desk_buddy_ui::line_stream::LineStream;
+use embassy_time::Duration;
+use embedded_tls::alert::AlertDescription;
+use embedded_tls::TlsError;
 use heapless::{String, Vec};
 use reqwless::{
-    client::HttpClient,
-    request::{Method, Request, RequestBody},
+    client::HttpConnection,
+    client::HttpResource,
+    headers::ContentType,
+    request::{Method, Request, RequestBody, RequestBuilder},
+    response::Response,
 };
-use serde::{Deserialize, Serialize};
-
-/// You will notice I am using heapless::String instead of &str. I was having issues with sharing the struct between tasks
-/// because of str and decided to just go simple to keep moving
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-pub struct ForecastResponse {
-    pub latitude: f64,
-    pub longitude: f64,
-    pub generationtime_ms: f64,
-    pub utc_offset_seconds: i64,
-    pub timezone: String<32>,
-    pub timezone_abbreviation: String<8>,
-    pub elevation: f64,
-    pub current_units: CurrentUnits,
-    pub current: Current,
-    pub daily_units: DailyUnits,
-    pub daily: Daily,
-}
-
-///This is the units used for each of the current measurements
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-pub struct CurrentUnits {
-    pub time: String<7>,
-    pub interval: String<7>,
-    pub temperature_2m: String<3>,
-    pub relative_humidity_2m: String<2>,
-    //I think this will always be wmo code. Going to assume it is
-    // #[serde(rename = "weather_code")]
-    // pub weather_code: &'a str,
-}
-///This is the actual current weather measurements
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-pub struct Current {
-    pub time: String<16>,
-    pub interval: i64,
-    pub temperature_2m: f64,
-    pub relative_humidity_2m: i64,
-    ///See top for weather code meanings    
-    pub weather_code: u8,
-}
-
-///This is the units used for each of the daily measurements
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-pub struct DailyUnits {
-    pub time: String<7>,
-    //I think this will always be wmo code. Going to assume it is
-    // pub weather_code: &'a str,
-    pub temperature_2m_max: String<3>,
-    pub temperature_2m_min: String<3>,
-    //Just going to comment these out cause it's all just going to use the same time format
-    // pub sunrise: &'a str,
-    // pub sunset: &'a str,
-    pub precipitation_probability_max: String<1>,
-}
-
-///This is the actual daily weather measurements
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-//Hack
-//I know the vecs will always be 7(for my use case) since i get 7 week forecast
-//I know the Strings length will always be 10 or 16 because it's dates
-//Reason for the second was I was having lifetime issues with 'a &str in heapless::vec
-pub struct Daily {
-    // "2024-11-29",
-    pub time: Vec<String<10>, 7>,
-    ///See top for weather code meanings    
-    pub weather_code: Vec<u8, 7>,
-    pub temperature_2m_max: Vec<f64, 7>,
-    pub temperature_2m_min: Vec<f64, 7>,
-    // 2024-11-29T06:37
-    pub sunrise: Vec<String<16>, 7>,
-    // 2024-11-29T06:37
-    pub sunset: Vec<String<16>, 7>,
-    pub precipitation_probability_max: Vec<i64, 7>,
-}
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 ///time response
 #[derive(Deserialize)]
 pub struct TimeApiResponse<'a> {
     pub datetime: &'a str,
-    pub day_of_week: u8,
+    ///Offset from UTC in seconds without daylight savings
+    pub raw_offset: i64,
+    ///Extra daylight savings offset in seconds, 0 when not in daylight savings
+    pub dst_offset: i64,
 }
 
 ///Blyesky CreateSession Request
@@ -96,46 +33,43 @@ pub struct CreateSessionRequest<'a> {
     pub password: &'a str,
 }
 
-/// Goofy wrapper but lets me do one impl for RequestBody
+///Bluesky UpdateSeen Request
 #[derive(Serialize)]
-pub struct WebRequestBody<'a, T> {
-    pub body: &'a T,
+pub struct UpdateSeenRequest<'a> {
+    #[serde(rename = "seenAt")]
+    pub seen_at: &'a str,
 }
 
-impl<T> RequestBody for WebRequestBody<'_, T>
-where
-    T: for<'a> serde::Serialize,
-{
-    async fn write<W: embedded_io_async::Write>(&self, writer: &mut W) -> Result<(), W::Error> {
-        let mut buffer = [0u8; 8_320];
-        let bytes = serde_json_core::to_slice(&self.body, &mut buffer).unwrap();
-        let only_used_bytes = &buffer[..bytes];
-        // info!("Request Body: {}", from_utf8(&only_used_bytes).unwrap());
-        writer.write_all(&only_used_bytes).await?;
+///XRPC error body, like {"error":"ExpiredToken","message":"Token has expired"}
+#[derive(Deserialize)]
+struct XrpcErrorResponse<'a> {
+    error: &'a str,
+}
+
+/// A JSON body already serialized by WebRequest::json, so sending it can not fail part way
+pub struct WebRequestBody {
+    bytes: Vec<u8, JSON_BODY_SIZE>,
+}
 
-        Ok(())
+impl RequestBody for WebRequestBody {
+    fn len(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+
+    async fn write<W: embedded_io_async::Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        writer.write_all(&self.bytes).await
     }
 }
 
-///BlueSky CreateSession Response
-#[allow(dead_code)]
+///BlueSky CreateSession and RefreshSession Response. Only the parts we keep
 #[derive(Debug, Deserialize)]
-pub struct CreateSessionResponse<'a> {
+pub struct SessionResponse<'a> {
     #[serde(rename = "accessJwt")]
     pub access_jwt: &'a str,
     #[serde(rename = "refreshJwt")]
     pub refresh_jwt: &'a str,
     pub handle: &'a str,
     pub did: &'a str,
-    // // //Not sure what this is
-    // // // pub did_doc: Option<serde_json::Value>,
-    pub email: &'a str,
-    #[serde(rename = "emailConfirmed")]
-    pub email_confirmed: bool,
-    #[serde(rename = "emailAuthFactor")]
-    pub email_auth_factor: bool,
-    pub active: bool,
-    pub status: Option<&'a str>,
 }
 
 ///BlueSky notification count Response
@@ -144,147 +78,537 @@ pub struct GetUnreadCountResponse {
     pub count: i32,
 }
 
+///One notification from app.bsky.notification.listNotifications. Owned since they are streamed in one at a time
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-pub struct ListNotificationsResponse<'a> {
-    //This is hard coded for now
-    pub notifications: Vec<Notification<'a>, 1>,
-    #[serde(rename = "seenAt")]
-    pub seen_at: &'a str,
+#[derive(Debug, Clone, Deserialize)]
+pub struct Notification {
+    pub author: Author,
+    pub reason: String<16>,
+    #[serde(rename = "isRead")]
+    pub is_read: bool,
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: String<32>,
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Author {
+    pub handle: String<64>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String<128>>,
+}
+
+///One post from app.bsky.feed.getTimeline or getFeed. Only the parts the feed screen shows
 #[derive(Debug, Deserialize)]
-pub struct Notification<'a> {
-    pub author: Author<'a>,
-    pub reason: &'a str,
-    #[serde(rename = "reasonSubject")]
-    pub reason_subject: Option<&'a str>,
+pub struct FeedViewPost {
+    pub post: PostView,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
-pub struct Author<'a> {
-    // pub did: &'a str,
-    pub handle: &'a str,
-    #[serde(rename = "displayName")]
-    pub display_name: &'a str,
+pub struct PostView {
+    pub author: Author,
+    pub record: PostRecord,
 }
 
-#[derive(Debug, Format)]
+#[derive(Debug, Deserialize)]
+pub struct PostRecord {
+    ///Up to 300 characters, which can be a lot more bytes with emoji
+    pub text: String<1_024>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String<32>,
+}
+
+/// Longest url a WebRequest can hold, query included
+const URL_LENGTH: usize = 1_024;
+const MAX_HEADERS: usize = 4;
+/// Biggest JSON body we send. The login with a long handle and app password is the biggest
+const JSON_BODY_SIZE: usize = 512;
+/// Longest string in a streamed element once it is unescaped. Post text is the longest we take
+const UNESCAPE_BUFFER_SIZE: usize = 1_024;
+/// Used when a request does not set its own timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Format)]
 pub enum WebCallError {
+    /// The url did not fit in the request or could not be parsed
+    InvalidUrl,
+    Dns,
+    Connect,
+    Timeout,
+    /// The server could not prove who it is, like a local server with a different PSK
+    TlsVerificationFailed,
+    /// The host gets credentials and we can not check its certificate, see ALLOW_UNVERIFIED_TLS in the .env
+    TlsNotVerifiable,
+    /// The TLS handshake went wrong some other way, like the server not speaking TLS 1.3
+    Tls,
     HttpError(u16),
-    WebRequestError,
+    /// An XRPC error saying the token is expired or not valid. Bluesky sends these as a 400 instead of a 401
+    TokenRejected,
+    /// Bigger than the request's max_response_size or the rx buffer
+    BodyTooLarge,
     FailedToReadResponse,
-    DeserializationError,
+    Json,
+    /// The server asked us to slow down. Not retried, the caller waits until it is allowed again
+    RateLimited,
 }
 
-//TODO the web requests all share a lot of the same code and could be refactored to have a common deserialization function for responses and handling
+impl WebCallError {
+    /// Errors that might go away if we try again. A bad url or JSON will fail the same way every time
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            WebCallError::Dns | WebCallError::Connect | WebCallError::Timeout => true,
+            //Server errors, too many requests and request timeout
+            WebCallError::HttpError(status) => *status >= 500 || *status == 429 || *status == 408,
+            _ => false,
+        }
+    }
+}
 
-pub async fn send_request<'a, T, ResponseType>(
-    http_client: &mut HttpClient<'a, TcpClient<'a, 4>, DnsSocket<'a>>,
-    base_url: &str,
-    request: Request<'a, T>,
-    rx_buffer: &'a mut [u8],
-) -> Result<ResponseType, WebCallError>
-where
-    T: RequestBody,
-    ResponseType: serde::Deserialize<'a>,
-{
-    let mut conn = match http_client.resource(base_url).await {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to make HTTP request: {:?}", e);
-            return Err(WebCallError::WebRequestError);
+/// Turns reqwless errors into our own so the callers can tell what went wrong
+pub fn reqwless_error(e: reqwless::Error) -> WebCallError {
+    error!("Web request failed: {:?}", e);
+    match e {
+        reqwless::Error::Dns => WebCallError::Dns,
+        reqwless::Error::InvalidUrl(_) => WebCallError::InvalidUrl,
+        reqwless::Error::Tls(e) => tls_error(e),
+        reqwless::Error::BufferTooSmall => WebCallError::BodyTooLarge,
+        _ => WebCallError::Connect,
+    }
+}
+
+/// Only the handshake failing to check out is a verification failure, the rest are the connection or the server
+pub fn tls_error(e: TlsError) -> WebCallError {
+    match e {
+        TlsError::InvalidCertificate
+        | TlsError::InvalidSignature
+        | TlsError::CryptoError
+        | TlsError::HandshakeAborted(
+            _,
+            AlertDescription::BadCertificate
+            | AlertDescription::UnsupportedCertificate
+            | AlertDescription::CertificateRevoked
+            | AlertDescription::CertificateExpired
+            | AlertDescription::CertificateUnknown
+            | AlertDescription::UnknownCa
+            | AlertDescription::AccessDenied
+            | AlertDescription::DecryptError
+            | AlertDescription::UnknownPskIdentity,
+        ) => WebCallError::TlsVerificationFailed,
+        TlsError::Io(_) | TlsError::IoError | TlsError::ConnectionClosed => WebCallError::Connect,
+        _ => WebCallError::Tls,
+    }
+}
+
+/// Builder for every web request we make. Send it with the HttpService, or a HostSession if there
+/// are a few calls to the same host.
+/// ```ignore
+/// let request = WebRequest::get("https://api.open-meteo.com/v1/forecast")
+///     .query("latitude", "41.8")
+///     .timeout(Duration::from_secs(10));
+/// ```
+pub struct WebRequest<'a, B = ()> {
+    pub(crate) head: RequestHead<'a>,
+    body: B,
+}
+
+/// Everything in a WebRequest but the body
+pub struct RequestHead<'a> {
+    method: Method,
+    pub(crate) url: String<URL_LENGTH>,
+    url_overflowed: bool,
+    headers: Vec<(&'a str, &'a str), MAX_HEADERS>,
+    /// Sent with a Content-Type of application/json
+    json: bool,
+    /// The body did not serialize, the request fails when it is built
+    json_failed: bool,
+    max_response_size: Option<usize>,
+    pub(crate) timeout: Duration,
+}
+
+impl<'a> WebRequest<'a> {
+    pub fn get(url: &str) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    pub fn post(url: &str) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    #[allow(dead_code)]
+    pub fn put(url: &str) -> Self {
+        Self::new(Method::PUT, url)
+    }
+
+    /// The url can be a full url, or just the path when it is sent on a HostSession
+    fn new(method: Method, url: &str) -> Self {
+        let mut url_string = String::new();
+        let url_overflowed = url_string.push_str(url).is_err();
+        Self {
+            head: RequestHead {
+                method,
+                url: url_string,
+                url_overflowed,
+                headers: Vec::new(),
+                json: false,
+                json_failed: false,
+                max_response_size: None,
+                timeout: DEFAULT_TIMEOUT,
+            },
+            body: (),
         }
-    };
+    }
 
-    let response = match conn.send(request, rx_buffer).await {
-        Ok(req) => req,
-        Err(e) => {
-            error!("Failed to make HTTP request: {:?}", e);
-            return Err(WebCallError::WebRequestError);
+    /// Sends `body` as JSON. It is serialized now, if it does not fit sending fails with WebCallError::Json
+    pub fn json<T: Serialize>(mut self, body: &T) -> WebRequest<'a, WebRequestBody> {
+        let mut bytes = Vec::new();
+        bytes.resize_default(JSON_BODY_SIZE).ok();
+        match serde_json_core::to_slice(body, &mut bytes) {
+            Ok(used) => bytes.truncate(used),
+            Err(_e) => {
+                error!("JSON body is bigger than {} bytes", JSON_BODY_SIZE);
+                bytes.clear();
+                self.head.json_failed = true;
+            }
+        }
+        self.head.json = true;
+        WebRequest {
+            head: self.head,
+            body: WebRequestBody { bytes },
+        }
+    }
+}
+
+impl<'a, B> WebRequest<'a, B> {
+    /// Adds a query parameter. The key and value are URL encoded
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        let url = &mut self.head.url;
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let mut overflowed = url.push(separator).is_err();
+        for byte in key.bytes() {
+            overflowed |= push_url_encoded(url, byte).is_err();
+        }
+        overflowed |= url.push('=').is_err();
+        for byte in value.bytes() {
+            overflowed |= push_url_encoded(url, byte).is_err();
+        }
+        self.head.url_overflowed |= overflowed;
+        self
+    }
+
+    pub fn header(mut self, name: &'a str, value: &'a str) -> Self {
+        if self.head.headers.push((name, value)).is_err() {
+            error!("Too many headers, dropped {}", name);
+        }
+        self
+    }
+
+    /// Responses bigger than this are refused even if they would fit in the rx buffer
+    pub fn max_response_size(mut self, bytes: usize) -> Self {
+        self.head.max_response_size = Some(bytes);
+        self
+    }
+
+    /// How long connecting, and then sending and reading the response can each take
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.head.timeout = timeout;
+        self
+    }
+}
+
+impl<'a> RequestHead<'a> {
+    /// The reqwless request. Borrows the url and headers so the head has to outlive it
+    fn build<'r, B: RequestBody>(
+        &'r self,
+        host: &'r str,
+        body: B,
+    ) -> Result<Request<'r, B>, WebCallError> {
+        if self.url_overflowed {
+            error!(
+                "Url is longer than {} bytes: {}",
+                URL_LENGTH,
+                self.url.as_str()
+            );
+            return Err(WebCallError::InvalidUrl);
+        }
+        if self.json_failed {
+            return Err(WebCallError::Json);
+        }
+
+        let (_, path) = split_url(&self.url);
+        let mut builder = Request::new(self.method, path)
+            .host(host)
+            .headers(&self.headers);
+        if self.json {
+            builder = builder.content_type(ContentType::ApplicationJson);
+        }
+        Ok(builder.body(body).build())
+    }
+}
+
+fn push_url_encoded<const N: usize>(url: &mut String<N>, byte: u8) -> Result<(), ()> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+            url.push(byte as char)
+        }
+        _ => {
+            url.push('%')?;
+            url.push(HEX[(byte >> 4) as usize] as char)?;
+            url.push(HEX[(byte & 0xF) as usize] as char)
         }
+    }
+}
+
+/// Splits a url into what to connect to (https://host:port) and the path with the query.
+/// The first part is empty if it was only a path
+pub fn split_url(url: &str) -> (&str, &str) {
+    let after_scheme = match url.find("://") {
+        Some(index) => index + 3,
+        None => return ("", url),
     };
+    match url[after_scheme..].find(|c| c == '/' || c == '?') {
+        Some(index) => url.split_at(after_scheme + index),
+        None => (url, "/"),
+    }
+}
+
+/// Sends the request and checks the status. If the server says how big the body is it is checked
+/// against `max_response_size` before anything is read
+async fn send_checked<'r, 'res, 'buf, C, B>(
+    resource: &'r mut HttpResource<'res, C>,
+    request: Request<'r, B>,
+    rx_buffer: &'buf mut [u8],
+    max_response_size: Option<usize>,
+) -> Result<Response<'r, 'buf, HttpConnection<'res, C>>, WebCallError>
+where
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
+{
+    let response = resource
+        .send(request, rx_buffer)
+        .await
+        .map_err(reqwless_error)?;
 
     if !response.status.is_successful() {
-        let status_code = response.status.0.clone();
+        let status_code = response.status.0;
         error!("HTTP request failed with status: {:?}", response.status);
-        // error!("Failed response: {}", response);
-        match from_utf8(response.body().read_to_end().await.unwrap()) {
-            Ok(body) => {
+        match response
+            .body()
+            .read_to_end()
+            .await
+            .map(|body| from_utf8(body))
+        {
+            Ok(Ok(body)) => {
                 error!("Response body: {}", body);
+                if let Ok((xrpc_error, _used)) =
+                    serde_json_core::de::from_str::<XrpcErrorResponse>(body)
+                {
+                    if matches!(xrpc_error.error, "ExpiredToken" | "InvalidToken") {
+                        return Err(WebCallError::TokenRejected);
+                    }
+                }
             }
-            Err(_e) => {
-                error!("Failed to read response body");
-            }
+            _ => error!("Failed to read response body"),
         }
-
         return Err(WebCallError::HttpError(status_code));
     }
 
-    let body = match from_utf8(response.body().read_to_end().await.unwrap()) {
-        Ok(b) => b,
-        Err(_e) => {
-            error!("Failed to read response body");
-            return Err(WebCallError::FailedToReadResponse);
+    if let (Some(content_length), Some(max_response_size)) =
+        (response.content_length, max_response_size)
+    {
+        if content_length > max_response_size {
+            error!(
+                "Response is {} bytes, max is {}",
+                content_length, max_response_size
+            );
+            return Err(WebCallError::BodyTooLarge);
         }
-    };
+    }
+    Ok(response)
+}
 
+/// Sends a web request and deserializes the whole JSON body from the rx buffer
+pub async fn send_web_request<'buf, C, B, ResponseType>(
+    resource: &mut HttpResource<'_, C>,
+    host: &str,
+    web_request: WebRequest<'_, B>,
+    rx_buffer: &'buf mut [u8],
+) -> Result<ResponseType, WebCallError>
+where
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
+    ResponseType: serde::Deserialize<'buf>,
+{
+    let body = send_web_request_raw(resource, host, web_request, rx_buffer).await?;
+    parse_json(body)
+}
+
+/// Deserializes a whole response body, logging it if it does not match
+pub fn parse_json<'a, ResponseType: serde::Deserialize<'a>>(
+    body: &'a str,
+) -> Result<ResponseType, WebCallError> {
     match serde_json_core::de::from_slice::<ResponseType>(body.as_bytes()) {
         Ok((output, _used)) => Ok(output),
         Err(e) => {
             error!("Response body: {}", body);
             print_serde_json_error(e);
-            return Err(WebCallError::DeserializationError);
+            Err(WebCallError::Json)
         }
     }
 }
 
-/// Wrapper to help you make a GET request that responses with JSON
-pub async fn get_web_request<'a, ResponseType>(
-    http_client: &mut HttpClient<'a, TcpClient<'a, 4>, DnsSocket<'a>>,
-    url: &str,
-    rx_buffer: &'a mut [u8],
-) -> Result<ResponseType, WebCallError>
+/// Same as send_web_request but hands back the body as text, for responses we can not describe with a struct
+/// up front like the office status where the .env says where to look
+pub async fn send_web_request_raw<'buf, C, B>(
+    resource: &mut HttpResource<'_, C>,
+    host: &str,
+    web_request: WebRequest<'_, B>,
+    rx_buffer: &'buf mut [u8],
+) -> Result<&'buf str, WebCallError>
 where
-    ResponseType: serde::Deserialize<'a>,
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
 {
-    let mut request = match http_client.request(Method::GET, &url).await {
-        Ok(req) => req,
-        Err(e) => {
-            error!("Failed to make HTTP request: {:?}", e);
-            return Err(WebCallError::WebRequestError);
-        }
-    };
+    let WebRequest { head, body } = web_request;
+    let max_response_size = head
+        .max_response_size
+        .unwrap_or(rx_buffer.len())
+        .min(rx_buffer.len());
 
-    let response = match request.send(rx_buffer).await {
-        Ok(resp) => resp,
-        Err(_e) => {
-            error!("Failed to send HTTP request");
-            return Err(WebCallError::WebRequestError);
-        }
-    };
+    let request = head.build(host, body)?;
+    let response = send_checked(resource, request, rx_buffer, Some(max_response_size)).await?;
 
-    if !response.status.is_successful() {
-        error!("HTTP request failed with status: {:?}", response.status);
-        return Err(WebCallError::HttpError(response.status.0));
+    let body = response
+        .body()
+        .read_to_end()
+        .await
+        .map_err(reqwless_error)?;
+    if body.len() > max_response_size {
+        error!(
+            "Response is {} bytes, max is {}",
+            body.len(),
+            max_response_size
+        );
+        return Err(WebCallError::BodyTooLarge);
     }
 
-    let body = match from_utf8(response.body().read_to_end().await.unwrap()) {
-        Ok(b) => b,
+    match from_utf8(body) {
+        Ok(body) => Ok(body),
         Err(_e) => {
             error!("Failed to read response body");
-            return Err(WebCallError::FailedToReadResponse);
+            Err(WebCallError::FailedToReadResponse)
         }
-    };
-    match serde_json_core::de::from_slice::<ResponseType>(body.as_bytes()) {
-        Ok((output, _used)) => Ok(output),
-        Err(e) => {
-            print_serde_json_error(e);
-            return Err(WebCallError::DeserializationError);
+    }
+}
+
+/// For calls like updateSeen that only answer with a status. The body is read and thrown away so the
+/// connection can be used again
+pub async fn send_web_request_discarding_body<C, B>(
+    resource: &mut HttpResource<'_, C>,
+    host: &str,
+    web_request: WebRequest<'_, B>,
+    rx_buffer: &mut [u8],
+) -> Result<(), WebCallError>
+where
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
+{
+    let WebRequest { head, body } = web_request;
+    let request = head.build(host, body)?;
+    let response = send_checked(resource, request, rx_buffer, None).await?;
+    response
+        .body()
+        .read_to_end()
+        .await
+        .map_err(reqwless_error)?;
+    Ok(())
+}
+
+/// Sends a web request and streams the array under `array_key` in the response one element at a time,
+/// so the whole body never has to fit in memory. Each element has to fit in `element_buffer`, bigger ones are skipped.
+/// `on_element` returns false to stop early. Returns how many elements were handed over
+pub async fn stream_web_request<C, B, T, F>(
+    resource: &mut HttpResource<'_, C>,
+    host: &str,
+    web_request: WebRequest<'_, B>,
+    rx_buffer: &mut [u8],
+    array_key: &str,
+    element_buffer: &mut [u8],
+    mut on_element: F,
+) -> Result<usize, WebCallError>
+where
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
+    T: DeserializeOwned,
+    F: FnMut(T) -> bool,
+{
+    let WebRequest { head, body } = web_request;
+    //Size limit is left off on purpose, that is the point of streaming
+    let request = head.build(host, body)?;
+    let response = send_checked(resource, request, rx_buffer, None).await?;
+
+    let mut stream = JsonArrayStream::new(response.body().reader(), element_buffer);
+    stream.find_array(array_key).await.map_err(stream_error)?;
+
+    let mut count = 0;
+    let mut unescape_buffer = [0u8; UNESCAPE_BUFFER_SIZE];
+    while let Some(element) = stream.next_element().await.map_err(stream_error)? {
+        match serde_json_core::de::from_slice_escaped::<T>(element, &mut unescape_buffer) {
+            Ok((value, _used)) => {
+                count += 1;
+                if !on_element(value) {
+                    break;
+                }
+            }
+            Err(e) => {
+                print_serde_json_error(e);
+                warn!("Skipping an element that did not deserialize");
+            }
         }
     }
+    //Read whatever is left so the connection can be used for the next request
+    stream.drain().await;
+    Ok(count)
+}
+
+/// Sends a web request and hands the text body over a line at a time, for formats like iCalendar that are not JSON.
+/// Folded lines come out whole, see LineStream. `on_line` returns false to stop early. Returns how many lines were handed over
+pub async fn stream_lines_web_request<C, B, F>(
+    resource: &mut HttpResource<'_, C>,
+    host: &str,
+    web_request: WebRequest<'_, B>,
+    rx_buffer: &mut [u8],
+    line_buffer: &mut [u8],
+    mut on_line: F,
+) -> Result<usize, WebCallError>
+where
+    C: embedded_io_async::Read + embedded_io_async::Write,
+    B: RequestBody,
+    F: FnMut(&str) -> bool,
+{
+    let WebRequest { head, body } = web_request;
+    let request = head.build(host, body)?;
+    let response = send_checked(resource, request, rx_buffer, None).await?;
+
+    let mut lines = LineStream::new(response.body().reader(), line_buffer);
+    let mut count = 0;
+    while let Some(line) = lines.next_line().await.map_err(stream_error)? {
+        count += 1;
+        if !on_line(line) {
+            //Read whatever is left so the connection can be used for the next request
+            while let Ok(Some(_)) = lines.next_line().await {}
+            break;
+        }
+    }
+    Ok(count)
+}
+
+fn stream_error(e: StreamError) -> WebCallError {
+    error!("Failed to stream the response: {:?}", e);
+    match e {
+        StreamError::Read => WebCallError::FailedToReadResponse,
+        StreamError::UnexpectedEnd | StreamError::ArrayNotFound => WebCallError::Json,
+    }
 }
 
 //HACK probably a better way to print this
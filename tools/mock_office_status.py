@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+"""Fake clock in/out server for trying out the office status on the display.
+
+GET /status answers {"clocked_in": true} (the default OFFICE_STATUS_PATH).
+POST /toggle flips it, POST /in and POST /out set it.
+
+Point the display at it in the .env with OFFICE_STATUS_URL="http://<your computer's ip>:8080/status"
+"""
+
+import argparse
+import json
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+clocked_in = True
+
+
+class Handler(BaseHTTPRequestHandler):
+    def do_GET(self):
+        if self.path != "/status":
+            self.send_error(404)
+            return
+        self.send_json({"clocked_in": clocked_in})
+
+    def do_POST(self):
+        global clocked_in
+        if self.path == "/toggle":
+            clocked_in = not clocked_in
+        elif self.path == "/in":
+            clocked_in = True
+        elif self.path == "/out":
+            clocked_in = False
+        else:
+            self.send_error(404)
+            return
+        self.send_json({"clocked_in": clocked_in})
+
+    def send_json(self, value):
+        body = json.dumps(value).encode()
+        self.send_response(200)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--port", type=int, default=8080)
+    args = parser.parse_args()
+    print(f"Serving the office status on port {args.port}, POST /toggle to clock in or out")
+    HTTPServer(("0.0.0.0", args.port), Handler).serve_forever()
@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+"""Turns a screenshot of the display into a PNG for looking over layouts without the panel.
+
+Takes the buddy's /screenshot address or a .pbm or .bmp file saved from it:
+
+    python3 tools/screenshot.py http://<buddy ip>/screenshot screen.png
+    python3 tools/screenshot.py screen.bmp screen.png --scale 2
+
+Only needs the standard library.
+"""
+
+import argparse
+import struct
+import sys
+import urllib.request
+import zlib
+
+WIDTH = 400
+HEIGHT = 300
+
+
+def read_pbm(data):
+    """Rows of pixels from a binary PBM, True is black"""
+    fields = []
+    position = 2
+    while len(fields) < 2:
+        while data[position : position + 1].isspace():
+            position += 1
+        if data[position : position + 1] == b"#":
+            position = data.index(b"\n", position) + 1
+            continue
+        end = position
+        while not data[end : end + 1].isspace():
+            end += 1
+        fields.append(int(data[position:end]))
+        position = end
+    width, height = fields
+    position += 1
+    row_size = (width + 7) // 8
+    return [
+        [bool(data[position + y * row_size + x // 8] & (0x80 >> (x % 8))) for x in range(width)]
+        for y in range(height)
+    ]
+
+
+def read_bmp(data):
+    """Rows of pixels from a 1 bit BMP like the buddy makes, True is black"""
+    offset, = struct.unpack_from("<I", data, 10)
+    width, height, _, bits = struct.unpack_from("<iiHH", data, 18)
+    if bits != 1:
+        sys.exit(f"Only 1 bit BMPs, this one is {bits} bit")
+    # The palette says which bit value is black
+    black_bit = 0 if data[54:57] == b"\x00\x00\x00" else 1
+    row_size = ((width + 31) // 32) * 4
+    rows = []
+    for row in range(abs(height)):
+        start = offset + row * row_size
+        rows.append(
+            [((data[start + x // 8] >> (7 - x % 8)) & 1) == black_bit for x in range(width)]
+        )
+    # Positive height is bottom up
+    return rows[::-1] if height > 0 else rows
+
+
+def write_png(path, rows, scale):
+    width = len(rows[0]) * scale
+    raw = bytearray()
+    for row in rows:
+        line = bytearray()
+        for black in row:
+            line += (b"\x00" if black else b"\xff") * scale
+        for _ in range(scale):
+            raw += b"\x00" + line
+
+    def chunk(kind, body):
+        return (
+            struct.pack(">I", len(body))
+            + kind
+            + body
+            + struct.pack(">I", zlib.crc32(kind + body) & 0xFFFFFFFF)
+        )
+
+    with open(path, "wb") as png:
+        png.write(b"\x89PNG\r\n\x1a\n")
+        # 8 bit grayscale
+        png.write(chunk(b"IHDR", struct.pack(">IIBBBBB", width, len(rows) * scale, 8, 0, 0, 0, 0)))
+        png.write(chunk(b"IDAT", zlib.compress(bytes(raw), 9)))
+        png.write(chunk(b"IEND", b""))
+
+
+def load(source, token):
+    if source.startswith(("http://", "https://")):
+        request = urllib.request.Request(source)
+        if token:
+            request.add_header("Authorization", f"Bearer {token}")
+        with urllib.request.urlopen(request, timeout=30) as response:
+            return response.read()
+    with open(source, "rb") as file:
+        return file.read()
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("source", help="the buddy's /screenshot address, or a .pbm or .bmp file")
+    parser.add_argument("output", help="PNG to write")
+    parser.add_argument("--scale", type=int, default=1, help="make each pixel this many pixels wide")
+    parser.add_argument("--token", default="", help="API_TOKEN if the buddy has one set")
+    args = parser.parse_args()
+
+    data = load(args.source, args.token)
+    if data.startswith(b"P4"):
+        rows = read_pbm(data)
+    elif data.startswith(b"BM"):
+        rows = read_bmp(data)
+    else:
+        sys.exit("Not a binary PBM or a BMP")
+    scale = max(args.scale, 1)
+    write_png(args.output, rows, scale)
+    print(f"Wrote {args.output}, {len(rows[0]) * scale}x{len(rows) * scale}")
@@ -0,0 +1,147 @@
+use crate::date::{days_from_civil, is_leap_year};
+use heapless::Vec;
+
+/// Most birthdays and anniversaries together
+pub const MAX_ANNUAL_EVENTS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AnnualKind {
+    Birthday,
+    Anniversary,
+}
+
+/// A date that comes around every year, like a birthday
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AnnualEvent {
+    pub name: &'static str,
+    pub month: u8,
+    pub day: u8,
+    /// Year it started if it was given, for how old or how many years
+    pub year: Option<u16>,
+    pub kind: AnnualKind,
+}
+
+impl AnnualEvent {
+    /// The day it falls on in `year`. Feb 29 is kept on Feb 28 when it is not a leap year
+    pub fn day_in(&self, year: u16) -> u8 {
+        if self.month == 2 && self.day == 29 && !is_leap_year(year as i32) {
+            return 28;
+        }
+        self.day
+    }
+
+    /// How many years it has been on its day in `year`, if we know when it started
+    pub fn years_in(&self, year: u16) -> Option<u16> {
+        self.year
+            .and_then(|started| year.checked_sub(started))
+            .filter(|years| *years > 0)
+    }
+}
+
+/// Birthdays and anniversaries from BIRTHDAYS and ANNIVERSARIES in the .env
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AnnualEvents {
+    pub events: Vec<AnnualEvent, MAX_ANNUAL_EVENTS>,
+    /// Days ahead to start reminding about the next one. 0 turns reminders off
+    pub reminder_days: u16,
+}
+
+impl AnnualEvents {
+    /// Lists look like "12-08 Sam, 1990-04-16 Alex", the year is optional
+    pub fn parse(birthdays: &'static str, anniversaries: &'static str, reminder_days: u16) -> Self {
+        let mut events = Vec::new();
+        for (list, kind) in [
+            (birthdays, AnnualKind::Birthday),
+            (anniversaries, AnnualKind::Anniversary),
+        ] {
+            for entry in list.split(',') {
+                let entry = entry.trim();
+                if entry.is_empty() {
+                    continue;
+                }
+                let Some(event) = parse_entry(entry, kind) else {
+                    error!("{} should look like 12-08 Sam or 1990-12-08 Sam", entry);
+                    continue;
+                };
+                if events.push(event).is_err() {
+                    error!(
+                        "Only room for {} birthdays and anniversaries",
+                        MAX_ANNUAL_EVENTS
+                    );
+                    break;
+                }
+            }
+        }
+        Self {
+            events,
+            reminder_days,
+        }
+    }
+
+    /// Everything that falls on this date
+    pub fn on(&self, year: u16, month: u8, day: u8) -> impl Iterator<Item = &AnnualEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.month == month && event.day_in(year) == day)
+    }
+
+    pub fn any_on(&self, year: u16, month: u8, day: u8) -> bool {
+        self.on(year, month, day).next().is_some()
+    }
+
+    /// The closest one coming up within the reminder days, not counting today, with how many days away it is
+    pub fn next_reminder(&self, year: u16, month: u8, day: u8) -> Option<(&AnnualEvent, u16)> {
+        let today = days_from_civil(year as i32, month, day);
+        self.events
+            .iter()
+            .filter_map(|event| {
+                //Next time it comes around, this year or next
+                let this_year =
+                    days_from_civil(year as i32, event.month, event.day_in(year)) - today;
+                let days_until = match this_year {
+                    1.. => this_year,
+                    _ => {
+                        days_from_civil(year as i32 + 1, event.month, event.day_in(year + 1))
+                            - today
+                    }
+                };
+                Some((event, u16::try_from(days_until).ok()?))
+            })
+            .filter(|(_, days_until)| *days_until <= self.reminder_days)
+            .min_by_key(|(_, days_until)| *days_until)
+    }
+}
+
+/// 12-08 Sam or 1990-12-08 Sam. Slashes work too
+fn parse_entry(entry: &'static str, kind: AnnualKind) -> Option<AnnualEvent> {
+    let (date, name) = entry.split_once(' ')?;
+    let name = name.trim();
+    let mut parts = date.split(['-', '/']);
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(month), Some(day), None, None) => (None, month, day),
+        (Some(year), Some(month), Some(day), None) => (Some(year.parse().ok()?), month, day),
+        _ => return None,
+    };
+    let month = month.parse::<u8>().ok()?;
+    let day = day.parse::<u8>().ok()?;
+    //Checked against a leap year so Feb 29 is allowed
+    let days_in_month = match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if name.is_empty() || day == 0 || day > days_in_month {
+        return None;
+    }
+    Some(AnnualEvent {
+        name,
+        month,
+        day,
+        year,
+        kind,
+    })
+}
@@ -0,0 +1,217 @@
+use crate::date::DateTime;
+use core::f64::consts::PI;
+use libm::{acos, asin, cos, floor, sin, tan};
+
+/// Average days between two new moons
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// Julian day of a known new moon (2000-01-06 18:14 UTC) everything is counted from
+const KNOWN_NEW_MOON: f64 = 2451550.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    /// Short name that fits next to the moon glyph
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            MoonPhase::New => "New",
+            MoonPhase::WaxingCrescent => "Wax Cres",
+            MoonPhase::FirstQuarter => "1st Qtr",
+            MoonPhase::WaxingGibbous => "Wax Gib",
+            MoonPhase::Full => "Full",
+            MoonPhase::WaningGibbous => "Wan Gib",
+            MoonPhase::LastQuarter => "Last Qtr",
+            MoonPhase::WaningCrescent => "Wan Cres",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MoonInfo {
+    /// Days since the last new moon
+    pub age: f64,
+    /// How far through the cycle we are. 0.0 is new, 0.5 is full
+    pub cycle_fraction: f64,
+    /// Lit part of the moon from 0.0 to 1.0
+    pub illumination: f64,
+    pub phase: MoonPhase,
+}
+
+/// Sun times for a day in minutes after local midnight.
+/// None if the sun does not cross that angle that day, like the polar summer or winter
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SunTimes {
+    pub civil_dawn: Option<u16>,
+    pub sunrise: Option<u16>,
+    pub sunset: Option<u16>,
+    pub civil_dusk: Option<u16>,
+    /// Only used when there is no sunrise or sunset. True for the midnight sun
+    pub always_up: bool,
+}
+
+impl SunTimes {
+    pub fn is_daytime(&self, date_time: &DateTime) -> bool {
+        let now = date_time.hour as u16 * 60 + date_time.minute as u16;
+        match (self.sunrise, self.sunset) {
+            (Some(sunrise), Some(sunset)) => now >= sunrise && now < sunset,
+            _ => self.always_up,
+        }
+    }
+}
+
+/// Julian day from the RTC's local time. `utc_offset_seconds` is the same as Open-Meteo sends back
+pub fn julian_day(date_time: &DateTime, utc_offset_seconds: i64) -> f64 {
+    let mut year = date_time.year as f64;
+    let mut month = date_time.month as f64;
+    if month <= 2.0 {
+        year -= 1.0;
+        month += 12.0;
+    }
+
+    //Gregorian calendar correction
+    let century = floor(year / 100.0);
+    let correction = 2.0 - century + floor(century / 4.0);
+
+    let day_fraction =
+        (date_time.hour as f64 + date_time.minute as f64 / 60.0 + date_time.second as f64 / 3600.0)
+            / 24.0
+            - utc_offset_seconds as f64 / 86_400.0;
+
+    floor(365.25 * (year + 4716.0))
+        + floor(30.6001 * (month + 1.0))
+        + date_time.day as f64
+        + day_fraction
+        + correction
+        - 1524.5
+}
+
+/// Works out the moon phase using the mean lunar cycle. Can be off by about half a day, which is plenty for a glyph
+pub fn moon_info(date_time: &DateTime, utc_offset_seconds: i64) -> MoonInfo {
+    let days_since_new_moon = julian_day(date_time, utc_offset_seconds) - KNOWN_NEW_MOON;
+    let cycles = days_since_new_moon / SYNODIC_MONTH;
+    let cycle_fraction = cycles - floor(cycles);
+
+    let phase = match floor(cycle_fraction * 8.0 + 0.5) as u8 % 8 {
+        0 => MoonPhase::New,
+        1 => MoonPhase::WaxingCrescent,
+        2 => MoonPhase::FirstQuarter,
+        3 => MoonPhase::WaxingGibbous,
+        4 => MoonPhase::Full,
+        5 => MoonPhase::WaningGibbous,
+        6 => MoonPhase::LastQuarter,
+        _ => MoonPhase::WaningCrescent,
+    };
+
+    MoonInfo {
+        age: cycle_fraction * SYNODIC_MONTH,
+        cycle_fraction,
+        illumination: (1.0 - cos(2.0 * PI * cycle_fraction)) / 2.0,
+        phase,
+    }
+}
+
+/// Sunrise, sunset and civil twilight for the RTC's date using the NOAA solar calculator equations.
+/// Good to about a minute, which is more than the display shows
+pub fn sun_times(
+    date_time: &DateTime,
+    latitude: f64,
+    longitude: f64,
+    utc_offset_seconds: i64,
+) -> SunTimes {
+    let utc_offset_minutes = utc_offset_seconds as f64 / 60.0;
+
+    //Everything is worked out for local noon
+    let noon = DateTime {
+        hour: 12,
+        minute: 0,
+        second: 0,
+        ..date_time.clone()
+    };
+    let julian_century = (julian_day(&noon, utc_offset_seconds) - 2451545.0) / 36525.0;
+    let (declination, equation_of_time) = solar_declination_and_equation_of_time(julian_century);
+
+    //Minutes after local midnight
+    let solar_noon = 720.0 - 4.0 * longitude - equation_of_time + utc_offset_minutes;
+
+    let sunrise_hour_angle = hour_angle(latitude, declination, 90.833);
+    let civil_hour_angle = hour_angle(latitude, declination, 96.0);
+
+    let minutes = |hour_angle: Option<f64>, sign: f64| {
+        hour_angle.map(|hour_angle| {
+            let minutes = solar_noon + sign * 4.0 * hour_angle;
+            (floor(minutes + 0.5) as i32).rem_euclid(1440) as u16
+        })
+    };
+
+    SunTimes {
+        civil_dawn: minutes(civil_hour_angle, -1.0),
+        sunrise: minutes(sunrise_hour_angle, -1.0),
+        sunset: minutes(sunrise_hour_angle, 1.0),
+        civil_dusk: minutes(civil_hour_angle, 1.0),
+        //No sunrise or sunset means it is the midnight sun if the sun is on our side of the equator
+        always_up: sunrise_hour_angle.is_none() && (latitude >= 0.0) == (declination >= 0.0),
+    }
+}
+
+/// Returns the sun's declination in degrees and the equation of time in minutes
+fn solar_declination_and_equation_of_time(julian_century: f64) -> (f64, f64) {
+    let t = julian_century;
+    let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)) % 360.0;
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+
+    let mean_anomaly_radians = mean_anomaly.to_radians();
+    let equation_of_center = sin(mean_anomaly_radians) * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + sin(2.0 * mean_anomaly_radians) * (0.019993 - 0.000101 * t)
+        + sin(3.0 * mean_anomaly_radians) * 0.000289;
+
+    let true_longitude = mean_longitude + equation_of_center;
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude = true_longitude - 0.00569 - 0.00478 * sin(omega);
+
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * cos(omega)).to_radians();
+
+    let declination = asin(sin(obliquity) * sin(apparent_longitude.to_radians())).to_degrees();
+
+    let y = tan(obliquity / 2.0) * tan(obliquity / 2.0);
+    let mean_longitude_radians = mean_longitude.to_radians();
+    let equation_of_time = 4.0
+        * (y * sin(2.0 * mean_longitude_radians) - 2.0 * eccentricity * sin(mean_anomaly_radians)
+            + 4.0
+                * eccentricity
+                * y
+                * sin(mean_anomaly_radians)
+                * cos(2.0 * mean_longitude_radians)
+            - 0.5 * y * y * sin(4.0 * mean_longitude_radians)
+            - 1.25 * eccentricity * eccentricity * sin(2.0 * mean_anomaly_radians))
+        .to_degrees();
+
+    (declination, equation_of_time)
+}
+
+/// Hour angle in degrees for the sun to reach `zenith` degrees. None if it never gets there that day
+fn hour_angle(latitude: f64, declination: f64, zenith: f64) -> Option<f64> {
+    let latitude = latitude.to_radians();
+    let declination = declination.to_radians();
+    let cos_hour_angle = cos(zenith.to_radians()) / (cos(latitude) * cos(declination))
+        - tan(latitude) * tan(declination);
+
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    Some(acos(cos_hour_angle).to_degrees())
+}
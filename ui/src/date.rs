@@ -0,0 +1,352 @@
+//! Dates without the RTC. The firmware turns the RP2040's DateTime into this one and back
+
+pub const MINUTES_PER_DAY: i64 = 1_440;
+
+/// Same as embassy_rp's so it can be swapped for it at the RTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DayOfWeek {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+}
+
+impl DayOfWeek {
+    /// 0 is Sunday like the time API and the RTC. Anything past 6 is Sunday
+    pub fn from_number(number: u8) -> Self {
+        match number {
+            1 => DayOfWeek::Monday,
+            2 => DayOfWeek::Tuesday,
+            3 => DayOfWeek::Wednesday,
+            4 => DayOfWeek::Thursday,
+            5 => DayOfWeek::Friday,
+            6 => DayOfWeek::Saturday,
+            _ => DayOfWeek::Sunday,
+        }
+    }
+
+    /// Sun, Mon and so on
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            DayOfWeek::Sunday => "Sun",
+            DayOfWeek::Monday => "Mon",
+            DayOfWeek::Tuesday => "Tue",
+            DayOfWeek::Wednesday => "Wed",
+            DayOfWeek::Thursday => "Thu",
+            DayOfWeek::Friday => "Fri",
+            DayOfWeek::Saturday => "Sat",
+        }
+    }
+}
+
+/// Local time, laid out the same as embassy_rp::rtc::DateTime
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub day_of_week: DayOfWeek,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// Minutes since 1970 in UTC for the local time the RTC keeps
+pub fn utc_minutes(local_time: &DateTime, utc_offset_seconds: i64) -> i64 {
+    days_from_civil(local_time.year as i32, local_time.month, local_time.day) * MINUTES_PER_DAY
+        + local_time.hour as i64 * 60
+        + local_time.minute as i64
+        - utc_offset_seconds / 60
+}
+
+/// Days since 1970-01-01
+pub fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = (if month <= 2 { year - 1 } else { year }) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day from days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month as u8, day as u8)
+}
+
+/// 0 is Monday like RRULE weeks
+pub fn weekday(days: i64) -> u8 {
+    //1970-01-01 was a Thursday
+    (days + 3).rem_euclid(7) as u8
+}
+
+pub fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// The day of the week for a date, worked out from the days since 1970
+pub fn day_of_week(year: i32, month: u8, day: u8) -> DayOfWeek {
+    day_of_week_from_days(days_from_civil(year, month, day))
+}
+
+/// The day of the week for days since 1970-01-01
+pub fn day_of_week_from_days(days: i64) -> DayOfWeek {
+    //weekday counts from Monday, DayOfWeek from Sunday
+    DayOfWeek::from_number((weekday(days) + 1) % 7)
+}
+
+/// The date `days` later, or earlier if it is negative. The time of day stays the same
+pub fn add_days(date_time: &DateTime, days: i64) -> DateTime {
+    let days = days_from_civil(date_time.year as i32, date_time.month, date_time.day) + days;
+    let (year, month, day) = civil_from_days(days);
+    DateTime {
+        year: year as u16,
+        month,
+        day,
+        day_of_week: day_of_week_from_days(days),
+        ..date_time.clone()
+    }
+}
+
+/// Whole days from one date to the other, negative if `to` is before `from`. The time of day is left out
+pub fn days_between(from: &DateTime, to: &DateTime) -> i64 {
+    days_from_civil(to.year as i32, to.month, to.day)
+        - days_from_civil(from.year as i32, from.month, from.day)
+}
+
+/// The ISO 8601 week year and week number. Weeks start on a Monday and week 1 is the one with the year's
+/// first Thursday, so the first few days of January can be in the last week of the year before
+pub fn iso_week(year: i32, month: u8, day: u8) -> (i32, u8) {
+    let days = days_from_civil(year, month, day);
+    //The Thursday of the same week decides which year it is in
+    let thursday = days - weekday(days) as i64 + 3;
+    let (week_year, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(week_year, 1, 1)) / 7 + 1;
+    (week_year, week as u8)
+}
+
+/// Why a date or time could not be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    /// Not laid out like 2024-12-10 or 2024-12-10T11:45:00.123+01:00
+    Format,
+    /// Laid out right but not a real date or time, like 2023-02-29 or 25:00
+    OutOfRange,
+}
+
+/// A date and time read by parse_date_time
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// As it was written, in its own offset
+    pub date_time: DateTime,
+    /// The fraction of a second, anything past nanoseconds is dropped
+    pub nanosecond: u32,
+    /// None if it did not have one, like Open-Meteo's times which are already local
+    pub utc_offset_seconds: Option<i32>,
+}
+
+impl Timestamp {
+    /// The same moment in UTC. One without an offset is taken to be UTC already
+    pub fn utc(&self) -> DateTime {
+        let date_time = &self.date_time;
+        let seconds = days_from_civil(date_time.year as i32, date_time.month, date_time.day)
+            * 86_400
+            + date_time.hour as i64 * 3_600
+            + date_time.minute as i64 * 60
+            + date_time.second as i64
+            - self.utc_offset_seconds.unwrap_or(0) as i64;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+        let second_of_day = seconds.rem_euclid(86_400);
+        DateTime {
+            year: year as u16,
+            month,
+            day,
+            day_of_week: day_of_week(year, month, day),
+            hour: (second_of_day / 3_600) as u8,
+            minute: (second_of_day / 60 % 60) as u8,
+            second: (second_of_day % 60) as u8,
+        }
+    }
+}
+
+/// Reads a date like 2024-12-10. The time is midnight
+pub fn parse_date(text: &str) -> Result<DateTime, ParseError> {
+    let mut cursor = Cursor::new(text);
+    let date_time = cursor.date()?;
+    cursor.end()?;
+    Ok(date_time)
+}
+
+/// Reads ISO 8601 and RFC 3339 date times like 2024-12-10T11:45, 2024-12-10T18:03:59.253Z
+/// or 2024-12-10T12:03:59.253687-06:00. The seconds, fraction and offset can each be left off.
+/// A leap second is read as :59 since the RTC can not hold one
+pub fn parse_date_time(text: &str) -> Result<Timestamp, ParseError> {
+    let mut cursor = Cursor::new(text);
+    let mut date_time = cursor.date()?;
+    if !(cursor.eat(b'T') || cursor.eat(b't') || cursor.eat(b' ')) {
+        return Err(ParseError::Format);
+    }
+
+    date_time.hour = cursor.number(2, 0..=23)? as u8;
+    cursor.expect(b':')?;
+    date_time.minute = cursor.number(2, 0..=59)? as u8;
+    let mut nanosecond = 0;
+    if cursor.eat(b':') {
+        date_time.second = cursor.number(2, 0..=60)?.min(59) as u8;
+        if cursor.eat(b'.') || cursor.eat(b',') {
+            nanosecond = cursor.fraction()?;
+        }
+    }
+
+    let utc_offset_seconds = if cursor.eat(b'Z') || cursor.eat(b'z') {
+        Some(0)
+    } else if cursor.eat(b'+') {
+        Some(cursor.offset()?)
+    } else if cursor.eat(b'-') {
+        Some(-cursor.offset()?)
+    } else {
+        None
+    };
+    cursor.end()?;
+
+    Ok(Timestamp {
+        date_time,
+        nanosecond,
+        utc_offset_seconds,
+    })
+}
+
+/// Walks through the text a byte at a time for the parsers
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    /// Moves past `byte` if it is next
+    fn eat(&mut self, byte: u8) -> bool {
+        let next = self.bytes.get(self.position) == Some(&byte);
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        self.eat(byte).then_some(()).ok_or(ParseError::Format)
+    }
+
+    fn end(&self) -> Result<(), ParseError> {
+        (self.position == self.bytes.len())
+            .then_some(())
+            .ok_or(ParseError::Format)
+    }
+
+    /// Exactly `digits` digits, checked against `range`
+    fn number(
+        &mut self,
+        digits: usize,
+        range: core::ops::RangeInclusive<u32>,
+    ) -> Result<u32, ParseError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + digits)
+            .ok_or(ParseError::Format)?;
+        let mut number = 0;
+        for byte in bytes {
+            if !byte.is_ascii_digit() {
+                return Err(ParseError::Format);
+            }
+            number = number * 10 + (byte - b'0') as u32;
+        }
+        self.position += digits;
+        range
+            .contains(&number)
+            .then_some(number)
+            .ok_or(ParseError::OutOfRange)
+    }
+
+    fn date(&mut self) -> Result<DateTime, ParseError> {
+        let year = self.number(4, 0..=9999)? as u16;
+        self.expect(b'-')?;
+        let month = self.number(2, 1..=12)? as u8;
+        self.expect(b'-')?;
+        let day = self.number(2, 1..=days_in_month(year as i32, month) as u32)? as u8;
+        Ok(DateTime {
+            year,
+            month,
+            day,
+            day_of_week: day_of_week(year as i32, month, day),
+            hour: 0,
+            minute: 0,
+            second: 0,
+        })
+    }
+
+    /// The digits after the decimal point as nanoseconds. Needs at least one
+    fn fraction(&mut self) -> Result<u32, ParseError> {
+        let start = self.position;
+        let mut nanosecond = 0;
+        while let Some(byte) = self.bytes.get(self.position).filter(|b| b.is_ascii_digit()) {
+            let place = self.position - start;
+            if place < 9 {
+                nanosecond += (byte - b'0') as u32 * 10u32.pow(8 - place as u32);
+            }
+            self.position += 1;
+        }
+        if self.position == start {
+            return Err(ParseError::Format);
+        }
+        Ok(nanosecond)
+    }
+
+    /// +06:00, +0600 or +06 after the sign, in seconds
+    fn offset(&mut self) -> Result<i32, ParseError> {
+        let hours = self.number(2, 0..=23)?;
+        let minutes = if self.eat(b':') || self.position + 2 <= self.bytes.len() {
+            self.number(2, 0..=59)?
+        } else {
+            0
+        };
+        Ok((hours * 3_600 + minutes * 60) as i32)
+    }
+}
@@ -0,0 +1,1668 @@
+use crate::annual::{AnnualEvent, AnnualEvents, AnnualKind};
+use crate::astronomy::{MoonInfo, SunTimes};
+use crate::date::{day_of_week_from_days, days_between, parse_date, parse_date_time, DateTime};
+use crate::io::easy_format_str;
+use crate::text::{to_ascii, word_wrap};
+use crate::weather::{AirQualityCurrent, Current, CurrentUnits, Daily, ForecastResponse};
+use crate::weather_icons;
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::primitives::{
+    Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle, Triangle,
+};
+use embedded_graphics::{
+    image::Image,
+    mono_font::MonoTextStyleBuilder,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use epd_waveshare::color::Color;
+use heapless::String;
+use libm::{cos, floor, round, roundf, sin, sqrt};
+use serde::Deserialize;
+use tinybmp::Bmp;
+
+//Some display models
+
+///Just a copy of SensorData to have debug, clone and format. Deserializes for the simulator fixtures
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InsideSensorData {
+    pub co2: u16,
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+///How many notifications are kept. The paging view shows NOTIFICATIONS_PER_PAGE at a time
+pub const MAX_NOTIFICATIONS: usize = 6;
+pub const NOTIFICATIONS_PER_PAGE: usize = 2;
+
+///Why a notification was sent. Drawn as a small icon next to who sent it
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationReason {
+    Like,
+    Repost,
+    Follow,
+    Mention,
+    Reply,
+    Quote,
+    Other,
+}
+
+impl NotificationReason {
+    pub fn from_reason(reason: &str) -> Self {
+        match reason {
+            "like" => NotificationReason::Like,
+            "repost" => NotificationReason::Repost,
+            "follow" => NotificationReason::Follow,
+            "mention" => NotificationReason::Mention,
+            "reply" => NotificationReason::Reply,
+            "quote" => NotificationReason::Quote,
+            _ => NotificationReason::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NotificationEntry {
+    ///Display name, or the handle if they have not set one
+    pub author: String<64>,
+    pub reason: NotificationReason,
+    pub is_read: bool,
+    ///When it happened as a julian day in UTC. Shown as how long ago
+    pub indexed_at: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BlueSkyNotificationData {
+    pub unread_notifications: i32,
+    ///Newest first
+    pub notifications: heapless::Vec<NotificationEntry, MAX_NOTIFICATIONS>,
+}
+
+impl BlueSkyNotificationData {
+    pub fn pages(&self) -> usize {
+        self.notifications
+            .len()
+            .div_ceil(NOTIFICATIONS_PER_PAGE)
+            .max(1)
+    }
+}
+
+///The clock in badge under the time
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[serde(rename_all = "snake_case")]
+pub enum OfficeBadge {
+    ClockedIn,
+    ClockedOut,
+    ///Clocked out after lunch on a work day, drawn inverted so it stands out
+    ForgotToClockIn,
+}
+
+///Most posts kept for the feed screen. Kept small since they go through the event channel
+pub const MAX_FEED_POSTS: usize = 5;
+
+///A post for the feed screen. The text has already been through text::to_ascii so profont can draw all of it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FeedPost {
+    ///Display name, or the handle if they have not set one
+    pub author: String<48>,
+    pub handle: String<48>,
+    ///Posts are up to 300 characters
+    pub text: String<320>,
+    ///When it was posted as a julian day in UTC
+    pub created_at: Option<f64>,
+}
+
+/// Calendar events kept for the widget. A couple more than it shows so it still has some once the first ones are over
+pub const MAX_EVENTS: usize = 5;
+
+/// One upcoming meeting. Recurring events show up once per occurrence
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalendarEvent {
+    pub summary: String<48>,
+    /// Minutes since 1970 in UTC
+    pub start: i64,
+    pub end: i64,
+    /// Whole day events like holidays. Start and end are local midnights
+    pub all_day: bool,
+}
+
+///How the daily forecast is laid out. Set with FORECAST_LAYOUT in the .env
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ForecastLayout {
+    ///The original tall box per day with the big weather icon
+    Boxes,
+    ///One short text row per day
+    Rows,
+}
+
+///Forecast widget settings
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ForecastConfig {
+    ///3, 5 or 7 days
+    pub days: usize,
+    pub layout: ForecastLayout,
+}
+
+impl ForecastConfig {
+    ///From FORECAST_DAYS and FORECAST_LAYOUT in the .env. Anything else gets the original 5 day boxes
+    pub fn parse(days: &str, layout: &str) -> Self {
+        let days = match days {
+            "3" => 3,
+            "7" => 7,
+            _ => 5,
+        };
+        let layout = match layout {
+            "rows" => ForecastLayout::Rows,
+            _ => ForecastLayout::Boxes,
+        };
+        Self { days, layout }
+    }
+}
+
+//The draw functions
+
+///Draws one page of the notifications with the unread count. `now` is the current julian day in UTC for the
+///"5m" next to each one, leave it None if the time is not set yet
+pub fn draw_blue_sky_notification(
+    starting_point: Point,
+    notification: &BlueSkyNotificationData,
+    page: usize,
+    now: Option<f64>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, Size::new(240, 48))
+        .into_styled(rectangle_style)
+        .draw(display);
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/bluesky_logo.bmp"),
+        starting_point.x + 10,
+        starting_point.y,
+    );
+
+    let mut formatting_buffer = [0u8; 10];
+    let unread_notifications = easy_format_str(
+        format_args!("{}", notification.unread_notifications),
+        &mut formatting_buffer,
+    );
+
+    //Unread count
+    draw_text(
+        display,
+        unread_notifications.unwrap(),
+        starting_point.x + 40,
+        starting_point.y,
+    );
+
+    let pages = notification.pages();
+    let page = page % pages;
+    if pages > 1 {
+        let mut formatting_buffer = [0u8; 10];
+        let page_text = easy_format_str(
+            format_args!("{}/{}", page + 1, pages),
+            &mut formatting_buffer,
+        );
+        draw_text_font(
+            display,
+            page_text.unwrap(),
+            starting_point.x + 210,
+            starting_point.y + 2,
+            &profont::PROFONT_9_POINT,
+        );
+    }
+
+    let mut y = starting_point.y + 20;
+    for entry in notification
+        .notifications
+        .iter()
+        .skip(page * NOTIFICATIONS_PER_PAGE)
+        .take(NOTIFICATIONS_PER_PAGE)
+    {
+        if !entry.is_read {
+            let _ = Circle::new(Point::new(starting_point.x + 30, y + 3), 5)
+                .into_styled(PrimitiveStyle::with_fill(Color::Black))
+                .draw(display);
+        }
+        draw_notification_icon(
+            display,
+            Point::new(starting_point.x + 40, y + 1),
+            entry.reason,
+        );
+
+        let minutes_ago = match (now, entry.indexed_at) {
+            (Some(now), Some(indexed_at)) => Some(((now - indexed_at).max(0.0) * 1440.0) as u64),
+            _ => None,
+        };
+        let mut age_buffer = [0u8; 10];
+        let age = minutes_ago.map(|minutes| format_age(minutes, &mut age_buffer));
+
+        //ProFont 9 is 6px wide, so 31 characters fit. Leave room for the age
+        let mut formatting_buffer = [0u8; 520];
+        let line = easy_format_str(
+            format_args!("{:.24} {}", entry.author.as_str(), age.unwrap_or("")),
+            &mut formatting_buffer,
+        );
+        draw_text_font(
+            display,
+            line.unwrap(),
+            starting_point.x + 54,
+            y,
+            &profont::PROFONT_9_POINT,
+        );
+        y += 14;
+    }
+}
+
+///The feed screen. Takes the whole display, one post at a time with who posted it and how long ago
+pub fn draw_feed_post(
+    post: &FeedPost,
+    position: usize,
+    post_count: usize,
+    now: Option<f64>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let _ = display.clear(Color::White);
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/bluesky_logo.bmp"),
+        5,
+        5,
+    );
+    //Leave room on the right for the age and position
+    let mut formatting_buffer = [0u8; 80];
+    let author = easy_format_str(
+        format_args!("{:.28}", post.author.as_str()),
+        &mut formatting_buffer,
+    );
+    draw_text_font(display, author.unwrap(), 40, 4, &profont::PROFONT_14_POINT);
+
+    let mut formatting_buffer = [0u8; 80];
+    let handle = easy_format_str(format_args!("@{}", post.handle), &mut formatting_buffer);
+    draw_text_font(display, handle.unwrap(), 40, 24, &profont::PROFONT_9_POINT);
+
+    let minutes_ago = match (now, post.created_at) {
+        (Some(now), Some(created_at)) => Some(((now - created_at).max(0.0) * 1440.0) as u64),
+        _ => None,
+    };
+    let mut age_buffer = [0u8; 10];
+    let age = minutes_ago.map(|minutes| format_age(minutes, &mut age_buffer));
+    let mut formatting_buffer = [0u8; 20];
+    let position_text = easy_format_str(
+        format_args!("{} {}/{}", age.unwrap_or(""), position + 1, post_count),
+        &mut formatting_buffer,
+    );
+    draw_text_font(
+        display,
+        position_text.unwrap(),
+        330,
+        6,
+        &profont::PROFONT_9_POINT,
+    );
+
+    let _ = Line::new(Point::new(5, 40), Point::new(395, 40))
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+        .draw(display);
+
+    let font = &profont::PROFONT_14_POINT;
+    let line_height = font.character_size.height as i32 + 2;
+    let width = (380 / font.character_size.width) as usize;
+    let lines = word_wrap::<16>(&post.text, width);
+    let mut y = 48;
+    for line in lines {
+        if y + line_height > 300 {
+            break;
+        }
+        draw_text_font(display, line, 10, y, font);
+        y += line_height;
+    }
+}
+
+///A message sent from Home Assistant or the local API. Takes the whole display, big and in the middle
+pub fn draw_message(text: &str, display: &mut impl DrawTarget<Color = Color>) {
+    let _ = display.clear(Color::White);
+
+    let text: String<256> = to_ascii(text);
+    let font = &profont::PROFONT_24_POINT;
+    let character_width = font.character_size.width as i32;
+    let line_height = font.character_size.height as i32 + 4;
+    let lines = word_wrap::<9>(&text, (390 / character_width) as usize);
+    let mut y = (300 - lines.len() as i32 * line_height).max(0) / 2;
+    for line in lines {
+        let x = (400 - line.len() as i32 * character_width) / 2;
+        draw_text_font(display, line, x, y, font);
+        y += line_height;
+    }
+}
+
+///Draws the clock in status as a little badge. None clears it
+pub fn draw_office_status(
+    starting_point: Point,
+    possible_badge: Option<OfficeBadge>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let (text, inverted) = match possible_badge {
+        Some(OfficeBadge::ClockedIn) => ("Clocked in", false),
+        Some(OfficeBadge::ClockedOut) => ("Clocked out", false),
+        Some(OfficeBadge::ForgotToClockIn) => ("Clock back in!", true),
+        None => ("", false),
+    };
+    draw_badge(starting_point, text, inverted, display);
+}
+
+///Birthday or anniversary in the badge spot. Inverted on the day, a reminder before that
+pub fn draw_annual_banner(
+    starting_point: Point,
+    event: &AnnualEvent,
+    days_until: u16,
+    year: u16,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let name: String<32> = to_ascii(event.name);
+    let mut formatting_buffer = [0u8; 64];
+    let text = match (event.kind, days_until, event.years_in(year)) {
+        (AnnualKind::Birthday, 0, Some(years)) => easy_format_str(
+            format_args!("{} turns {}!", name, years),
+            &mut formatting_buffer,
+        ),
+        (AnnualKind::Birthday, 0, None) => {
+            easy_format_str(format_args!("{}'s birthday!", name), &mut formatting_buffer)
+        }
+        (AnnualKind::Anniversary, 0, Some(years)) => easy_format_str(
+            format_args!("{} {} years!", name, years),
+            &mut formatting_buffer,
+        ),
+        (AnnualKind::Anniversary, 0, None) => {
+            easy_format_str(format_args!("{} today!", name), &mut formatting_buffer)
+        }
+        (AnnualKind::Birthday, 1, _) => {
+            easy_format_str(format_args!("{}'s bday tmrw", name), &mut formatting_buffer)
+        }
+        (AnnualKind::Birthday, _, _) => easy_format_str(
+            format_args!("{}'s bday in {}d", name, days_until),
+            &mut formatting_buffer,
+        ),
+        (AnnualKind::Anniversary, 1, _) => {
+            easy_format_str(format_args!("{} tmrw", name), &mut formatting_buffer)
+        }
+        (AnnualKind::Anniversary, _, _) => easy_format_str(
+            format_args!("{} in {}d", name, days_until),
+            &mut formatting_buffer,
+        ),
+    };
+    draw_badge(starting_point, text.unwrap_or(""), days_until == 0, display);
+}
+
+/// The badge under the time. Forgetting to clock back in is the most important,
+/// then a birthday today, then the clock in status and last a reminder about a birthday coming up
+pub fn draw_badge_area(
+    starting_point: Point,
+    office_badge: Option<OfficeBadge>,
+    annual_events: &AnnualEvents,
+    possible_today: Option<&DateTime>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    if office_badge == Some(OfficeBadge::ForgotToClockIn) {
+        draw_office_status(starting_point, office_badge, display);
+        return;
+    }
+    let Some(today) = possible_today else {
+        draw_office_status(starting_point, office_badge, display);
+        return;
+    };
+    if let Some(event) = annual_events.on(today.year, today.month, today.day).next() {
+        draw_annual_banner(starting_point, event, 0, today.year, display);
+        return;
+    }
+    if office_badge.is_some() {
+        draw_office_status(starting_point, office_badge, display);
+        return;
+    }
+    match annual_events.next_reminder(today.year, today.month, today.day) {
+        Some((event, days_until)) => {
+            draw_annual_banner(starting_point, event, days_until, today.year, display)
+        }
+        None => draw_office_status(starting_point, None, display),
+    }
+}
+
+///The rounded badge under the time. Empty text just clears it
+fn draw_badge(
+    starting_point: Point,
+    text: &str,
+    inverted: bool,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let size = Size::new(150, 18);
+    let _ = Rectangle::new(starting_point, size)
+        .into_styled(PrimitiveStyle::with_fill(Color::White))
+        .draw(display);
+    if text.is_empty() {
+        return;
+    }
+
+    let badge_style = if inverted {
+        PrimitiveStyle::with_fill(Color::Black)
+    } else {
+        PrimitiveStyle::with_stroke(Color::Black, 1)
+    };
+    let _ =
+        RoundedRectangle::with_equal_corners(Rectangle::new(starting_point, size), Size::new(6, 6))
+            .into_styled(badge_style)
+            .draw(display);
+
+    let (text_color, background_color) = if inverted {
+        (Color::White, Color::Black)
+    } else {
+        (Color::Black, Color::White)
+    };
+    let font = &profont::PROFONT_12_POINT;
+    //Long names get cut off at the edge of the badge. Text is ASCII by now so any byte is on a character
+    let fits = (size.width as usize - 16) / font.character_size.width as usize;
+    let text = &text[..text.len().min(fits)];
+    let style = MonoTextStyleBuilder::new()
+        .font(font)
+        .text_color(text_color)
+        .background_color(background_color)
+        .build();
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+    let _ = Text::with_text_style(
+        text,
+        Point::new(starting_point.x + 8, starting_point.y + 2),
+        style,
+        text_style,
+    )
+    .draw(display);
+}
+
+///Draws the inside sensor data from the scd40 sensor. The sensor is always in celsius
+pub fn draw_scd_data(
+    starting_point: Point,
+    sensor_data: InsideSensorData,
+    fahrenheit: bool,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let mut formatting_buffer = [0u8; 520];
+    let temp = if fahrenheit {
+        easy_format_str(
+            format_args!("{}°F", roundf(sensor_data.temperature * 1.8 + 32.0)),
+            &mut formatting_buffer,
+        )
+    } else {
+        easy_format_str(
+            format_args!("{}°C", roundf(sensor_data.temperature)),
+            &mut formatting_buffer,
+        )
+    };
+
+    let mut formatting_buffer = [0u8; 520];
+    let humidity = easy_format_str(
+        format_args!("{}%", roundf(sensor_data.humidity)),
+        &mut formatting_buffer,
+    );
+    let mut formatting_buffer = [0u8; 520];
+    let co2 = easy_format_str(
+        format_args!("{}ppm", sensor_data.co2),
+        &mut formatting_buffer,
+    );
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/house_fill.bmp"),
+        starting_point.x,
+        starting_point.y,
+    );
+
+    draw_text(
+        display,
+        temp.unwrap(),
+        starting_point.x + 33,
+        starting_point.y,
+    );
+
+    draw_text(
+        display,
+        humidity.unwrap(),
+        starting_point.x + 33,
+        starting_point.y + 15,
+    );
+    draw_text(
+        display,
+        co2.unwrap(),
+        starting_point.x + 33,
+        starting_point.y + 30,
+    );
+}
+
+///Draws the outside air quality from Open-Meteo. Meant to sit under the inside sensor data
+pub fn draw_air_quality(
+    starting_point: Point,
+    air_quality: AirQualityCurrent,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    //White out the old values first since the text length changes
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, Size::new(80, 45))
+        .into_styled(rectangle_style)
+        .draw(display);
+
+    let mut formatting_buffer = [0u8; 520];
+    let aqi = match air_quality.us_aqi {
+        Some(us_aqi) => easy_format_str(
+            format_args!("AQI {} {}", us_aqi, us_aqi_category(us_aqi)),
+            &mut formatting_buffer,
+        ),
+        None => Ok("AQI --"),
+    };
+
+    let mut formatting_buffer = [0u8; 520];
+    let pm2_5 = match air_quality.pm2_5 {
+        Some(pm2_5) => easy_format_str(
+            format_args!("PM2.5 {}", roundf(pm2_5 as f32)),
+            &mut formatting_buffer,
+        ),
+        None => Ok("PM2.5 --"),
+    };
+
+    //Pollen is only available in Europe so fall back to ozone everywhere else
+    let mut formatting_buffer = [0u8; 520];
+    let last_line = match (air_quality.max_pollen(), air_quality.ozone) {
+        (Some(pollen), _) => easy_format_str(
+            format_args!("Pollen {}", roundf(pollen as f32)),
+            &mut formatting_buffer,
+        ),
+        (None, Some(ozone)) => easy_format_str(
+            format_args!("O3 {}", roundf(ozone as f32)),
+            &mut formatting_buffer,
+        ),
+        (None, None) => Ok("O3 --"),
+    };
+
+    draw_text(display, aqi.unwrap(), starting_point.x, starting_point.y);
+    draw_text(
+        display,
+        pm2_5.unwrap(),
+        starting_point.x,
+        starting_point.y + 15,
+    );
+    draw_text(
+        display,
+        last_line.unwrap(),
+        starting_point.x,
+        starting_point.y + 30,
+    );
+}
+
+///Draw time
+pub fn draw_time(date_time: DateTime, display: &mut impl DrawTarget<Color = Color>) {
+    //Need to white out the time before drawing the new time. Differences in date size can leave one digit hanging
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(Point::new(0, 0), Size::new(155, 25))
+        .into_styled(rectangle_style)
+        .draw(display);
+
+    let mut am = true;
+    let twelve_hour = if date_time.hour >= 12 {
+        am = false;
+        if date_time.hour == 12 {
+            12
+        } else {
+            date_time.hour - 12
+        }
+    } else if date_time.hour == 0 {
+        12
+    } else {
+        date_time.hour
+    };
+
+    let am_pm = if am { "AM" } else { "PM" };
+
+    let mut formatting_buffer = [0u8; 520];
+    let formatted_time = easy_format_str(
+        format_args!(
+            "{:02}:{:02} {} {}/{}/{}",
+            twelve_hour, date_time.minute, am_pm, date_time.month, date_time.day, date_time.year
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_text(display, formatted_time.unwrap(), 5, 10);
+}
+
+/// Draw the current outside weather
+pub fn draw_current_outside_weather(
+    starting_point: Point,
+    current: Current,
+    units: CurrentUnits,
+    daytime: bool,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let current_image = match daytime {
+        true => weather_icons::get_weather_icon(current.weather_code).get_icon(),
+        //TODO add more of night icons cause I think its just clear atm
+        false => weather_icons::get_night_weather_icon(current.weather_code).get_icon(),
+    };
+
+    draw_bmp(
+        display,
+        current_image,
+        starting_point.x,
+        starting_point.y - 15,
+    );
+
+    //White out the text column since the wind and feels like text can change length
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(
+        Point::new(starting_point.x + 58, starting_point.y),
+        Size::new(110, 75),
+    )
+    .into_styled(rectangle_style)
+    .draw(display);
+
+    let mut formatting_buffer = [0u8; 520];
+    let current_temp = easy_format_str(
+        format_args!("{}{}", current.temperature_2m, units.temperature_2m),
+        &mut formatting_buffer,
+    );
+
+    draw_text(
+        display,
+        current_temp.unwrap(),
+        starting_point.x + 58,
+        starting_point.y,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let current_humidity = easy_format_str(
+        format_args!(
+            "{}{}",
+            current.relative_humidity_2m, units.relative_humidity_2m
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_text(
+        display,
+        current_humidity.unwrap(),
+        starting_point.x + 58,
+        starting_point.y + 15,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let feels_like = easy_format_str(
+        format_args!(
+            "Feels {}{}",
+            round(current.apparent_temperature),
+            units.apparent_temperature
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_text(
+        display,
+        feels_like.unwrap(),
+        starting_point.x + 58,
+        starting_point.y + 30,
+    );
+
+    //Wind arrow then the speed and gusts next to it
+    draw_wind_arrow(
+        display,
+        Point::new(starting_point.x + 65, starting_point.y + 52),
+        current.wind_direction_10m,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let wind = easy_format_str(
+        format_args!(
+            "{}G{}{}",
+            round(current.wind_speed_10m),
+            round(current.wind_gusts_10m),
+            units.wind_speed_10m
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_text(
+        display,
+        wind.unwrap(),
+        starting_point.x + 75,
+        starting_point.y + 45,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let uv = easy_format_str(
+        format_args!(
+            "UV {} {}",
+            round(current.uv_index),
+            uv_index_level(current.uv_index)
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_text(
+        display,
+        uv.unwrap(),
+        starting_point.x + 58,
+        starting_point.y + 60,
+    );
+}
+
+///Draws the moon phase glyph with its name and illumination. Pass None during the day to clear it
+pub fn draw_moon_phase(
+    starting_point: Point,
+    possible_moon: Option<MoonInfo>,
+    southern_hemisphere: bool,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, Size::new(100, 30))
+        .into_styled(rectangle_style)
+        .draw(display);
+
+    let Some(moon) = possible_moon else {
+        return;
+    };
+
+    draw_moon_glyph(
+        display,
+        Point::new(starting_point.x + 13, starting_point.y + 13),
+        12,
+        moon.cycle_fraction,
+        southern_hemisphere,
+    );
+
+    draw_text(
+        display,
+        moon.phase.short_name(),
+        starting_point.x + 30,
+        starting_point.y,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let illumination = easy_format_str(
+        format_args!("{}%", round(moon.illumination * 100.0)),
+        &mut formatting_buffer,
+    );
+    draw_text(
+        display,
+        illumination.unwrap(),
+        starting_point.x + 30,
+        starting_point.y + 15,
+    );
+}
+
+///Fallback for the forecast area when there is no forecast. Shows the sun times worked out offline
+pub fn draw_sun_times(
+    starting_point: Point,
+    sun_times: SunTimes,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let forecast_box_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::Black)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, Size::new(160, 60))
+        .into_styled(forecast_box_style)
+        .draw(display);
+
+    let mut sunrise_buffer = [0u8; 6];
+    let mut sunset_buffer = [0u8; 6];
+    let mut formatting_buffer = [0u8; 520];
+    let sun_rise_set = easy_format_str(
+        format_args!(
+            "{}-{}",
+            format_minutes_of_day(sun_times.sunrise, &mut sunrise_buffer),
+            format_minutes_of_day(sun_times.sunset, &mut sunset_buffer)
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/weather_icons/small_sun.bmp"),
+        starting_point.x + 5,
+        starting_point.y + 6,
+    );
+    draw_text(
+        display,
+        sun_rise_set.unwrap(),
+        starting_point.x + 35,
+        starting_point.y + 10,
+    );
+
+    let mut dawn_buffer = [0u8; 6];
+    let mut dusk_buffer = [0u8; 6];
+    let mut formatting_buffer = [0u8; 520];
+    let civil_twilight = easy_format_str(
+        format_args!(
+            "{}-{}",
+            format_minutes_of_day(sun_times.civil_dawn, &mut dawn_buffer),
+            format_minutes_of_day(sun_times.civil_dusk, &mut dusk_buffer)
+        ),
+        &mut formatting_buffer,
+    );
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/weather_icons/small_moon.bmp"),
+        starting_point.x + 5,
+        starting_point.y + 32,
+    );
+    draw_text(
+        display,
+        civil_twilight.unwrap(),
+        starting_point.x + 35,
+        starting_point.y + 36,
+    );
+}
+
+///Lists the stale data and how many minutes ago it was updated like "Forecast 3h ago", then any services that are
+///offline. Clears the area when there is nothing to show
+pub fn draw_stale_data(
+    starting_point: Point,
+    stale: &[(&str, u64)],
+    offline: &[&str],
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let rectangle_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::White)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, Size::new(145, 55))
+        .into_styled(rectangle_style)
+        .draw(display);
+
+    let mut y = starting_point.y;
+    for (name, age) in stale {
+        let mut age_buffer = [0u8; 10];
+        let mut formatting_buffer = [0u8; 520];
+        let age_text = easy_format_str(
+            format_args!("{} {} ago", name, format_age(*age, &mut age_buffer)),
+            &mut formatting_buffer,
+        );
+
+        draw_text_font(
+            display,
+            age_text.unwrap(),
+            starting_point.x,
+            y,
+            &profont::PROFONT_9_POINT,
+        );
+        y += 12;
+    }
+
+    //Services the wireless task has stopped trying for a bit. Only room for 4 lines in total
+    for name in offline.iter().take(4usize.saturating_sub(stale.len())) {
+        let mut formatting_buffer = [0u8; 520];
+        let offline_text =
+            easy_format_str(format_args!("{} offline", name), &mut formatting_buffer);
+        draw_text_font(
+            display,
+            offline_text.unwrap(),
+            starting_point.x,
+            y,
+            &profont::PROFONT_9_POINT,
+        );
+        y += 12;
+    }
+}
+
+///Draws the next few calendar events with a countdown to the next meeting above them.
+///Shares the spot with the stale list. `now` is minutes since 1970 in UTC like the events
+pub fn draw_calendar(
+    starting_point: Point,
+    events: &[CalendarEvent],
+    now: i64,
+    utc_offset_minutes: i64,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    //Only room for 24 characters a line in PROFONT_9_POINT
+    const LINE_LENGTH: usize = 24;
+    let _ = Rectangle::new(starting_point, Size::new(145, 55))
+        .into_styled(PrimitiveStyle::with_fill(Color::White))
+        .draw(display);
+
+    let mut upcoming = events.iter().filter(|event| event.end > now).peekable();
+    let mut countdown_buffer = [0u8; 12];
+    let mut formatting_buffer = [0u8; 64];
+    //All day events are not meetings, they would always be on
+    let countdown = match upcoming.clone().find(|event| !event.all_day) {
+        Some(event) if event.start <= now => easy_format_str(
+            format_args!(
+                "Now, ends in {}",
+                format_countdown(event.end - now, &mut countdown_buffer)
+            ),
+            &mut formatting_buffer,
+        ),
+        Some(event) => easy_format_str(
+            format_args!(
+                "Next in {}",
+                format_countdown(event.start - now, &mut countdown_buffer)
+            ),
+            &mut formatting_buffer,
+        ),
+        None if upcoming.peek().is_none() => Ok("Nothing coming up"),
+        None => Ok("No more meetings"),
+    };
+    draw_text_font(
+        display,
+        countdown.unwrap_or(""),
+        starting_point.x,
+        starting_point.y,
+        &profont::PROFONT_9_POINT,
+    );
+
+    let today = (now + utc_offset_minutes).div_euclid(1_440);
+    let mut y = starting_point.y + 12;
+    for event in upcoming.take(3) {
+        let local_start = event.start + utc_offset_minutes;
+        let day = local_start.div_euclid(1_440);
+        let day_name = day_of_week_from_days(day).short_name();
+        let minutes = local_start.rem_euclid(1_440);
+        let mut line_buffer = [0u8; 96];
+        let line = match (event.all_day, day == today) {
+            (true, true) => {
+                easy_format_str(format_args!("Today {}", event.summary), &mut line_buffer)
+            }
+            (true, false) => easy_format_str(
+                format_args!("{} {}", day_name, event.summary),
+                &mut line_buffer,
+            ),
+            (false, true) => easy_format_str(
+                format_args!("{:02}:{:02} {}", minutes / 60, minutes % 60, event.summary),
+                &mut line_buffer,
+            ),
+            (false, false) => easy_format_str(
+                format_args!(
+                    "{} {:02}:{:02} {}",
+                    day_name,
+                    minutes / 60,
+                    minutes % 60,
+                    event.summary
+                ),
+                &mut line_buffer,
+            ),
+        }
+        .unwrap_or("");
+        //Summaries have been through to_ascii so this is always on a character
+        let line = &line[..line.len().min(LINE_LENGTH)];
+        draw_text_font(
+            display,
+            line,
+            starting_point.x,
+            y,
+            &profont::PROFONT_9_POINT,
+        );
+        y += 12;
+    }
+}
+
+///Draws the daily forecast into `area` using the configured layout and number of days
+pub fn draw_forecast(
+    starting_point: Point,
+    area: Size,
+    forecast: &ForecastResponse,
+    config: ForecastConfig,
+    annual_events: &AnnualEvents,
+    possible_current_datetime: Option<DateTime>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    //Open-Meteo could send back less days than we want to show
+    let days = config.days.min(forecast.daily.complete_days());
+    if days == 0 {
+        return;
+    }
+    //I think all units are the same so just going to use this one
+    let unit = &forecast.daily_units.temperature_2m_max;
+    let mut day_starting_point = starting_point;
+
+    match config.layout {
+        ForecastLayout::Boxes => {
+            let forecast_box_width = area.width / days as u32;
+            for i in 0..days {
+                draw_weather_forecast_box(
+                    day_starting_point,
+                    forecast_box_width,
+                    &forecast.daily.time[i],
+                    unit,
+                    forecast.daily.temperature_2m_max[i],
+                    forecast.daily.temperature_2m_min[i],
+                    forecast.daily.weather_code[i],
+                    forecast.daily.sunrise[i].clone(),
+                    forecast.daily.sunset[i].clone(),
+                    annual_events,
+                    possible_current_datetime.clone(),
+                    display,
+                );
+                day_starting_point.x += forecast_box_width as i32;
+            }
+        }
+        ForecastLayout::Rows => {
+            let row_size = Size::new(area.width, area.height / days as u32);
+            for i in 0..days {
+                draw_weather_forecast_row(
+                    day_starting_point,
+                    row_size,
+                    &forecast.daily,
+                    unit,
+                    annual_events,
+                    possible_current_datetime.clone(),
+                    i,
+                    display,
+                );
+                day_starting_point.y += row_size.height as i32;
+            }
+        }
+    }
+}
+
+///Compact version of the forecast box. Everything on one line with a text description instead of the icon
+#[allow(clippy::too_many_arguments)]
+pub fn draw_weather_forecast_row(
+    starting_point: Point,
+    row_size: Size,
+    daily: &Daily,
+    units: &str,
+    annual_events: &AnnualEvents,
+    possible_current_datetime: Option<DateTime>,
+    index: usize,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    let forecast_row_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::Black)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    let _ = Rectangle::new(starting_point, row_size)
+        .into_styled(forecast_row_style)
+        .draw(display);
+
+    //Center the text in the row
+    let text_y = starting_point.y + (row_size.height as i32 - 15) / 2;
+
+    let possible_date = parse_date(&daily.time[index]).ok();
+    let mut formatting_buffer = [0u8; 520];
+    let month_day = format_month_day(possible_date.as_ref(), &mut formatting_buffer);
+
+    draw_text(
+        display,
+        forecast_day_label(
+            month_day,
+            possible_date.as_ref(),
+            possible_current_datetime.as_ref(),
+        ),
+        starting_point.x + 5,
+        text_y,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let max_min_text = easy_format_str(
+        format_args!(
+            "{}{}/{}{}",
+            floor(daily.temperature_2m_max[index]),
+            units,
+            floor(daily.temperature_2m_min[index]),
+            units
+        ),
+        &mut formatting_buffer,
+    );
+    draw_text(
+        display,
+        max_min_text.unwrap(),
+        starting_point.x + 45,
+        text_y,
+    );
+
+    draw_text(
+        display,
+        weather_icons::get_weather_description(daily.weather_code[index]),
+        starting_point.x + 135,
+        text_y,
+    );
+
+    let mut formatting_buffer = [0u8; 520];
+    let precipitation = easy_format_str(
+        format_args!("{}%", daily.precipitation_probability_max[index]),
+        &mut formatting_buffer,
+    );
+    draw_text(
+        display,
+        precipitation.unwrap(),
+        starting_point.x + 212,
+        text_y,
+    );
+
+    let mut sunrise_buffer = [0u8; 8];
+    let mut sunset_buffer = [0u8; 8];
+    let mut formatting_buffer = [0u8; 520];
+    let sun_rise_set = easy_format_str(
+        format_args!(
+            "{}-{}",
+            format_minutes_of_day(minutes_of_day(&daily.sunrise[index]), &mut sunrise_buffer),
+            format_minutes_of_day(minutes_of_day(&daily.sunset[index]), &mut sunset_buffer)
+        ),
+        &mut formatting_buffer,
+    );
+    draw_text(
+        display,
+        sun_rise_set.unwrap(),
+        starting_point.x + 250,
+        text_y,
+    );
+
+    if possible_date.is_some_and(|date| annual_events.any_on(date.year, date.month, date.day)) {
+        draw_bmp(
+            display,
+            include_bytes!("../../images/birthday_cake.bmp"),
+            starting_point.x + row_size.width as i32 - 20,
+            starting_point.y + (row_size.height as i32 - 14) / 2,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_weather_forecast_box(
+    starting_point: Point,
+    forecast_box_width: u32,
+    daily_date: &str,
+    units: &str,
+    daily_max_temp: f64,
+    daily_min_temp: f64,
+    daily_weather_code: u8,
+    sun_rise: String<16>,
+    sun_set: String<16>,
+    annual_events: &AnnualEvents,
+    possible_current_datetime: Option<DateTime>,
+    display: &mut impl DrawTarget<Color = Color>,
+) {
+    //TODO need to see about measure icons placement from bottom not top
+    //This is about how some weather icons are taller than others
+    //Updated note 12-06 I think it's fine?
+    let daily_max_rounded = floor(daily_max_temp);
+    let daily_min_rounded = floor(daily_min_temp);
+
+    info!(
+        "Date: {:?}, Max Temp: {:?}, Min Temp: {:?}, Weather Code: {:?}",
+        daily_date, daily_max_temp, daily_min_temp, daily_weather_code
+    );
+
+    //Narrow boxes are used for the 7 day forecast and do not have room for the units or sun icons
+    let narrow = forecast_box_width < 80;
+
+    //Keeps the 64px weather icons from bleeding into the next box when they are narrow
+    let display = &mut display.clipped(&Rectangle::new(
+        starting_point,
+        Size::new(forecast_box_width, 150),
+    ));
+
+    //forecast box style
+    let forecast_box_style = PrimitiveStyleBuilder::new()
+        .stroke_color(Color::Black)
+        .stroke_width(1)
+        .fill_color(Color::White)
+        .build();
+
+    //Top of rectangle showing date
+    let _ = Rectangle::new(starting_point, Size::new(forecast_box_width, 25))
+        .into_styled(forecast_box_style)
+        .draw(display);
+
+    //Outline of the daily forecast box
+    let _ = Rectangle::new(
+        Point::new(starting_point.x, starting_point.y + 25),
+        Size::new(forecast_box_width, 125),
+    )
+    .into_styled(forecast_box_style)
+    .draw(display);
+
+    // Writing the forecast content
+    let possible_date = parse_date(daily_date).ok();
+
+    let mut sunrise_buffer = [0u8; 8];
+    let mut sunset_buffer = [0u8; 8];
+    let sun_rise_time = format_minutes_of_day(minutes_of_day(&sun_rise), &mut sunrise_buffer);
+    let sun_set_time = format_minutes_of_day(minutes_of_day(&sun_set), &mut sunset_buffer);
+
+    //Month/day text
+    let mut formatting_buffer = [0u8; 520];
+    let month_day = format_month_day(possible_date.as_ref(), &mut formatting_buffer);
+
+    draw_text(
+        display,
+        forecast_day_label(
+            month_day,
+            possible_date.as_ref(),
+            possible_current_datetime.as_ref(),
+        ),
+        starting_point.x + if narrow { 5 } else { 16 },
+        starting_point.y + 6,
+    );
+
+    if possible_date.is_some_and(|date| annual_events.any_on(date.year, date.month, date.day)) {
+        draw_bmp(
+            display,
+            include_bytes!("../../images/birthday_cake_24.bmp"),
+            starting_point.x + forecast_box_width as i32 - 26,
+            starting_point.y + 1,
+        );
+    }
+
+    //Draw weather icon
+    //TODO check precipitation_probability_max to decide if to show rain. then can check
+    //if over freezing to decide if snow?
+    draw_bmp(
+        display,
+        weather_icons::get_weather_icon(daily_weather_code).get_icon(),
+        starting_point.x + if narrow { -4 } else { 10 },
+        starting_point.y + 45,
+    );
+
+    //Max and min temp
+    let mut formatting_buffer = [0u8; 520];
+    let max_min_text = if narrow {
+        easy_format_str(
+            format_args!("{}/{}", daily_max_rounded, daily_min_rounded),
+            &mut formatting_buffer,
+        )
+    } else {
+        easy_format_str(
+            format_args!(
+                "{}{}/{}{}",
+                daily_max_rounded, units, daily_min_rounded, units
+            ),
+            &mut formatting_buffer,
+        )
+    };
+    draw_text(
+        display,
+        max_min_text.unwrap(),
+        starting_point.x + 5,
+        starting_point.y + 35,
+    );
+
+    //Sun set and rise section
+    if narrow {
+        //No room for the sun and moon icons so just the times
+        draw_text(
+            display,
+            sun_rise_time,
+            starting_point.x + 5,
+            starting_point.y + 105,
+        );
+        draw_text(
+            display,
+            sun_set_time,
+            starting_point.x + 5,
+            starting_point.y + 130,
+        );
+        return;
+    }
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/weather_icons/small_sun.bmp"),
+        starting_point.x + 1,
+        starting_point.y + 100,
+    );
+
+    draw_text(
+        display,
+        sun_rise_time,
+        starting_point.x + 30,
+        starting_point.y + 105,
+    );
+
+    draw_bmp(
+        display,
+        include_bytes!("../../images/weather_icons/small_moon.bmp"),
+        starting_point.x + 1,
+        starting_point.y + 125,
+    );
+
+    draw_text(
+        display,
+        sun_set_time,
+        starting_point.x + 30,
+        starting_point.y + 130,
+    );
+}
+
+/// Short day of the week from the forecast day's own date. A day that has already gone by, from a stale
+/// forecast, gets the month/day instead so it does not look like next week
+fn forecast_day_label<'a>(
+    month_day: &'a str,
+    possible_date: Option<&DateTime>,
+    possible_current_datetime: Option<&DateTime>,
+) -> &'a str {
+    let Some(date) = possible_date else {
+        return month_day;
+    };
+    if possible_current_datetime
+        .is_some_and(|current_datetime| days_between(current_datetime, date) < 0)
+    {
+        return month_day;
+    }
+    date.day_of_week.short_name()
+}
+
+/// 12/10, or --/-- if the forecast had a date we could not read
+fn format_month_day<'a>(possible_date: Option<&DateTime>, buffer: &'a mut [u8]) -> &'a str {
+    match possible_date {
+        Some(date) => easy_format_str(format_args!("{}/{}", date.month, date.day), buffer).unwrap(),
+        None => "--/--",
+    }
+}
+
+/// Minutes after midnight for a forecast time like 2024-12-10T07:45. None if it is not one
+fn minutes_of_day(date_time: &str) -> Option<u16> {
+    let date_time = parse_date_time(date_time).ok()?.date_time;
+    Some(date_time.hour as u16 * 60 + date_time.minute as u16)
+}
+
+/// Formats minutes after midnight as 24 hour HH:MM like the forecast times. --:-- if there is none
+fn format_minutes_of_day(possible_minutes: Option<u16>, buffer: &mut [u8]) -> &str {
+    match possible_minutes {
+        Some(minutes) => easy_format_str(
+            format_args!("{:02}:{:02}", minutes / 60, minutes % 60),
+            buffer,
+        )
+        .unwrap(),
+        None => "--:--",
+    }
+}
+
+///Short age like 5m, 3h or 2d
+fn format_age(minutes: u64, buffer: &mut [u8]) -> &str {
+    let formatted = match minutes {
+        0..=59 => easy_format_str(format_args!("{}m", minutes), buffer),
+        60..=1439 => easy_format_str(format_args!("{}h", minutes / 60), buffer),
+        _ => easy_format_str(format_args!("{}d", minutes / 1440), buffer),
+    };
+    formatted.unwrap_or("")
+}
+
+///Countdown like 25m, 1h25m or 2d
+fn format_countdown(minutes: i64, buffer: &mut [u8]) -> &str {
+    let formatted = match minutes {
+        ..=59 => easy_format_str(format_args!("{}m", minutes.max(0)), buffer),
+        60..=1439 if minutes % 60 == 0 => {
+            easy_format_str(format_args!("{}h", minutes / 60), buffer)
+        }
+        60..=1439 => easy_format_str(
+            format_args!("{}h{:02}m", minutes / 60, minutes % 60),
+            buffer,
+        ),
+        _ => easy_format_str(format_args!("{}d", minutes / 1440), buffer),
+    };
+    formatted.unwrap_or("")
+}
+
+/// Short label for the US AQI categories. Kept short to fit next to the number
+fn us_aqi_category(us_aqi: u16) -> &'static str {
+    match us_aqi {
+        0..=50 => "Good",
+        51..=100 => "Mod",
+        101..=150 => "USG",
+        151..=200 => "Bad",
+        201..=300 => "V Bad",
+        _ => "Haz",
+    }
+}
+
+/// WHO UV index exposure levels
+fn uv_index_level(uv_index: f64) -> &'static str {
+    match round(uv_index) as u8 {
+        0..=2 => "Low",
+        3..=5 => "Mod",
+        6..=7 => "High",
+        8..=10 => "V High",
+        _ => "Ext",
+    }
+}
+
+//drawing helpers
+
+/// Draws a small arrow centered on `center` pointing the way the wind is blowing.
+/// `wind_direction` is the meteorological direction (where the wind comes from) in degrees
+fn draw_wind_arrow(
+    display: &mut impl DrawTarget<Color = Color>,
+    center: Point,
+    wind_direction: f64,
+) {
+    //Flip it around so the arrow points where the wind is going
+    let radians = (wind_direction + 180.0).to_radians();
+    //Screen y grows down so north is -y
+    let (dx, dy) = (sin(radians), -cos(radians));
+    let length = 6.0;
+
+    let tip = Point::new(
+        center.x + round(dx * length) as i32,
+        center.y + round(dy * length) as i32,
+    );
+    let tail = Point::new(
+        center.x - round(dx * length) as i32,
+        center.y - round(dy * length) as i32,
+    );
+    //The two back corners of the arrow head are perpendicular to the shaft
+    let head_base = Point::new(
+        center.x + round(dx * 1.0) as i32,
+        center.y + round(dy * 1.0) as i32,
+    );
+    let head_left = Point::new(
+        head_base.x + round(-dy * 4.0) as i32,
+        head_base.y + round(dx * 4.0) as i32,
+    );
+    let head_right = Point::new(
+        head_base.x - round(-dy * 4.0) as i32,
+        head_base.y - round(dx * 4.0) as i32,
+    );
+
+    let _ = Line::new(tail, tip)
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 2))
+        .draw(display);
+    let _ = Triangle::new(tip, head_left, head_right)
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(display);
+}
+
+/// Draws the moon as an outline with the unlit part filled in black.
+/// In the northern hemisphere the right side lights up first, the south sees it mirrored
+fn draw_moon_glyph(
+    display: &mut impl DrawTarget<Color = Color>,
+    center: Point,
+    radius: i32,
+    cycle_fraction: f64,
+    southern_hemisphere: bool,
+) {
+    let _ = Circle::with_center(center, (radius * 2 + 1) as u32)
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+        .draw(display);
+
+    let terminator = cos(2.0 * core::f64::consts::PI * cycle_fraction);
+    let waxing = cycle_fraction < 0.5;
+    let dark_style = PrimitiveStyle::with_stroke(Color::Black, 1);
+
+    for dy in -radius..=radius {
+        let half_width = sqrt((radius * radius - dy * dy) as f64);
+        //Dark part of this row from left to right with x relative to the center
+        let (mut left, mut right) = if waxing {
+            (-half_width, half_width * terminator)
+        } else {
+            (-half_width * terminator, half_width)
+        };
+        if southern_hemisphere {
+            (left, right) = (-right, -left);
+        }
+        if right - left < 0.5 {
+            continue;
+        }
+
+        let _ = Line::new(
+            Point::new(center.x + round(left) as i32, center.y + dy),
+            Point::new(center.x + round(right) as i32, center.y + dy),
+        )
+        .into_styled(dark_style)
+        .draw(display);
+    }
+}
+
+/// 9px icons for the notification reasons, drawn with primitives so they do not need their own bmps
+fn draw_notification_icon(
+    display: &mut impl DrawTarget<Color = Color>,
+    top_left: Point,
+    reason: NotificationReason,
+) {
+    let fill = PrimitiveStyle::with_fill(Color::Black);
+    let stroke = PrimitiveStyle::with_stroke(Color::Black, 1);
+    let at = |x: i32, y: i32| Point::new(top_left.x + x, top_left.y + y);
+
+    match reason {
+        NotificationReason::Like => {
+            let _ = Circle::new(at(0, 0), 5).into_styled(fill).draw(display);
+            let _ = Circle::new(at(4, 0), 5).into_styled(fill).draw(display);
+            let _ = Triangle::new(at(0, 3), at(8, 3), at(4, 8))
+                .into_styled(fill)
+                .draw(display);
+        }
+        NotificationReason::Repost => {
+            //Arrow going right on top and one coming back on the bottom
+            let _ = Line::new(at(0, 2), at(6, 2))
+                .into_styled(stroke)
+                .draw(display);
+            let _ = Triangle::new(at(6, 0), at(8, 2), at(6, 4))
+                .into_styled(fill)
+                .draw(display);
+            let _ = Line::new(at(2, 6), at(8, 6))
+                .into_styled(stroke)
+                .draw(display);
+            let _ = Triangle::new(at(2, 4), at(0, 6), at(2, 8))
+                .into_styled(fill)
+                .draw(display);
+        }
+        NotificationReason::Follow => {
+            let _ = Circle::new(at(1, 0), 4).into_styled(fill).draw(display);
+            let _ = Rectangle::new(at(0, 5), Size::new(6, 4))
+                .into_styled(fill)
+                .draw(display);
+            let _ = Line::new(at(8, 1), at(8, 5))
+                .into_styled(stroke)
+                .draw(display);
+            let _ = Line::new(at(6, 3), at(10, 3))
+                .into_styled(stroke)
+                .draw(display);
+        }
+        NotificationReason::Reply => {
+            //Speech bubble
+            let _ = Rectangle::new(at(0, 0), Size::new(9, 6))
+                .into_styled(stroke)
+                .draw(display);
+            let _ = Triangle::new(at(2, 5), at(5, 5), at(2, 8))
+                .into_styled(fill)
+                .draw(display);
+        }
+        NotificationReason::Quote => {
+            //Two tick marks like a "
+            for x in [1, 5] {
+                let _ = Rectangle::new(at(x, 0), Size::new(3, 3))
+                    .into_styled(fill)
+                    .draw(display);
+                let _ = Line::new(at(x + 2, 3), at(x, 6))
+                    .into_styled(stroke)
+                    .draw(display);
+            }
+        }
+        NotificationReason::Mention => {
+            draw_text_font(
+                display,
+                "@",
+                top_left.x + 1,
+                top_left.y - 2,
+                &profont::PROFONT_9_POINT,
+            );
+        }
+        NotificationReason::Other => {
+            let _ = Circle::new(at(1, 1), 7).into_styled(stroke).draw(display);
+        }
+    }
+}
+
+fn draw_bmp(display: &mut impl DrawTarget<Color = Color>, bmp_data: &[u8], x: i32, y: i32) {
+    let bmp: Bmp<BinaryColor> = Bmp::from_slice(bmp_data).unwrap();
+    let _ = Image::new(&bmp, Point::new(x, y)).draw(&mut display.color_converted());
+}
+
+fn draw_text(display: &mut impl DrawTarget<Color = Color>, text: &str, x: i32, y: i32) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&profont::PROFONT_12_POINT)
+        .text_color(Color::Black)
+        .background_color(Color::White)
+        .build();
+
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let _ = Text::with_text_style(text, Point::new(x, y), style, text_style).draw(display);
+    debug!("Draw text: {:?}", text);
+}
+
+fn draw_text_font(
+    display: &mut impl DrawTarget<Color = Color>,
+    text: &str,
+    x: i32,
+    y: i32,
+    font: &MonoFont,
+) {
+    let style = MonoTextStyleBuilder::new()
+        .font(font)
+        .text_color(Color::Black)
+        .background_color(Color::White)
+        .build();
+
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let _ = Text::with_text_style(text, Point::new(x, y), style, text_style).draw(display);
+    debug!("Draw text: {:?}", text);
+}
@@ -0,0 +1,546 @@
+//! The calendar from an iCalendar file, worked out to the next few events for the dashboard
+
+use crate::date::{civil_from_days, days_from_civil, days_in_month, weekday, MINUTES_PER_DAY};
+use crate::display::{CalendarEvent, MAX_EVENTS};
+use crate::text::to_ascii;
+use heapless::{String, Vec};
+
+/// Next events looked at while reading the calendar. Recurring events can take a few of these at once
+const MAX_CANDIDATES: usize = 8;
+/// Moved or changed instances of recurring events (RECURRENCE-ID) so the original time is not shown as well
+const MAX_OVERRIDES: usize = 16;
+const MAX_EXDATES: usize = 16;
+/// Keeps a rule we read wrong from going forever
+const MAX_OCCURRENCES: i64 = 5_000;
+/// INTERVAL is clamped to this so stepping through the periods can not overflow
+const MAX_INTERVAL: i64 = 1_000;
+/// Longer events are cut short, 100 years is long enough for anything on a calendar
+const MAX_DURATION: i64 = 36_525 * MINUTES_PER_DAY;
+
+/// Reads an iCalendar file (RFC 5545) a line at a time and keeps the next few events.
+/// Only the parts a desk display needs: VEVENT with DTSTART, DTEND or DURATION, SUMMARY, STATUS, EXDATE,
+/// RECURRENCE-ID and RRULE with FREQ, INTERVAL, COUNT, UNTIL and BYDAY.
+/// Times with a TZID are taken as our own timezone, so a meeting set in another timezone shows at the wrong time
+pub struct IcsParser {
+    /// Minutes since 1970 in UTC
+    now: i64,
+    utc_offset_minutes: i64,
+    in_event: bool,
+    /// Components inside the event like VALARM. Their properties are not the event's
+    nested_depth: u8,
+    event: PendingEvent,
+    candidates: Vec<Candidate, MAX_CANDIDATES>,
+    overrides: Vec<(u32, i64), MAX_OVERRIDES>,
+}
+
+#[derive(Default)]
+struct PendingEvent {
+    uid: u32,
+    summary: String<48>,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+    duration: Option<i64>,
+    rule: Option<RecurrenceRule>,
+    /// UTC minutes
+    exdates: Vec<i64, MAX_EXDATES>,
+    /// UTC minutes of the occurrence this one replaces
+    recurrence_id: Option<i64>,
+    cancelled: bool,
+}
+
+struct Candidate {
+    uid: u32,
+    /// When the recurring event would have been, so a RECURRENCE-ID can take it out. None for events that do not repeat
+    occurrence_of: Option<i64>,
+    event: CalendarEvent,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EventTime {
+    /// Minutes since 1970, in UTC if `utc` otherwise local
+    minutes: i64,
+    utc: bool,
+    all_day: bool,
+}
+
+impl EventTime {
+    fn to_utc(self, utc_offset_minutes: i64) -> i64 {
+        match self.utc {
+            true => self.minutes,
+            false => self.minutes - utc_offset_minutes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone)]
+struct RecurrenceRule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<u32>,
+    until: Option<EventTime>,
+    /// Ordinal (0 for every, -1 for last) and weekday with 0 as Monday, sorted by weekday
+    by_day: Vec<(i8, u8), 7>,
+}
+
+impl IcsParser {
+    pub fn new(now: i64, utc_offset_minutes: i64) -> Self {
+        Self {
+            now,
+            utc_offset_minutes,
+            in_event: false,
+            nested_depth: 0,
+            event: PendingEvent::default(),
+            candidates: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Takes one unfolded content line, see line_stream::LineStream
+    pub fn parse_line(&mut self, line: &str) {
+        let Some((name, parameters, value)) = split_content_line(line) else {
+            return;
+        };
+
+        match name {
+            "BEGIN" if !self.in_event => self.begin(value),
+            "BEGIN" => self.nested_depth = self.nested_depth.saturating_add(1),
+            "END" if self.in_event && self.nested_depth > 0 => self.nested_depth -= 1,
+            "END" if self.in_event && value == "VEVENT" => {
+                self.in_event = false;
+                self.finish_event();
+            }
+            _ if !self.in_event || self.nested_depth > 0 => {}
+            "UID" => self.event.uid = hash(value),
+            "SUMMARY" => {
+                let mut summary: String<128> = String::new();
+                unescape_text(value, &mut summary);
+                self.event.summary = to_ascii(summary.trim());
+            }
+            "DTSTART" => self.event.start = parse_event_time(value, parameters),
+            "DTEND" => self.event.end = parse_event_time(value, parameters),
+            "DURATION" => self.event.duration = parse_duration(value),
+            "RRULE" => {
+                self.event.rule = parse_rule(value);
+                if self.event.rule.is_none() {
+                    warn!(
+                        "Could not use the repeat rule {}, only showing the first one",
+                        value
+                    );
+                }
+            }
+            "EXDATE" => {
+                for exdate in value.split(',') {
+                    if let Some(exdate) = parse_event_time(exdate, parameters) {
+                        let _ = self
+                            .event
+                            .exdates
+                            .push(exdate.to_utc(self.utc_offset_minutes));
+                    }
+                }
+            }
+            "RECURRENCE-ID" => {
+                self.event.recurrence_id = parse_event_time(value, parameters)
+                    .map(|time| time.to_utc(self.utc_offset_minutes))
+            }
+            "STATUS" => self.event.cancelled = value == "CANCELLED",
+            _ => {}
+        }
+    }
+
+    /// The next events in order, ones that are already over are left out
+    pub fn finish(self) -> Vec<CalendarEvent, MAX_EVENTS> {
+        let mut events = Vec::new();
+        for candidate in self.candidates.iter() {
+            let replaced = candidate
+                .occurrence_of
+                .is_some_and(|start| self.overrides.contains(&(candidate.uid, start)));
+            if !replaced && events.push(candidate.event.clone()).is_err() {
+                break;
+            }
+        }
+        events
+    }
+
+    fn begin(&mut self, component: &str) {
+        if component == "VEVENT" {
+            self.in_event = true;
+            self.nested_depth = 0;
+            self.event = PendingEvent::default();
+        }
+    }
+
+    fn finish_event(&mut self) {
+        let event = core::mem::take(&mut self.event);
+        if event.cancelled {
+            return;
+        }
+        let Some(start) = event.start else {
+            return;
+        };
+
+        let offset = self.utc_offset_minutes;
+        let start_utc = start.to_utc(offset);
+        let duration = match (event.end, event.duration) {
+            (Some(end), _) => end.to_utc(offset) - start_utc,
+            (None, Some(duration)) => duration,
+            (None, None) if start.all_day => MINUTES_PER_DAY,
+            (None, None) => 0,
+        }
+        .clamp(0, MAX_DURATION);
+        if let Some(recurrence_id) = event.recurrence_id {
+            let _ = self.overrides.push((event.uid, recurrence_id));
+        }
+
+        let Some(rule) = &event.rule else {
+            self.consider(
+                event.uid,
+                None,
+                start_utc,
+                duration,
+                &event.summary,
+                start.all_day,
+            );
+            return;
+        };
+
+        //The rule steps through days in the same time the event is in, local unless it ends in Z
+        let frame_offset = start_utc - start.minutes;
+        //UNTIL as a date means the whole of that day
+        let until = rule.until.map(|until| match until.all_day {
+            true => until.to_utc(offset) - frame_offset + MINUTES_PER_DAY - 1,
+            false => until.to_utc(offset) - frame_offset,
+        });
+        let skip_before = self.now - frame_offset - duration;
+        rule.for_each_occurrence(start.minutes, until, skip_before, |occurrence| {
+            let occurrence_utc = occurrence + frame_offset;
+            if event.exdates.contains(&occurrence_utc) {
+                return true;
+            }
+            self.consider(
+                event.uid,
+                Some(occurrence_utc),
+                occurrence_utc,
+                duration,
+                &event.summary,
+                start.all_day,
+            )
+        });
+    }
+
+    /// Keeps the event if it is one of the next ones. False once it starts after all of them so a recurring event can stop
+    fn consider(
+        &mut self,
+        uid: u32,
+        occurrence_of: Option<i64>,
+        start: i64,
+        duration: i64,
+        summary: &str,
+        all_day: bool,
+    ) -> bool {
+        let end = start + duration;
+        if end <= self.now {
+            return true;
+        }
+        if self.candidates.is_full()
+            && self
+                .candidates
+                .last()
+                .is_some_and(|last| last.event.start <= start)
+        {
+            return false;
+        }
+
+        let position = self
+            .candidates
+            .iter()
+            .position(|candidate| candidate.event.start > start)
+            .unwrap_or(self.candidates.len());
+        if self.candidates.is_full() {
+            self.candidates.pop();
+        }
+        let _ = self.candidates.insert(
+            position,
+            Candidate {
+                uid,
+                occurrence_of,
+                event: CalendarEvent {
+                    summary: match summary.is_empty() {
+                        true => String::try_from("Busy").unwrap_or_default(),
+                        false => String::try_from(summary).unwrap_or_default(),
+                    },
+                    start,
+                    end,
+                    all_day,
+                },
+            },
+        );
+        true
+    }
+}
+
+impl RecurrenceRule {
+    /// Calls `visit` with the start of each occurrence in order, in the same time as `start`. Stops when it returns false.
+    /// Rules without a COUNT jump ahead to `skip_before` instead of going through years of old meetings
+    fn for_each_occurrence(
+        &self,
+        start: i64,
+        until: Option<i64>,
+        skip_before: i64,
+        mut visit: impl FnMut(i64) -> bool,
+    ) {
+        let start_day = start.div_euclid(MINUTES_PER_DAY);
+        let time_of_day = start.rem_euclid(MINUTES_PER_DAY);
+        let (year, month, day) = civil_from_days(start_day);
+
+        let mut occurrences = 0u32;
+        let mut emit = |occurrence: i64| -> bool {
+            //BYDAY can land before DTSTART in the first week, those do not count
+            if occurrence < start {
+                return true;
+            }
+            if until.is_some_and(|until| occurrence > until)
+                || self.count.is_some_and(|count| occurrences >= count)
+            {
+                return false;
+            }
+            occurrences += 1;
+            visit(occurrence)
+        };
+
+        let period_days = match self.frequency {
+            Frequency::Daily => self.interval,
+            Frequency::Weekly => self.interval * 7,
+            Frequency::Monthly | Frequency::Yearly => 0,
+        };
+        let first_period = match (self.count, period_days) {
+            (None, 1..) => ((skip_before - start) / (period_days * MINUTES_PER_DAY) - 1).max(0),
+            _ => 0,
+        };
+
+        for period in first_period..first_period + MAX_OCCURRENCES {
+            let step = period * self.interval;
+            let keep_going = match self.frequency {
+                Frequency::Daily => emit(start + step * MINUTES_PER_DAY),
+                Frequency::Weekly if self.by_day.is_empty() => {
+                    emit(start + step * 7 * MINUTES_PER_DAY)
+                }
+                Frequency::Weekly => {
+                    let week_start = start_day - weekday(start_day) as i64 + step * 7;
+                    self.by_day.iter().all(|&(_, day_of_week)| {
+                        emit((week_start + day_of_week as i64) * MINUTES_PER_DAY + time_of_day)
+                    })
+                }
+                Frequency::Monthly => {
+                    let months = month as i64 - 1 + step;
+                    let year = year + months.div_euclid(12) as i32;
+                    let month = months.rem_euclid(12) as u8 + 1;
+                    let day = match self.by_day.first() {
+                        //Like the second Tuesday of the month
+                        Some(&(ordinal, day_of_week)) if ordinal != 0 => {
+                            nth_weekday(year, month, ordinal, day_of_week)
+                        }
+                        _ => (day <= days_in_month(year, month)).then_some(day),
+                    };
+                    match day {
+                        Some(day) => {
+                            emit(days_from_civil(year, month, day) * MINUTES_PER_DAY + time_of_day)
+                        }
+                        //No 31st this month
+                        None => true,
+                    }
+                }
+                //Feb 29 only comes around on leap years
+                Frequency::Yearly => match day <= days_in_month(year + step as i32, month) {
+                    true => emit(
+                        days_from_civil(year + step as i32, month, day) * MINUTES_PER_DAY
+                            + time_of_day,
+                    ),
+                    false => true,
+                },
+            };
+            if !keep_going {
+                return;
+            }
+        }
+    }
+}
+
+/// NAME;PARAM=VALUE:VALUE into its parts. Colons in quoted parameters do not count
+fn split_content_line(line: &str) -> Option<(&str, &str, &str)> {
+    let mut in_quotes = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => {
+                let head = &line[..index];
+                let (name, parameters) = head.split_once(';').unwrap_or((head, ""));
+                return Some((name, parameters, &line[index + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 20241210 or 20241210T093000 with an optional Z on the end
+fn parse_event_time(value: &str, parameters: &str) -> Option<EventTime> {
+    let number = |range: core::ops::Range<usize>| value.get(range)?.parse::<u8>().ok();
+    let year = value.get(0..4)?.parse::<i32>().ok()?;
+    let month = number(4..6)?;
+    let day = number(6..8)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+
+    let all_day = value.len() == 8 || parameters.split(';').any(|p| p == "VALUE=DATE");
+    if all_day {
+        return Some(EventTime {
+            minutes: days * MINUTES_PER_DAY,
+            utc: false,
+            all_day,
+        });
+    }
+    if value.get(8..9)? != "T" {
+        return None;
+    }
+    let hour = number(9..11)?;
+    let minute = number(11..13)?;
+    Some(EventTime {
+        minutes: days * MINUTES_PER_DAY + hour as i64 * 60 + minute as i64,
+        utc: value.ends_with('Z'),
+        all_day,
+    })
+}
+
+/// Durations like PT1H30M or P1D in minutes. Seconds are dropped
+fn parse_duration(value: &str) -> Option<i64> {
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut minutes = 0;
+    let mut number: i64 = 0;
+    for character in rest.strip_prefix('P')?.chars() {
+        let unit = match character {
+            '0'..='9' => {
+                number = number
+                    .checked_mul(10)?
+                    .checked_add((character as u8 - b'0') as i64)?;
+                continue;
+            }
+            'T' => continue,
+            'W' => 7 * MINUTES_PER_DAY,
+            'D' => MINUTES_PER_DAY,
+            'H' => 60,
+            'M' => 1,
+            'S' => 0,
+            _ => return None,
+        };
+        minutes = number
+            .checked_mul(unit)
+            .and_then(|part| part.checked_add(minutes))?;
+        number = 0;
+    }
+    Some(sign * minutes)
+}
+
+/// FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE. None for rules we can not follow, like HOURLY
+fn parse_rule(value: &str) -> Option<RecurrenceRule> {
+    let mut frequency = None;
+    let mut rule = RecurrenceRule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+    };
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key {
+            "FREQ" => {
+                frequency = Some(match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => rule.interval = value.parse::<i64>().ok()?.clamp(1, MAX_INTERVAL),
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => rule.until = Some(parse_event_time(value, "")?),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let split = day.len().checked_sub(2)?;
+                    let ordinal = match &day[..split] {
+                        "" => 0,
+                        ordinal => ordinal.parse::<i8>().ok()?,
+                    };
+                    let day_of_week = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"]
+                        .iter()
+                        .position(|name| *name == &day[split..])?;
+                    rule.by_day.push((ordinal, day_of_week as u8)).ok()?;
+                }
+                rule.by_day
+                    .sort_unstable_by_key(|&(_, day_of_week)| day_of_week);
+            }
+            _ => debug!("Ignoring {} in a repeat rule", key),
+        }
+    }
+    rule.frequency = frequency?;
+    Some(rule)
+}
+
+/// Takes the backslash escapes out of TEXT values. New lines become spaces
+fn unescape_text<const N: usize>(value: &str, unescaped: &mut String<N>) {
+    let mut escaped = false;
+    for character in value.chars() {
+        let character = match (escaped, character) {
+            (false, '\\') => {
+                escaped = true;
+                continue;
+            }
+            (true, 'n' | 'N') => ' ',
+            _ => character,
+        };
+        escaped = false;
+        if unescaped.push(character).is_err() {
+            return;
+        }
+    }
+}
+
+/// FNV-1a, UIDs can be long and we only need to tell them apart
+fn hash(value: &str) -> u32 {
+    value.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Day of the month for the nth weekday, like -1 and 4 for the last Friday. None if there is no 5th one
+fn nth_weekday(year: i32, month: u8, ordinal: i8, day_of_week: u8) -> Option<u8> {
+    let month_length = days_in_month(year, month);
+    let day = match ordinal {
+        //Anything past a 5th one is not in the month anyway
+        1..=5 => {
+            let first = weekday(days_from_civil(year, month, 1));
+            1 + (day_of_week + 7 - first) % 7 + (ordinal as u8 - 1) * 7
+        }
+        -5..=-1 => {
+            let last = weekday(days_from_civil(year, month, month_length));
+            let last_match = month_length - (last + 7 - day_of_week) % 7;
+            last_match.checked_sub((ordinal.unsigned_abs() - 1) * 7)?
+        }
+        _ => return None,
+    };
+    (1..=month_length).contains(&day).then_some(day)
+}
@@ -0,0 +1,132 @@
+/// Finds the raw JSON value at a dotted path like `data.user.clocked_in` or `entries.0.state`, where numbers
+/// index into arrays. For responses we do not know the shape of ahead of time, so no serde here.
+/// Keys are matched as written in the JSON, escapes are not undone
+pub fn find_value<'a>(json: &'a str, path: &str) -> Option<&'a str> {
+    let mut value = json.trim();
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        value = match value.as_bytes().first()? {
+            b'{' => object_member(value, key)?,
+            b'[' => array_element(value, key.parse().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// The value as plain text. Strings lose their quotes, everything else is left as it is, like true or 42
+pub fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn object_member<'a>(object: &'a str, key: &str) -> Option<&'a str> {
+    let bytes = object.as_bytes();
+    let mut position = 1;
+    loop {
+        position = skip_whitespace(bytes, position);
+        match bytes.get(position)? {
+            b'}' => return None,
+            b'"' => {}
+            _ => return None,
+        }
+        let key_end = value_end(bytes, position)?;
+        let member_key = &object[position + 1..key_end - 1];
+
+        position = skip_whitespace(bytes, key_end);
+        if *bytes.get(position)? != b':' {
+            return None;
+        }
+        let value_start = skip_whitespace(bytes, position + 1);
+        let end = value_end(bytes, value_start)?;
+        if member_key == key {
+            return Some(&object[value_start..end]);
+        }
+
+        position = skip_whitespace(bytes, end);
+        match bytes.get(position)? {
+            b',' => position += 1,
+            _ => return None,
+        }
+    }
+}
+
+fn array_element(array: &str, index: usize) -> Option<&str> {
+    let bytes = array.as_bytes();
+    let mut position = 1;
+    for current in 0.. {
+        position = skip_whitespace(bytes, position);
+        if *bytes.get(position)? == b']' {
+            return None;
+        }
+        let end = value_end(bytes, position)?;
+        if current == index {
+            return Some(&array[position..end]);
+        }
+
+        position = skip_whitespace(bytes, end);
+        match bytes.get(position)? {
+            b',' => position += 1,
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    while bytes
+        .get(position)
+        .is_some_and(|byte| byte.is_ascii_whitespace())
+    {
+        position += 1;
+    }
+    position
+}
+
+/// Index just past the value starting at `start`
+fn value_end(bytes: &[u8], start: usize) -> Option<usize> {
+    match bytes.get(start)? {
+        b'"' => {
+            let mut position = start + 1;
+            loop {
+                match bytes.get(position)? {
+                    b'\\' => position += 2,
+                    b'"' => return Some(position + 1),
+                    _ => position += 1,
+                }
+            }
+        }
+        b'{' | b'[' => {
+            let mut depth = 0u16;
+            let mut position = start;
+            loop {
+                match bytes.get(position)? {
+                    b'"' => {
+                        position = value_end(bytes, position)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(position + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                position += 1;
+            }
+        }
+        _ => {
+            //Numbers, true, false and null run until the next separator
+            let mut position = start;
+            while bytes.get(position).is_some_and(|byte| {
+                !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+            }) {
+                position += 1;
+            }
+            (position > start).then_some(position)
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Pulls one array out of a JSON response without holding the whole body
+
+use embedded_io_async::Read;
+use heapless::Vec;
+
+/// Longest key find_array can match
+const MAX_KEY_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StreamError {
+    Read,
+    /// The body ended in the middle of the JSON
+    UnexpectedEnd,
+    ArrayNotFound,
+}
+
+/// Reads one array out of a JSON body a little at a time. Only the current element is ever held in memory,
+/// which can then be handed to serde_json_core like a normal response.
+/// Only looks for the array in the top level object, like `notifications` in {"cursor": "..", "notifications": [..]}
+pub struct JsonArrayStream<'b, R> {
+    reader: R,
+    chunk: [u8; 128],
+    chunk_length: usize,
+    chunk_position: usize,
+    element_buffer: &'b mut [u8],
+    finished: bool,
+}
+
+impl<'b, R: Read> JsonArrayStream<'b, R> {
+    pub fn new(reader: R, element_buffer: &'b mut [u8]) -> Self {
+        Self {
+            reader,
+            chunk: [0; 128],
+            chunk_length: 0,
+            chunk_position: 0,
+            element_buffer,
+            finished: false,
+        }
+    }
+
+    /// Reads up to just inside the array under `key`
+    pub async fn find_array(&mut self, key: &str) -> Result<(), StreamError> {
+        let mut depth: u16 = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut string: Vec<u8, MAX_KEY_LENGTH> = Vec::new();
+        let mut string_overflowed = false;
+        let mut last_string_matches = false;
+        let mut awaiting_value = false;
+
+        loop {
+            let Some(byte) = self.next_byte().await? else {
+                return Err(StreamError::ArrayNotFound);
+            };
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                    last_string_matches =
+                        depth == 1 && !string_overflowed && string.as_slice() == key.as_bytes();
+                    continue;
+                }
+                string_overflowed |= string.push(byte).is_err();
+                continue;
+            }
+
+            if awaiting_value {
+                if byte.is_ascii_whitespace() {
+                    continue;
+                }
+                if byte == b'[' {
+                    return Ok(());
+                }
+                //The key was there but it is not an array, keep looking in case it shows up again
+                awaiting_value = false;
+            }
+
+            match byte {
+                b'"' => {
+                    in_string = true;
+                    string.clear();
+                    string_overflowed = false;
+                }
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                b':' => {
+                    awaiting_value = depth == 1 && last_string_matches;
+                    last_string_matches = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The next element of the array as raw JSON or None at the end of the array.
+    /// Elements that do not fit in the element buffer are skipped
+    pub async fn next_element(&mut self) -> Result<Option<&[u8]>, StreamError> {
+        loop {
+            if self.finished {
+                return Ok(None);
+            }
+
+            let mut byte = self.next_non_whitespace().await?;
+            if byte == b',' {
+                byte = self.next_non_whitespace().await?;
+            }
+            if byte == b']' {
+                self.finished = true;
+                return Ok(None);
+            }
+
+            let mut length = 0;
+            let mut overflowed = false;
+            let mut depth: u16 = 0;
+            let mut in_string = false;
+            let mut escaped = false;
+            //Objects, arrays and strings end on their closing byte. Numbers, true, false and null end on the byte after them
+            let is_scalar = !matches!(byte, b'{' | b'[' | b'"');
+
+            loop {
+                if is_scalar && (matches!(byte, b',' | b']' | b'}') || byte.is_ascii_whitespace()) {
+                    //That byte belongs to the array, let the next call see it
+                    self.chunk_position -= 1;
+                    break;
+                }
+
+                match self.element_buffer.get_mut(length) {
+                    Some(slot) => {
+                        *slot = byte;
+                        length += 1;
+                    }
+                    None => overflowed = true,
+                }
+
+                if !is_scalar {
+                    if in_string {
+                        if escaped {
+                            escaped = false;
+                        } else if byte == b'\\' {
+                            escaped = true;
+                        } else if byte == b'"' {
+                            in_string = false;
+                        }
+                    } else {
+                        match byte {
+                            b'"' => in_string = true,
+                            b'{' | b'[' => depth += 1,
+                            b'}' | b']' => depth = depth.saturating_sub(1),
+                            _ => {}
+                        }
+                    }
+                    if depth == 0 && !in_string {
+                        break;
+                    }
+                }
+
+                byte = match self.next_byte().await? {
+                    Some(byte) => byte,
+                    None if is_scalar => break,
+                    None => return Err(StreamError::UnexpectedEnd),
+                };
+            }
+
+            if overflowed {
+                warn!(
+                    "Skipping an element bigger than the {} byte element buffer",
+                    self.element_buffer.len()
+                );
+                continue;
+            }
+            return Ok(Some(&self.element_buffer[..length]));
+        }
+    }
+
+    /// Reads the rest of the body so the connection is ready for the next request
+    pub async fn drain(&mut self) {
+        while let Ok(Some(_)) = self.next_byte().await {}
+    }
+
+    async fn next_byte(&mut self) -> Result<Option<u8>, StreamError> {
+        if self.chunk_position == self.chunk_length {
+            let read = self
+                .reader
+                .read(&mut self.chunk)
+                .await
+                .map_err(|_| StreamError::Read)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.chunk_length = read;
+            self.chunk_position = 0;
+        }
+        let byte = self.chunk[self.chunk_position];
+        self.chunk_position += 1;
+        Ok(Some(byte))
+    }
+
+    async fn next_non_whitespace(&mut self) -> Result<u8, StreamError> {
+        loop {
+            match self.next_byte().await? {
+                Some(byte) if byte.is_ascii_whitespace() => continue,
+                Some(byte) => return Ok(byte),
+                None => return Err(StreamError::UnexpectedEnd),
+            }
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Where everything goes on the 400x300 dashboard, so the display task and the simulator agree
+
+use embedded_graphics::prelude::{Point, Size};
+
+pub const NOTIFICATIONS: Point = Point::new(160, 0);
+/// Office status or the birthday banner, under the time
+pub const BADGE: Point = Point::new(5, 28);
+pub const SENSORS: Point = Point::new(5, 50);
+pub const CURRENT_WEATHER: Point = Point::new(85, 50);
+/// Only at night, to the right of the current weather
+pub const MOON: Point = Point::new(255, 50);
+pub const AIR_QUALITY: Point = Point::new(5, 95);
+/// The stale list and the calendar take turns here
+pub const STATUS: Point = Point::new(255, 85);
+/// The forecast, or the sun times until there is one
+pub const FORECAST: Point = Point::new(0, 145);
+pub const FORECAST_SIZE: Size = Size::new(400, 155);
@@ -0,0 +1,18 @@
+#![no_std]
+
+#[macro_use]
+mod fmt;
+
+pub mod annual;
+pub mod astronomy;
+pub mod date;
+pub mod display;
+pub mod ics;
+pub mod io;
+pub mod json_path;
+pub mod json_stream;
+pub mod layout;
+pub mod line_stream;
+pub mod text;
+pub mod weather;
+pub mod weather_icons;
@@ -0,0 +1,81 @@
+use crate::json_stream::StreamError;
+use embedded_io_async::Read;
+
+/// Reads a text body one line at a time, like json_stream does for JSON arrays, so a whole calendar never has to fit in memory.
+/// Unfolds iCalendar style lines (RFC 5545 3.1), where a line starting with a space or tab carries on the one before it.
+/// Line endings are taken off and anything past the end of the line buffer is dropped
+pub struct LineStream<'b, R> {
+    reader: R,
+    chunk: [u8; 128],
+    chunk_length: usize,
+    chunk_position: usize,
+    line_buffer: &'b mut [u8],
+}
+
+impl<'b, R: Read> LineStream<'b, R> {
+    pub fn new(reader: R, line_buffer: &'b mut [u8]) -> Self {
+        Self {
+            reader,
+            chunk: [0; 128],
+            chunk_length: 0,
+            chunk_position: 0,
+            line_buffer,
+        }
+    }
+
+    /// The next unfolded line or None at the end of the body
+    pub async fn next_line(&mut self) -> Result<Option<&str>, StreamError> {
+        let mut length = 0;
+        loop {
+            let Some(byte) = self.next_byte().await? else {
+                if length == 0 {
+                    return Ok(None);
+                }
+                break;
+            };
+            match byte {
+                b'\r' => {}
+                b'\n' => match self.next_byte().await? {
+                    Some(b' ' | b'\t') => {}
+                    Some(_) => {
+                        //That byte starts the next line, let the next call see it
+                        self.chunk_position -= 1;
+                        break;
+                    }
+                    None => break,
+                },
+                _ => {
+                    if let Some(slot) = self.line_buffer.get_mut(length) {
+                        *slot = byte;
+                        length += 1;
+                    }
+                }
+            }
+        }
+
+        //A cut off line can end half way through a character, keep what is whole
+        let line = &self.line_buffer[..length];
+        Ok(Some(match core::str::from_utf8(line) {
+            Ok(line) => line,
+            Err(e) => core::str::from_utf8(&line[..e.valid_up_to()]).unwrap_or(""),
+        }))
+    }
+
+    async fn next_byte(&mut self) -> Result<Option<u8>, StreamError> {
+        if self.chunk_position == self.chunk_length {
+            let read = self
+                .reader
+                .read(&mut self.chunk)
+                .await
+                .map_err(|_| StreamError::Read)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.chunk_length = read;
+            self.chunk_position = 0;
+        }
+        let byte = self.chunk[self.chunk_position];
+        self.chunk_position += 1;
+        Ok(Some(byte))
+    }
+}
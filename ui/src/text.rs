@@ -0,0 +1,117 @@
+use heapless::{String, Vec};
+
+/// profont only has ASCII, so this turns text from the internet into something it can draw.
+/// Accents are taken off, fancy punctuation becomes the plain version and anything else (emoji, CJK) is dropped.
+/// Stops when `N` is full
+pub fn to_ascii<const N: usize>(text: &str) -> String<N> {
+    let mut ascii: String<N> = String::new();
+    for character in text.chars() {
+        let replacement = match character {
+            ' '..='~' | '\n' => {
+                if ascii.push(character).is_err() {
+                    break;
+                }
+                continue;
+            }
+            //Tabs and the odd spaces
+            '\t' | '\u{a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{3000}' => " ",
+            'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+            'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+            'Æ' => "AE",
+            'æ' => "ae",
+            'Ç' | 'Ć' | 'Č' => "C",
+            'ç' | 'ć' | 'č' => "c",
+            'Ď' | 'Đ' | 'Ð' => "D",
+            'ď' | 'đ' | 'ð' => "d",
+            'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+            'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'Ğ' => "G",
+            'ğ' => "g",
+            'Ì'..='Ï' | 'Ī' | 'İ' => "I",
+            'ì'..='ï' | 'ī' | 'ı' => "i",
+            'Ł' => "L",
+            'ł' => "l",
+            'Ñ' | 'Ń' | 'Ň' => "N",
+            'ñ' | 'ń' | 'ň' => "n",
+            'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+            'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+            'Œ' => "OE",
+            'œ' => "oe",
+            'Ř' => "R",
+            'ř' => "r",
+            'Ś' | 'Š' | 'Ş' => "S",
+            'ś' | 'š' | 'ş' => "s",
+            'ß' => "ss",
+            'Ť' => "T",
+            'ť' => "t",
+            'Þ' => "Th",
+            'þ' => "th",
+            'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' => "U",
+            'ù'..='ü' | 'ū' | 'ů' | 'ű' => "u",
+            'Ý' | 'Ÿ' => "Y",
+            'ý' | 'ÿ' => "y",
+            'Ź' | 'Ż' | 'Ž' => "Z",
+            'ź' | 'ż' | 'ž' => "z",
+            '‘' | '’' | '‚' | '′' | '´' => "'",
+            '“' | '”' | '„' | '″' | '«' | '»' => "\"",
+            '‐'..='―' | '−' => "-",
+            '…' => "...",
+            '•' | '·' => "*",
+            '×' => "x",
+            '°' => "deg",
+            '€' => "EUR",
+            '£' => "GBP",
+            '©' => "(c)",
+            '®' => "(r)",
+            '™' => "(tm)",
+            _ => "",
+        };
+        if ascii.push_str(replacement).is_err() {
+            break;
+        }
+    }
+    ascii
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking between words where it can.
+/// Newlines in the text start a new line and words longer than a line are cut. Expects ASCII, see to_ascii.
+/// Anything past `L` lines is left off
+pub fn word_wrap<const L: usize>(text: &str, width: usize) -> Vec<&str, L> {
+    let mut lines: Vec<&str, L> = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+
+    'paragraphs: for paragraph in text.split('\n') {
+        let mut rest = paragraph.trim();
+        if rest.is_empty() {
+            //Keep one blank line between paragraphs, posts use them a lot
+            if lines.last().is_some_and(|line| !line.is_empty()) && lines.push("").is_err() {
+                break;
+            }
+            continue;
+        }
+        while !rest.is_empty() {
+            //Char indices so anything that got past to_ascii can not land us in the middle of a character
+            let line = match rest.char_indices().nth(width) {
+                None => rest,
+                Some((cut, _)) => {
+                    //Break at the last space that fits, a space right after the line fits too
+                    let window = rest
+                        .char_indices()
+                        .nth(width + 1)
+                        .map_or(rest.len(), |(i, _)| i);
+                    match rest[..window].rfind(' ') {
+                        Some(space) if space > 0 => &rest[..space],
+                        _ => &rest[..cut],
+                    }
+                }
+            };
+            if lines.push(line.trim_end()).is_err() {
+                break 'paragraphs;
+            }
+            rest = rest[line.len()..].trim_start();
+        }
+    }
+    lines
+}
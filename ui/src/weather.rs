@@ -0,0 +1,177 @@
+//! What Open-Meteo sends back for the forecast and air quality
+
+use heapless::{String, Vec};
+use serde::Deserialize;
+
+/// You will notice I am using heapless::String instead of &str. I was having issues with sharing the struct between tasks
+/// because of str and decided to just go simple to keep moving
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct ForecastResponse {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub generationtime_ms: f64,
+    pub utc_offset_seconds: i64,
+    pub timezone: String<32>,
+    pub timezone_abbreviation: String<8>,
+    pub elevation: f64,
+    pub current_units: CurrentUnits,
+    pub current: Current,
+    pub daily_units: DailyUnits,
+    pub daily: Daily,
+}
+
+///This is the units used for each of the current measurements
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct CurrentUnits {
+    pub time: String<7>,
+    pub interval: String<7>,
+    pub temperature_2m: String<3>,
+    pub relative_humidity_2m: String<2>,
+    pub apparent_temperature: String<3>,
+    // mp/h or km/h
+    pub wind_speed_10m: String<5>,
+    // °
+    pub wind_direction_10m: String<2>,
+    pub wind_gusts_10m: String<5>,
+    //UV index has no unit and comes back as an empty string
+    pub uv_index: String<1>,
+    //I think this will always be wmo code. Going to assume it is
+    // #[serde(rename = "weather_code")]
+    // pub weather_code: &'a str,
+}
+///This is the actual current weather measurements
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct Current {
+    pub time: String<16>,
+    pub interval: i64,
+    pub temperature_2m: f64,
+    pub relative_humidity_2m: i64,
+    pub apparent_temperature: f64,
+    pub wind_speed_10m: f64,
+    ///Direction the wind is coming from in degrees. 0 is north
+    pub wind_direction_10m: f64,
+    pub wind_gusts_10m: f64,
+    pub uv_index: f64,
+    ///See top for weather code meanings    
+    pub weather_code: u8,
+}
+
+///This is the units used for each of the daily measurements
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct DailyUnits {
+    pub time: String<7>,
+    //I think this will always be wmo code. Going to assume it is
+    // pub weather_code: &'a str,
+    pub temperature_2m_max: String<3>,
+    pub temperature_2m_min: String<3>,
+    //Just going to comment these out cause it's all just going to use the same time format
+    // pub sunrise: &'a str,
+    // pub sunset: &'a str,
+    pub precipitation_probability_max: String<1>,
+}
+
+///This is the actual daily weather measurements
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+//Hack
+//I know the vecs will always be 7(for my use case) since i get 7 week forecast
+//I know the Strings length will always be 10 or 16 because it's dates
+//Reason for the second was I was having lifetime issues with 'a &str in heapless::vec
+pub struct Daily {
+    // "2024-11-29",
+    pub time: Vec<String<10>, 7>,
+    ///See top for weather code meanings    
+    pub weather_code: Vec<u8, 7>,
+    pub temperature_2m_max: Vec<f64, 7>,
+    pub temperature_2m_min: Vec<f64, 7>,
+    // 2024-11-29T06:37
+    pub sunrise: Vec<String<16>, 7>,
+    // 2024-11-29T06:37
+    pub sunset: Vec<String<16>, 7>,
+    pub precipitation_probability_max: Vec<i64, 7>,
+}
+
+impl Daily {
+    /// Days every field has a value for, in case Open-Meteo sends one of them back shorter
+    pub fn complete_days(&self) -> usize {
+        [
+            self.time.len(),
+            self.weather_code.len(),
+            self.temperature_2m_max.len(),
+            self.temperature_2m_min.len(),
+            self.sunrise.len(),
+            self.sunset.len(),
+            self.precipitation_probability_max.len(),
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0)
+    }
+}
+
+///Air quality response from the Open-Meteo air quality api
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct AirQualityResponse {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub generationtime_ms: f64,
+    pub utc_offset_seconds: i64,
+    pub timezone: String<32>,
+    pub timezone_abbreviation: String<8>,
+    pub elevation: f64,
+    pub current_units: AirQualityCurrentUnits,
+    pub current: AirQualityCurrent,
+}
+
+///This is the units used for each of the current air quality measurements
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct AirQualityCurrentUnits {
+    pub time: String<7>,
+    pub interval: String<7>,
+    pub us_aqi: String<8>,
+    pub european_aqi: String<8>,
+    // μg/m³
+    pub pm10: String<8>,
+    pub pm2_5: String<8>,
+    pub ozone: String<8>,
+    //Pollen units are all grains/m³ so just skipping them
+}
+
+///This is the actual current air quality measurements
+/// Everything is an Option since Open-Meteo sends null when a value is not available for the location.
+/// Pollen is only forecasted for Europe so will always be None anywhere else
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct AirQualityCurrent {
+    pub time: String<16>,
+    pub interval: i64,
+    pub us_aqi: Option<u16>,
+    pub european_aqi: Option<u16>,
+    pub pm10: Option<f64>,
+    pub pm2_5: Option<f64>,
+    pub ozone: Option<f64>,
+    pub alder_pollen: Option<f64>,
+    pub birch_pollen: Option<f64>,
+    pub grass_pollen: Option<f64>,
+    pub mugwort_pollen: Option<f64>,
+    pub olive_pollen: Option<f64>,
+    pub ragweed_pollen: Option<f64>,
+}
+
+impl AirQualityCurrent {
+    /// Returns the highest pollen count of all the pollen types, None if there is no pollen data
+    pub fn max_pollen(&self) -> Option<f64> {
+        [
+            self.alder_pollen,
+            self.birch_pollen,
+            self.grass_pollen,
+            self.mugwort_pollen,
+            self.olive_pollen,
+            self.ragweed_pollen,
+        ]
+        .into_iter()
+        .flatten()
+        .fold(None, |max, pollen| match max {
+            Some(max) if max >= pollen => Some(max),
+            _ => Some(pollen),
+        })
+    }
+}
@@ -62,124 +62,124 @@ impl WeatherIcon {
     pub fn get_icon(&self) -> &'static [u8] {
         match self {
             // WeatherIcon::ChanceFlurries => {
-            //     include_bytes!("../images/weather_icons/chanceflurries.bmp")
+            //     include_bytes!("../../images/weather_icons/chanceflurries.bmp")
             // }
             WeatherIcon::ChanceRain => {
-                include_bytes!("../images/weather_icons/chancerain.bmp")
+                include_bytes!("../../images/weather_icons/chancerain.bmp")
             }
             WeatherIcon::ChanceSleet => {
-                include_bytes!("../images/weather_icons/chancesleet.bmp")
+                include_bytes!("../../images/weather_icons/chancesleet.bmp")
             }
             // WeatherIcon::ChanceSnow => {
-            //     include_bytes!("../images/weather_icons/chancesnow.bmp")
+            //     include_bytes!("../../images/weather_icons/chancesnow.bmp")
             // }
             // WeatherIcon::ChanceStorms => {
-            //     include_bytes!("../images/weather_icons/chancetstorms.bmp")
+            //     include_bytes!("../../images/weather_icons/chancetstorms.bmp")
             // }
             WeatherIcon::Clear => {
-                include_bytes!("../images/weather_icons/clear.bmp")
+                include_bytes!("../../images/weather_icons/clear.bmp")
             }
             WeatherIcon::Cloudy => {
-                include_bytes!("../images/weather_icons/cloudy.bmp")
+                include_bytes!("../../images/weather_icons/cloudy.bmp")
             }
             WeatherIcon::Flurries => {
-                include_bytes!("../images/weather_icons/flurries.bmp")
+                include_bytes!("../../images/weather_icons/flurries.bmp")
             }
             WeatherIcon::Fog => {
-                include_bytes!("../images/weather_icons/fog.bmp")
+                include_bytes!("../../images/weather_icons/fog.bmp")
             }
             // WeatherIcon::Hazy => {
-            //     include_bytes!("../images/weather_icons/hazy.bmp")
+            //     include_bytes!("../../images/weather_icons/hazy.bmp")
             // }
             WeatherIcon::MostlyCloudy => {
-                include_bytes!("../images/weather_icons/mostlycloudy.bmp")
+                include_bytes!("../../images/weather_icons/mostlycloudy.bmp")
             }
             // WeatherIcon::MostlySunny => {
-            //     include_bytes!("../images/weather_icons/mostlysunny.bmp")
+            //     include_bytes!("../../images/weather_icons/mostlysunny.bmp")
             // }
             // WeatherIcon::NtChanceFlurries => {
-            //     include_bytes!("../images/weather_icons/nt_chanceflurries.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_chanceflurries.bmp")
             // }
             // WeatherIcon::NtChanceRain => {
-            //     include_bytes!("../images/weather_icons/nt_chancerain.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_chancerain.bmp")
             // }
             // WeatherIcon::NtChanceSleet => {
-            //     include_bytes!("../images/weather_icons/nt_chancesleet.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_chancesleet.bmp")
             // }
             // WeatherIcon::NtChanceSnow => {
-            //     include_bytes!("../images/weather_icons/nt_chancesnow.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_chancesnow.bmp")
             // }
             // WeatherIcon::NtChanceStorms => {
-            //     include_bytes!("../images/weather_icons/nt_chancetstorms.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_chancetstorms.bmp")
             // }
             WeatherIcon::NtClear => {
-                include_bytes!("../images/weather_icons/nt_clear.bmp")
+                include_bytes!("../../images/weather_icons/nt_clear.bmp")
             }
             // WeatherIcon::NtCloudy => {
-            //     include_bytes!("../images/weather_icons/nt_cloudy.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_cloudy.bmp")
             // }
             // WeatherIcon::NtFlurries => {
-            //     include_bytes!("../images/weather_icons/nt_flurries.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_flurries.bmp")
             // }
             // WeatherIcon::NtFog => {
-            //     include_bytes!("../images/weather_icons/nt_fog.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_fog.bmp")
             // }
             // WeatherIcon::NtHazy => {
-            //     include_bytes!("../images/weather_icons/nt_hazy.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_hazy.bmp")
             // }
             // WeatherIcon::NtMostlyCloudy => {
-            //     include_bytes!("../images/weather_icons/nt_mostlycloudy.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_mostlycloudy.bmp")
             // }
             // WeatherIcon::NtMostlySunny => {
-            //     include_bytes!("../images/weather_icons/nt_mostlysunny.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_mostlysunny.bmp")
             // }
             // WeatherIcon::NtPartlyCloudy => {
-            //     include_bytes!("../images/weather_icons/nt_partlycloudy.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_partlycloudy.bmp")
             // }
             // WeatherIcon::NtPartlySunny => {
-            //     include_bytes!("../images/weather_icons/nt_partlysunny.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_partlysunny.bmp")
             // }
             // WeatherIcon::NtRain => {
-            //     include_bytes!("../images/weather_icons/nt_rain.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_rain.bmp")
             // }
             // WeatherIcon::NtSleet => {
-            //     include_bytes!("../images/weather_icons/nt_sleet.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_sleet.bmp")
             // }
             // WeatherIcon::NtSnow => {
-            //     include_bytes!("../images/weather_icons/nt_snow.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_snow.bmp")
             // }
             // WeatherIcon::NtSunny => {
-            //     include_bytes!("../images/weather_icons/nt_sunny.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_sunny.bmp")
             // }
             // WeatherIcon::NtTStorms => {
-            //     include_bytes!("../images/weather_icons/nt_tstorms.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_tstorms.bmp")
             // }
             // WeatherIcon::NtUnknown => {
-            //     include_bytes!("../images/weather_icons/nt_unknown.bmp")
+            //     include_bytes!("../../images/weather_icons/nt_unknown.bmp")
             // }
             WeatherIcon::PartlyCloudy => {
-                include_bytes!("../images/weather_icons/partlycloudy.bmp")
+                include_bytes!("../../images/weather_icons/partlycloudy.bmp")
             }
             // WeatherIcon::PartlySunny => {
-            //     include_bytes!("../images/weather_icons/partlysunny.bmp")
+            //     include_bytes!("../../images/weather_icons/partlysunny.bmp")
             // }
             WeatherIcon::Rain => {
-                include_bytes!("../images/weather_icons/rain.bmp")
+                include_bytes!("../../images/weather_icons/rain.bmp")
             }
             WeatherIcon::Sleet => {
-                include_bytes!("../images/weather_icons/sleet.bmp")
+                include_bytes!("../../images/weather_icons/sleet.bmp")
             }
             WeatherIcon::Snow => {
-                include_bytes!("../images/weather_icons/snow.bmp")
+                include_bytes!("../../images/weather_icons/snow.bmp")
             }
             // WeatherIcon::Sunny => {
-            //     include_bytes!("../images/weather_icons/sunny.bmp")
+            //     include_bytes!("../../images/weather_icons/sunny.bmp")
             // }
             WeatherIcon::TStorms => {
-                include_bytes!("../images/weather_icons/tstorms.bmp")
+                include_bytes!("../../images/weather_icons/tstorms.bmp")
             }
             WeatherIcon::Unknown => {
-                include_bytes!("../images/weather_icons/unknown.bmp")
+                include_bytes!("../../images/weather_icons/unknown.bmp")
             }
         }
     }
@@ -199,7 +199,7 @@ pub fn get_weather_icon(code: u8) -> WeatherIcon {
         66 | 67 => WeatherIcon::Sleet,
         71 => WeatherIcon::Flurries,
         73 | 75 | 77 => WeatherIcon::Snow,
-        80 | 81 | 82 => WeatherIcon::Rain,
+        80..=82 => WeatherIcon::Rain,
         85 | 86 => WeatherIcon::Snow,
         95 | 96 | 99 => WeatherIcon::TStorms,
         _ => WeatherIcon::Unknown,
@@ -226,3 +226,23 @@ pub fn get_night_weather_icon(code: u8) -> WeatherIcon {
         _ => WeatherIcon::NtClear, // _ => WeatherIcon::Unknown,
     }
 }
+
+/// Short text version of the weather code for places without room for an icon
+pub fn get_weather_description(code: u8) -> &'static str {
+    match code {
+        0 => "Clear",
+        1 => "Few Cloud",
+        2 => "P Cloudy",
+        3 => "Cloudy",
+        45 | 48 => "Fog",
+        51 | 53 | 55 => "Drizzle",
+        56 | 57 => "Frz Drzl",
+        61 | 63 | 65 => "Rain",
+        66 | 67 => "Frz Rain",
+        71 | 73 | 75 | 77 => "Snow",
+        80..=82 => "Showers",
+        85 | 86 => "Snow Shwr",
+        95 | 96 | 99 => "T-Storms",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,23 @@
+//! Shared by the streaming tests
+
+use core::convert::Infallible;
+use embedded_io_async::{ErrorType, Read};
+
+/// Gives out the body at most `chunk_size` bytes a read
+pub struct SlowBody<'a> {
+    pub body: &'a [u8],
+    pub chunk_size: usize,
+}
+
+impl ErrorType for SlowBody<'_> {
+    type Error = Infallible;
+}
+
+impl Read for SlowBody<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let length = self.chunk_size.min(buf.len()).min(self.body.len());
+        buf[..length].copy_from_slice(&self.body[..length]);
+        self.body = &self.body[length..];
+        Ok(length)
+    }
+}
@@ -0,0 +1,215 @@
+//! The date parser against made up timestamps. Anything it is given should come back as a Result, never a panic
+
+use desk_buddy_ui::date::{
+    add_days, civil_from_days, day_of_week, days_between, days_from_civil, days_in_month, iso_week,
+    parse_date, parse_date_time, DayOfWeek, ParseError,
+};
+use proptest::prelude::*;
+
+/// A real date from 0001 to 9999
+fn date() -> impl Strategy<Value = (u16, u8, u8)> {
+    (1u16..=9999, 1u8..=12).prop_flat_map(|(year, month)| {
+        (
+            Just(year),
+            Just(month),
+            1..=days_in_month(year as i32, month),
+        )
+    })
+}
+
+/// An offset like the ones real servers send, -12:00 to +14:00
+fn offset() -> impl Strategy<Value = i32> {
+    (-12 * 4..=14 * 4).prop_map(|quarter_hours| quarter_hours * 15 * 60)
+}
+
+fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}{:02}:{:02}", sign, offset / 3_600, offset / 60 % 60)
+}
+
+#[test]
+fn known_timestamps() {
+    let forecast = parse_date_time("2024-12-10T11:45").unwrap();
+    assert_eq!(forecast.date_time.hour, 11);
+    assert_eq!(forecast.date_time.minute, 45);
+    assert_eq!(forecast.date_time.day_of_week, DayOfWeek::Tuesday);
+    assert_eq!(forecast.utc_offset_seconds, None);
+
+    let time_api = parse_date_time("2024-12-10T12:03:59.253687-06:00").unwrap();
+    assert_eq!(time_api.date_time.second, 59);
+    assert_eq!(time_api.nanosecond, 253_687_000);
+    assert_eq!(time_api.utc_offset_seconds, Some(-6 * 3_600));
+    assert_eq!(time_api.utc().hour, 18);
+
+    let bluesky = parse_date_time("2024-12-31T23:30:00.000Z").unwrap();
+    assert_eq!(bluesky.utc_offset_seconds, Some(0));
+
+    //Crosses into the next year in UTC
+    let new_year = parse_date_time("2024-12-31T20:00:00-05:00").unwrap().utc();
+    assert_eq!((new_year.year, new_year.month, new_year.day), (2025, 1, 1));
+    assert_eq!(new_year.hour, 1);
+    assert_eq!(new_year.day_of_week, DayOfWeek::Wednesday);
+}
+
+#[test]
+fn rejects_what_is_not_a_date() {
+    assert_eq!(parse_date("2023-02-29"), Err(ParseError::OutOfRange));
+    assert!(parse_date("2024-02-29").is_ok());
+    assert_eq!(parse_date("2024-13-01"), Err(ParseError::OutOfRange));
+    assert_eq!(parse_date("2024-04-31"), Err(ParseError::OutOfRange));
+    assert_eq!(parse_date("2024-4-01"), Err(ParseError::Format));
+    assert_eq!(parse_date("2024-12-10T11:45"), Err(ParseError::Format));
+    assert_eq!(parse_date(""), Err(ParseError::Format));
+    assert_eq!(
+        parse_date_time("2024-12-10T24:00"),
+        Err(ParseError::OutOfRange)
+    );
+    assert_eq!(parse_date_time("2024-12-10"), Err(ParseError::Format));
+    assert_eq!(
+        parse_date_time("2024-12-10T11:45:00."),
+        Err(ParseError::Format)
+    );
+    assert_eq!(
+        parse_date_time("2024-12-10T11:45+06:0"),
+        Err(ParseError::Format)
+    );
+    assert_eq!(
+        parse_date_time("2024-12-10T11:45Zulu"),
+        Err(ParseError::Format)
+    );
+    //Multi-byte characters where the digits should be
+    assert_eq!(parse_date_time("2024-12-1é11:45"), Err(ParseError::Format));
+}
+
+#[test]
+fn leap_second_is_kept_in_the_minute() {
+    let timestamp = parse_date_time("2016-12-31T23:59:60Z").unwrap();
+    assert_eq!(timestamp.date_time.second, 59);
+}
+
+#[test]
+fn adding_days_crosses_months_and_leap_days() {
+    let start = parse_date_time("2024-02-27T08:30:00").unwrap().date_time;
+    let later = add_days(&start, 3);
+    assert_eq!((later.year, later.month, later.day), (2024, 3, 1));
+    assert_eq!((later.hour, later.minute), (8, 30));
+    assert_eq!(later.day_of_week, DayOfWeek::Friday);
+    assert_eq!(days_between(&start, &later), 3);
+
+    let earlier = add_days(&parse_date("2025-01-01").unwrap(), -1);
+    assert_eq!((earlier.year, earlier.month, earlier.day), (2024, 12, 31));
+    assert_eq!(
+        days_between(&parse_date("2025-01-01").unwrap(), &earlier),
+        -1
+    );
+}
+
+#[test]
+fn known_iso_weeks() {
+    assert_eq!(iso_week(2021, 1, 3), (2020, 53));
+    assert_eq!(iso_week(2021, 1, 4), (2021, 1));
+    assert_eq!(iso_week(2024, 12, 30), (2025, 1));
+    assert_eq!(iso_week(2025, 1, 14), (2025, 3));
+    assert_eq!(iso_week(2026, 12, 31), (2026, 53));
+}
+
+proptest! {
+    #[test]
+    fn anything_at_all_does_not_panic(text in "\\PC{0,40}") {
+        let _ = parse_date(&text);
+        let _ = parse_date_time(&text);
+    }
+
+    #[test]
+    fn nearly_right_does_not_panic(text in "[0-9]{1,5}-[0-9]{1,3}-[0-9]{1,3}[Tt ][0-9]{1,3}:[0-9]{1,3}(:[0-9]{1,3}(\\.[0-9]{0,12})?)?([Zz]|[+-][0-9]{0,4}|[+-][0-9]{2}:[0-9]{0,3})?") {
+        let _ = parse_date_time(&text);
+    }
+
+    #[test]
+    fn reads_back_what_was_written(
+        (year, month, day) in date(),
+        hour in 0u8..24,
+        minute in 0u8..60,
+        second in 0u8..60,
+        nanosecond in 0u32..1_000_000_000,
+        offset in offset(),
+    ) {
+        let text = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}{}",
+            year, month, day, hour, minute, second, nanosecond, format_offset(offset)
+        );
+        let timestamp = parse_date_time(&text).unwrap();
+        let date_time = &timestamp.date_time;
+        prop_assert_eq!(
+            (date_time.year, date_time.month, date_time.day),
+            (year, month, day)
+        );
+        prop_assert_eq!(
+            (date_time.hour, date_time.minute, date_time.second),
+            (hour, minute, second)
+        );
+        prop_assert_eq!(timestamp.nanosecond, nanosecond);
+        prop_assert_eq!(timestamp.utc_offset_seconds, Some(offset));
+    }
+
+    #[test]
+    fn day_of_week_follows_the_day_before((year, month, day) in date()) {
+        let days = days_from_civil(year as i32, month, day);
+        let (year_before, month_before, day_before) = civil_from_days(days - 1);
+        let before = day_of_week(year_before, month_before, day_before) as u8;
+        prop_assert_eq!(day_of_week(year as i32, month, day) as u8, (before + 1) % 7);
+        let parsed = parse_date(&format!("{:04}-{:02}-{:02}", year, month, day)).unwrap();
+        prop_assert_eq!(parsed.day_of_week, day_of_week(year as i32, month, day));
+    }
+
+    #[test]
+    fn utc_is_the_same_moment(
+        (year, month, day) in date(),
+        hour in 0u8..24,
+        minute in 0u8..60,
+        offset in offset(),
+    ) {
+        let local = parse_date_time(&format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}{}",
+            year, month, day, hour, minute, format_offset(offset)
+        ))
+        .unwrap();
+        let utc = local.utc();
+        let seconds = |date_time: &desk_buddy_ui::date::DateTime| {
+            days_from_civil(date_time.year as i32, date_time.month, date_time.day) * 86_400
+                + date_time.hour as i64 * 3_600
+                + date_time.minute as i64 * 60
+        };
+        prop_assert_eq!(seconds(&utc), seconds(&local.date_time) - offset as i64);
+        prop_assert_eq!(
+            utc.day_of_week,
+            day_of_week(utc.year as i32, utc.month, utc.day)
+        );
+    }
+
+    #[test]
+    fn add_days_and_days_between_agree((year, month, day) in date(), days in -300_000i64..300_000) {
+        let start = parse_date(&format!("{:04}-{:02}-{:02}", year, month, day)).unwrap();
+        let moved = add_days(&start, days);
+        prop_assume!((1..=9999).contains(&moved.year));
+        prop_assert_eq!(days_between(&start, &moved), days);
+        prop_assert_eq!(add_days(&moved, -days), start);
+    }
+
+    #[test]
+    fn a_week_later_is_the_same_day_and_the_next_week((year, month, day) in date()) {
+        prop_assume!(year < 9999);
+        let start = parse_date(&format!("{:04}-{:02}-{:02}", year, month, day)).unwrap();
+        let week_later = add_days(&start, 7);
+        prop_assert_eq!(week_later.day_of_week, start.day_of_week);
+
+        let (week_year, week) = iso_week(year as i32, month, day);
+        let (next_week_year, next_week) =
+            iso_week(week_later.year as i32, week_later.month, week_later.day);
+        prop_assert!(
+            (next_week_year, next_week) == (week_year, week + 1)
+                || (next_week_year == week_year + 1 && next_week == 1 && week >= 52)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Recurring events from made up iCalendar files, the parts of RFC 5545 that are easy to get wrong
+
+use desk_buddy_ui::date::{days_from_civil, MINUTES_PER_DAY};
+use desk_buddy_ui::display::CalendarEvent;
+use desk_buddy_ui::ics::IcsParser;
+
+/// Minutes since 1970 for a time in UTC
+fn minutes(year: i32, month: u8, day: u8, hour: i64, minute: i64) -> i64 {
+    days_from_civil(year, month, day) * MINUTES_PER_DAY + hour * 60 + minute
+}
+
+/// Runs the lines through a parser that thinks it is `now` in UTC
+fn parse(now: i64, ics: &str) -> Vec<CalendarEvent> {
+    let mut parser = IcsParser::new(now, 0);
+    for line in ics.lines() {
+        parser.parse_line(line.trim());
+    }
+    parser.finish().into_iter().collect()
+}
+
+fn starts(events: &[CalendarEvent]) -> Vec<i64> {
+    events.iter().map(|event| event.start).collect()
+}
+
+#[test]
+fn weekly_by_day() {
+    //Monday the 6th, so the first week also has a Wednesday and Friday
+    let events = parse(
+        minutes(2025, 1, 14, 12, 0),
+        "BEGIN:VCALENDAR
+        BEGIN:VEVENT
+        UID:standup
+        SUMMARY:Standup
+        DTSTART:20250106T090000Z
+        DTEND:20250106T091500Z
+        RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR
+        END:VEVENT
+        END:VCALENDAR",
+    );
+    assert_eq!(
+        starts(&events),
+        [
+            minutes(2025, 1, 15, 9, 0),
+            minutes(2025, 1, 17, 9, 0),
+            minutes(2025, 1, 20, 9, 0),
+            minutes(2025, 1, 22, 9, 0),
+            minutes(2025, 1, 24, 9, 0),
+        ]
+    );
+    assert_eq!(events[0].end - events[0].start, 15);
+    assert_eq!(events[0].summary, "Standup");
+}
+
+#[test]
+fn monthly_nth_weekday() {
+    let now = minutes(2025, 1, 20, 0, 0);
+    let second_tuesday = parse(
+        now,
+        "BEGIN:VEVENT
+        UID:review
+        DTSTART:20250114T170000Z
+        RRULE:FREQ=MONTHLY;BYDAY=2TU
+        END:VEVENT",
+    );
+    assert_eq!(
+        starts(&second_tuesday)[..3],
+        [
+            minutes(2025, 2, 11, 17, 0),
+            minutes(2025, 3, 11, 17, 0),
+            minutes(2025, 4, 8, 17, 0),
+        ]
+    );
+
+    let last_friday = parse(
+        now,
+        "BEGIN:VEVENT
+        UID:demo
+        DTSTART:20250131T150000Z
+        RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=3
+        END:VEVENT",
+    );
+    assert_eq!(
+        starts(&last_friday),
+        [
+            minutes(2025, 1, 31, 15, 0),
+            minutes(2025, 2, 28, 15, 0),
+            minutes(2025, 3, 28, 15, 0),
+        ]
+    );
+}
+
+#[test]
+fn until_as_a_date_takes_the_whole_day() {
+    let events = parse(
+        minutes(2025, 1, 14, 0, 0),
+        "BEGIN:VEVENT
+        UID:offsite
+        DTSTART:20250113T090000
+        DURATION:PT1H
+        RRULE:FREQ=DAILY;UNTIL=20250116
+        END:VEVENT",
+    );
+    assert_eq!(
+        starts(&events),
+        [
+            minutes(2025, 1, 14, 9, 0),
+            minutes(2025, 1, 15, 9, 0),
+            minutes(2025, 1, 16, 9, 0),
+        ]
+    );
+}
+
+#[test]
+fn count_is_from_the_start_and_the_rest_skip_ahead() {
+    let now = minutes(2025, 1, 14, 12, 0);
+    //Ten Tuesdays from the 3rd of December, the last four are still to come
+    let counted = parse(
+        now,
+        "BEGIN:VEVENT
+        UID:course
+        DTSTART:20241203T180000Z
+        RRULE:FREQ=WEEKLY;COUNT=10
+        END:VEVENT",
+    );
+    assert_eq!(
+        starts(&counted),
+        [
+            minutes(2025, 1, 14, 18, 0),
+            minutes(2025, 1, 21, 18, 0),
+            minutes(2025, 1, 28, 18, 0),
+            minutes(2025, 2, 4, 18, 0),
+        ]
+    );
+
+    //More days since 1990 than a rule is followed for, so this only shows up if it jumps ahead
+    let since_1990 = parse(
+        now,
+        "BEGIN:VEVENT
+        UID:coffee
+        DTSTART:19900101T150000Z
+        RRULE:FREQ=DAILY
+        END:VEVENT",
+    );
+    assert_eq!(since_1990.len(), 5);
+    assert_eq!(since_1990[0].start, minutes(2025, 1, 14, 15, 0));
+}
+
+#[test]
+fn recurrence_id_replaces_the_occurrence() {
+    let events = parse(
+        minutes(2025, 1, 14, 12, 0),
+        "BEGIN:VEVENT
+        UID:one-on-one
+        SUMMARY:1:1
+        DTSTART:20250107T140000Z
+        RRULE:FREQ=WEEKLY;COUNT=4
+        END:VEVENT
+        BEGIN:VEVENT
+        UID:one-on-one
+        SUMMARY:1:1 moved
+        RECURRENCE-ID:20250121T140000Z
+        DTSTART:20250122T100000Z
+        END:VEVENT",
+    );
+    let summaries: Vec<(&str, i64)> = events
+        .iter()
+        .map(|event| (event.summary.as_str(), event.start))
+        .collect();
+    assert_eq!(
+        summaries,
+        [
+            ("1:1", minutes(2025, 1, 14, 14, 0)),
+            ("1:1 moved", minutes(2025, 1, 22, 10, 0)),
+            ("1:1", minutes(2025, 1, 28, 14, 0)),
+        ]
+    );
+}
+
+#[test]
+fn huge_numbers_do_not_overflow() {
+    let now = minutes(2025, 1, 14, 12, 0);
+    for (duration, rule) in [
+        (
+            "P99999999999999999999W",
+            "FREQ=DAILY;INTERVAL=9223372036854775807",
+        ),
+        ("P9223372036854775807M", "FREQ=YEARLY;INTERVAL=2147483647"),
+        ("PT1H", "FREQ=MONTHLY;BYDAY=127MO"),
+        ("PT1H", "FREQ=MONTHLY;BYDAY=-128FR"),
+        ("PT1H", "FREQ=WEEKLY;INTERVAL=4000000000000000000;BYDAY=MO"),
+    ] {
+        parse(
+            now,
+            &format!(
+                "BEGIN:VEVENT
+                DTSTART:20250101T090000Z
+                DURATION:{}
+                RRULE:{}
+                END:VEVENT",
+                duration, rule
+            ),
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! Formatting into fixed buffers
+
+use desk_buddy_ui::io::easy_format_str;
+
+#[test]
+fn fits_exactly() {
+    let mut buffer = [0u8; 5];
+    assert_eq!(
+        easy_format_str(format_args!("{}d", 1234), &mut buffer),
+        Ok("1234d")
+    );
+}
+
+#[test]
+fn too_small_is_an_error_not_a_panic() {
+    let mut buffer = [0u8; 10];
+    assert!(easy_format_str(format_args!("{}d", u64::MAX / 1440), &mut buffer).is_err());
+}
@@ -0,0 +1,86 @@
+//! Dotted paths into office status responses we do not know the shape of
+
+use desk_buddy_ui::json_path::{find_value, unquote};
+
+const RESPONSE: &str = r#"{
+    "data": {"user": {"name": "Sam \"the clock\" Lee", "clocked_in": true}, "hours": 7.5},
+    "entries": [{"state": "in"}, {"state": "out", "note": "back at 1} or ]"}],
+    "clocked_in": "top level"
+}"#;
+
+#[test]
+fn nested_objects() {
+    assert_eq!(find_value(RESPONSE, "data.user.clocked_in"), Some("true"));
+    assert_eq!(find_value(RESPONSE, "data.hours"), Some("7.5"));
+    assert_eq!(
+        find_value(RESPONSE, "data.user"),
+        Some(r#"{"name": "Sam \"the clock\" Lee", "clocked_in": true}"#)
+    );
+}
+
+#[test]
+fn keys_only_match_at_their_own_level() {
+    //clocked_in inside data.user is not the top level one, and the top level one is not inside data
+    assert_eq!(find_value(RESPONSE, "clocked_in"), Some(r#""top level""#));
+    assert_eq!(find_value(RESPONSE, "data.clocked_in"), None);
+    assert_eq!(find_value(RESPONSE, "name"), None);
+}
+
+#[test]
+fn missing_keys() {
+    assert_eq!(find_value(RESPONSE, "missing"), None);
+    assert_eq!(find_value(RESPONSE, "data.user.missing"), None);
+    //Can not go into a value that is not an object or array
+    assert_eq!(find_value(RESPONSE, "data.hours.more"), None);
+    assert_eq!(find_value("{}", "data"), None);
+    assert_eq!(find_value("", "data"), None);
+}
+
+#[test]
+fn array_indices() {
+    assert_eq!(find_value(RESPONSE, "entries.0.state"), Some(r#""in""#));
+    assert_eq!(find_value(RESPONSE, "entries.1.state"), Some(r#""out""#));
+    assert_eq!(find_value(RESPONSE, "entries.2.state"), None);
+    assert_eq!(find_value(RESPONSE, "entries.first"), None);
+    assert_eq!(find_value("[[1, 2], [3, 4]]", "1.0"), Some("3"));
+    assert_eq!(find_value("[]", "0"), None);
+}
+
+#[test]
+fn escaped_strings() {
+    //The } and ] in the note do not end the entry early
+    assert_eq!(
+        find_value(RESPONSE, "entries.1.note"),
+        Some(r#""back at 1} or ]""#)
+    );
+    assert_eq!(
+        unquote(find_value(RESPONSE, "data.user.name").unwrap()),
+        r#"Sam \"the clock\" Lee"#
+    );
+    assert_eq!(find_value(r#"{"a\\": 1, "b": "\\"}"#, "b"), Some(r#""\\""#));
+    //Keys are matched as written, escapes and all
+    assert_eq!(
+        find_value(r#"{"say \"hi\"": 1}"#, r#"say \"hi\""#),
+        Some("1")
+    );
+}
+
+#[test]
+fn whole_value_with_an_empty_path() {
+    assert_eq!(find_value("  true ", ""), Some("true"));
+    assert_eq!(unquote("true"), "true");
+}
+
+#[test]
+fn cut_off_json_is_none_not_a_panic() {
+    for json in [
+        r#"{"data": {"user": "#,
+        r#"{"data": "open"#,
+        r#"{"data": "\"#,
+        r#"{"data""#,
+        "[1, ",
+    ] {
+        assert_eq!(find_value(json, "data.user"), None);
+        assert_eq!(find_value(json, "1"), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Arrays pulled out of made up responses, handed over a few bytes at a time like a slow connection
+
+mod common;
+
+use common::SlowBody;
+use desk_buddy_ui::json_stream::{JsonArrayStream, StreamError};
+use embassy_futures::block_on;
+
+/// Every element of the array under `key` as text
+fn elements(body: &str, key: &str, chunk_size: usize, buffer_size: usize) -> Vec<String> {
+    let mut element_buffer = vec![0u8; buffer_size];
+    let reader = SlowBody {
+        body: body.as_bytes(),
+        chunk_size,
+    };
+    let mut stream = JsonArrayStream::new(reader, &mut element_buffer);
+    block_on(async {
+        stream.find_array(key).await.unwrap();
+        let mut elements = Vec::new();
+        while let Some(element) = stream.next_element().await.unwrap() {
+            elements.push(String::from_utf8(element.to_vec()).unwrap());
+        }
+        elements
+    })
+}
+
+#[test]
+fn nested_objects_and_arrays() {
+    let body = r#"{"cursor": "abc", "notifications": [
+        {"uri": "at://1", "record": {"tags": ["a", "b"], "reply": {"parent": {}}}},
+        {"uri": "at://2", "labels": [[1, 2], []]}
+    ]}"#;
+    assert_eq!(
+        elements(body, "notifications", 128, 256),
+        [
+            r#"{"uri": "at://1", "record": {"tags": ["a", "b"], "reply": {"parent": {}}}}"#,
+            r#"{"uri": "at://2", "labels": [[1, 2], []]}"#,
+        ]
+    );
+}
+
+#[test]
+fn only_the_top_level_key_counts() {
+    let body = r#"{"feed": {"notifications": ["nested"]}, "notifications": ["top"]}"#;
+    assert_eq!(elements(body, "notifications", 128, 64), [r#""top""#]);
+}
+
+#[test]
+fn escaped_quotes_and_backslashes_in_strings() {
+    //The \" and ] in the text must not end the string or the element
+    let body = r#"{"posts": [{"text": "she said \"hi]\" \\", "n": 1}, {"text": "\\\\"}]}"#;
+    assert_eq!(
+        elements(body, "posts", 128, 64),
+        [
+            r#"{"text": "she said \"hi]\" \\", "n": 1}"#,
+            r#"{"text": "\\\\"}"#,
+        ]
+    );
+    //A key with an escaped quote is still just a string before the array
+    let body = r#"{"a\"posts": 1, "posts": [2]}"#;
+    assert_eq!(elements(body, "posts", 128, 64), ["2"]);
+}
+
+#[test]
+fn elements_bigger_than_the_buffer_are_skipped() {
+    let body = r#"{"items": [{"a": 1}, {"long": "this one does not fit in 16 bytes"}, {"b": 2}]}"#;
+    assert_eq!(
+        elements(body, "items", 128, 16),
+        [r#"{"a": 1}"#, r#"{"b": 2}"#]
+    );
+}
+
+#[test]
+fn scalar_elements() {
+    let body = r#"{"values": [1, -2.5e3,true , null,"text",false]}"#;
+    assert_eq!(
+        elements(body, "values", 128, 16),
+        ["1", "-2.5e3", "true", "null", r#""text""#, "false"]
+    );
+}
+
+#[test]
+fn elements_split_across_reads() {
+    let body = r#"{"cursor": "a long cursor so the array starts late", "items": [{"text": "one \"two\""}, 12345, {"deep": [[["x"]]]}]}"#;
+    let whole = elements(body, "items", 128, 64);
+    assert_eq!(whole.len(), 3);
+    //Every split point lands somewhere in the middle of a key, string, escape or number for one of these
+    for chunk_size in 1..body.len() {
+        assert_eq!(elements(body, "items", chunk_size, 64), whole);
+    }
+}
+
+#[test]
+fn missing_array_and_cut_off_body() {
+    let mut element_buffer = [0u8; 32];
+    block_on(async {
+        let reader = SlowBody {
+            body: br#"{"other": [1], "items": 5}"#,
+            chunk_size: 128,
+        };
+        let mut stream = JsonArrayStream::new(reader, &mut element_buffer);
+        assert_eq!(
+            stream.find_array("items").await,
+            Err(StreamError::ArrayNotFound)
+        );
+    });
+    block_on(async {
+        let reader = SlowBody {
+            body: br#"{"items": [{"a": 1}, {"b""#,
+            chunk_size: 128,
+        };
+        let mut stream = JsonArrayStream::new(reader, &mut element_buffer);
+        stream.find_array("items").await.unwrap();
+        assert!(stream.next_element().await.unwrap().is_some());
+        assert_eq!(stream.next_element().await, Err(StreamError::UnexpectedEnd));
+    });
+}
+
+#[test]
+fn stopping_early_and_draining_the_rest() {
+    let body = br#"{"items": [{"a": 1}, {"b": 2}, {"c": 3}], "cursor": "next"}"#;
+    let mut reader = SlowBody {
+        body,
+        chunk_size: 8,
+    };
+    let mut element_buffer = [0u8; 32];
+    block_on(async {
+        let mut stream = JsonArrayStream::new(&mut reader, &mut element_buffer);
+        stream.find_array("items").await.unwrap();
+        assert_eq!(
+            stream.next_element().await.unwrap(),
+            Some(&br#"{"a": 1}"#[..])
+        );
+        stream.drain().await;
+    });
+    //Nothing is left for the next request on the connection to trip over
+    assert!(reader.body.is_empty());
+}
@@ -0,0 +1,86 @@
+//! Calendar lines read a few bytes at a time like a slow connection
+
+mod common;
+
+use common::SlowBody;
+use desk_buddy_ui::line_stream::LineStream;
+use embassy_futures::block_on;
+
+/// Every unfolded line of the body
+fn lines(body: &str, chunk_size: usize, buffer_size: usize) -> Vec<String> {
+    let mut line_buffer = vec![0u8; buffer_size];
+    let reader = SlowBody {
+        body: body.as_bytes(),
+        chunk_size,
+    };
+    let mut stream = LineStream::new(reader, &mut line_buffer);
+    block_on(async {
+        let mut lines = Vec::new();
+        while let Some(line) = stream.next_line().await.unwrap() {
+            lines.push(line.to_string());
+        }
+        lines
+    })
+}
+
+#[test]
+fn crlf_and_lf_endings() {
+    let expected = ["BEGIN:VEVENT", "SUMMARY:Standup", "END:VEVENT"];
+    assert_eq!(
+        lines("BEGIN:VEVENT\r\nSUMMARY:Standup\r\nEND:VEVENT\r\n", 128, 64),
+        expected
+    );
+    assert_eq!(
+        lines("BEGIN:VEVENT\nSUMMARY:Standup\nEND:VEVENT\n", 128, 64),
+        expected
+    );
+    //No line ending on the last line
+    assert_eq!(
+        lines("BEGIN:VEVENT\r\nSUMMARY:Standup\r\nEND:VEVENT", 128, 64),
+        expected
+    );
+}
+
+#[test]
+fn folded_lines_with_a_space_or_tab() {
+    assert_eq!(
+        lines(
+            "SUMMARY:Quarterly plan\r\n ning with the\r\n\t whole team\r\nUID:1\r\n",
+            128,
+            64
+        ),
+        ["SUMMARY:Quarterly planning with the whole team", "UID:1"]
+    );
+    assert_eq!(
+        lines("DESCRIPTION:one\n two\nUID:2\n", 128, 64),
+        ["DESCRIPTION:onetwo", "UID:2"]
+    );
+}
+
+#[test]
+fn folds_on_a_read_boundary() {
+    let body = "BEGIN:VEVENT\r\nSUMMARY:Quarterly plan\r\n ning\r\nUID:1\r\nEND:VEVENT\r\n";
+    let whole = lines(body, 128, 64);
+    assert_eq!(
+        whole,
+        [
+            "BEGIN:VEVENT",
+            "SUMMARY:Quarterly planning",
+            "UID:1",
+            "END:VEVENT"
+        ]
+    );
+    //Lands between the \r and \n, and between the \n and the space that folds it, for one of these
+    for chunk_size in 1..body.len() {
+        assert_eq!(lines(body, chunk_size, 64), whole);
+    }
+}
+
+#[test]
+fn long_lines_are_cut_on_a_whole_character() {
+    //é is two bytes, the buffer ends half way through it
+    assert_eq!(
+        lines("SUMMARY:Café\r\nUID:1\r\n", 128, 12),
+        ["SUMMARY:Caf", "UID:1"]
+    );
+}
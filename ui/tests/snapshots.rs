@@ -0,0 +1,352 @@
+//! Draws each widget with made up data into a frame the size of the panel and compares it against the
+//! PNGs in tests/snapshots. After changing how something looks, look over the new images and update them with
+//!
+//!     UPDATE_SNAPSHOTS=1 cargo test
+//!
+//! A missing snapshot fails like one that does not match, so a new test needs a run with UPDATE_SNAPSHOTS=1 and its
+//! PNG committed. When one does not match, what was drawn is saved next to it as <name>.new.png to compare
+
+use desk_buddy_ui::annual::AnnualEvents;
+use desk_buddy_ui::date::{DateTime, DayOfWeek};
+use desk_buddy_ui::display::{
+    draw_blue_sky_notification, draw_current_outside_weather, draw_forecast, draw_scd_data,
+    draw_time, draw_weather_forecast_box, BlueSkyNotificationData, ForecastConfig, ForecastLayout,
+    InsideSensorData, NotificationEntry, NotificationReason,
+};
+use desk_buddy_ui::layout;
+use desk_buddy_ui::weather::{Current, CurrentUnits, ForecastResponse};
+use embedded_graphics::prelude::*;
+use epd_waveshare::{color::Color, epd4in2_v2::Display4in2};
+use heapless::String;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+const WIDTH: u32 = 400;
+const HEIGHT: u32 = 300;
+
+/// Tuesday evening, after sunset in January
+fn now() -> DateTime {
+    DateTime {
+        year: 2025,
+        month: 1,
+        day: 14,
+        day_of_week: DayOfWeek::Tuesday,
+        hour: 18,
+        minute: 42,
+        second: 0,
+    }
+}
+
+fn blank_frame() -> Display4in2 {
+    let mut display = Display4in2::default();
+    display.clear(Color::White).ok();
+    display
+}
+
+fn string<const N: usize>(text: &str) -> String<N> {
+    String::try_from(text).unwrap()
+}
+
+#[test]
+fn time() {
+    let mut display = blank_frame();
+    draw_time(now(), &mut display);
+    assert_snapshot("time", &display);
+}
+
+#[test]
+fn scd_data_celsius() {
+    let mut display = blank_frame();
+    let sensor_data = InsideSensorData {
+        co2: 812,
+        temperature: 21.4,
+        humidity: 38.5,
+    };
+    draw_scd_data(layout::SENSORS, sensor_data, false, &mut display);
+    assert_snapshot("scd_data_celsius", &display);
+}
+
+#[test]
+fn scd_data_fahrenheit() {
+    let mut display = blank_frame();
+    let sensor_data = InsideSensorData {
+        co2: 1_650,
+        temperature: 24.0,
+        humidity: 55.0,
+    };
+    draw_scd_data(layout::SENSORS, sensor_data, true, &mut display);
+    assert_snapshot("scd_data_fahrenheit", &display);
+}
+
+fn current_weather() -> (Current, CurrentUnits) {
+    let current = Current {
+        time: string("2025-01-14T18:30"),
+        interval: 900,
+        temperature_2m: 24.3,
+        relative_humidity_2m: 61,
+        apparent_temperature: 14.8,
+        wind_speed_10m: 9.4,
+        wind_direction_10m: 315.0,
+        wind_gusts_10m: 18.1,
+        uv_index: 0.0,
+        weather_code: 3,
+    };
+    let units = CurrentUnits {
+        time: string("iso8601"),
+        interval: string("seconds"),
+        temperature_2m: string("°F"),
+        relative_humidity_2m: string("%"),
+        apparent_temperature: string("°F"),
+        wind_speed_10m: string("mp/h"),
+        wind_direction_10m: string("°"),
+        wind_gusts_10m: string("mp/h"),
+        uv_index: string(""),
+    };
+    (current, units)
+}
+
+#[test]
+fn current_outside_weather_day() {
+    let mut display = blank_frame();
+    let (current, units) = current_weather();
+    draw_current_outside_weather(layout::CURRENT_WEATHER, current, units, true, &mut display);
+    assert_snapshot("current_outside_weather_day", &display);
+}
+
+#[test]
+fn current_outside_weather_night() {
+    let mut display = blank_frame();
+    let (current, units) = current_weather();
+    draw_current_outside_weather(layout::CURRENT_WEATHER, current, units, false, &mut display);
+    assert_snapshot("current_outside_weather_night", &display);
+}
+
+#[test]
+fn weather_forecast_boxes() {
+    let mut display = blank_frame();
+    //Two days after today so the birthday cake shows on the third box
+    let annual_events = AnnualEvents::parse("01-16 Sam", "", 3);
+    let days = [
+        (
+            "2025-01-14",
+            31.2,
+            18.5,
+            3,
+            "2025-01-14T07:50",
+            "2025-01-14T17:14",
+        ),
+        (
+            "2025-01-15",
+            28.4,
+            16.2,
+            71,
+            "2025-01-15T07:50",
+            "2025-01-15T17:15",
+        ),
+        (
+            "2025-01-16",
+            25.0,
+            12.9,
+            73,
+            "2025-01-16T07:49",
+            "2025-01-16T17:16",
+        ),
+    ];
+    let box_width = layout::FORECAST_SIZE.width / days.len() as u32;
+    let mut starting_point = layout::FORECAST;
+    for (date, max, min, code, sunrise, sunset) in days {
+        draw_weather_forecast_box(
+            starting_point,
+            box_width,
+            date,
+            "°F",
+            max,
+            min,
+            code,
+            string(sunrise),
+            string(sunset),
+            &annual_events,
+            Some(now()),
+            &mut display,
+        );
+        starting_point.x += box_width as i32;
+    }
+    assert_snapshot("weather_forecast_boxes", &display);
+}
+
+#[test]
+fn forecast_with_a_short_field_only_draws_complete_days() {
+    let mut forecast = ForecastResponse::default();
+    let daily = &mut forecast.daily;
+    for day in ["2025-01-14", "2025-01-15", "2025-01-16"] {
+        daily.time.push(string(day)).unwrap();
+        daily.temperature_2m_max.push(30.0).unwrap();
+        daily.temperature_2m_min.push(15.0).unwrap();
+        daily.sunrise.push(string("2025-01-14T07:50")).unwrap();
+        daily.sunset.push(string("2025-01-14T17:14")).unwrap();
+        daily.precipitation_probability_max.push(10).unwrap();
+    }
+    //One day short
+    daily.weather_code.extend_from_slice(&[3, 71]).unwrap();
+    assert_eq!(daily.complete_days(), 2);
+
+    let annual_events = AnnualEvents::parse("", "", 3);
+    for layout in [ForecastLayout::Boxes, ForecastLayout::Rows] {
+        let mut display = blank_frame();
+        draw_forecast(
+            layout::FORECAST,
+            layout::FORECAST_SIZE,
+            &forecast,
+            ForecastConfig { days: 3, layout },
+            &annual_events,
+            Some(now()),
+            &mut display,
+        );
+    }
+}
+
+fn notifications(unread_notifications: i32) -> BlueSkyNotificationData {
+    let entries = [
+        ("Alex", NotificationReason::Like, false, 4.0),
+        (
+            "jordan.bsky.social",
+            NotificationReason::Follow,
+            false,
+            52.0,
+        ),
+        ("Riley", NotificationReason::Reply, true, 190.0),
+    ];
+    let now = julian_now();
+    BlueSkyNotificationData {
+        unread_notifications,
+        notifications: entries
+            .into_iter()
+            .map(|(author, reason, is_read, minutes_ago)| NotificationEntry {
+                author: string(author),
+                reason,
+                is_read,
+                indexed_at: Some(now - minutes_ago / 1440.0),
+            })
+            .collect(),
+    }
+}
+
+/// 2025-01-15 00:42 UTC, which is now() at UTC-6
+fn julian_now() -> f64 {
+    2_460_690.5 + 42.0 / 1440.0
+}
+
+#[test]
+fn blue_sky_notification_first_page() {
+    let mut display = blank_frame();
+    draw_blue_sky_notification(
+        layout::NOTIFICATIONS,
+        &notifications(2),
+        0,
+        Some(julian_now()),
+        &mut display,
+    );
+    assert_snapshot("blue_sky_notification_first_page", &display);
+}
+
+#[test]
+fn blue_sky_notification_second_page() {
+    let mut display = blank_frame();
+    draw_blue_sky_notification(
+        layout::NOTIFICATIONS,
+        &notifications(0),
+        1,
+        Some(julian_now()),
+        &mut display,
+    );
+    assert_snapshot("blue_sky_notification_second_page", &display);
+}
+
+#[test]
+fn blue_sky_notification_without_time() {
+    let mut display = blank_frame();
+    draw_blue_sky_notification(
+        layout::NOTIFICATIONS,
+        &notifications(2),
+        0,
+        None,
+        &mut display,
+    );
+    assert_snapshot("blue_sky_notification_without_time", &display);
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(name)
+}
+
+/// The frame is 1 bit a pixel a row at a time with 1 as white, the same as a 1 bit grayscale PNG
+fn write_png(path: &PathBuf, frame: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let file = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(frame)
+        .unwrap();
+}
+
+fn read_png(path: &PathBuf) -> Vec<u8> {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut frame = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut frame).unwrap();
+    assert_eq!(
+        (info.width, info.height, info.color_type, info.bit_depth),
+        (WIDTH, HEIGHT, png::ColorType::Grayscale, png::BitDepth::One),
+        "{} should be a 400x300 1 bit grayscale PNG",
+        path.display()
+    );
+    frame.truncate(info.buffer_size());
+    frame
+}
+
+fn assert_snapshot(name: &str, display: &Display4in2) {
+    let frame = display.buffer();
+    let golden_path = snapshot_path(&format!("{}.png", name));
+    let new_path = snapshot_path(&format!("{}.new.png", name));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        write_png(&golden_path, frame);
+        let _ = fs::remove_file(&new_path);
+        eprintln!("Wrote {}", golden_path.display());
+        return;
+    }
+    if !golden_path.exists() {
+        write_png(&new_path, frame);
+        panic!(
+            "There is no {} yet. What was drawn is in {}, run with UPDATE_SNAPSHOTS=1 to keep it",
+            golden_path.display(),
+            new_path.display()
+        );
+    }
+
+    let golden = read_png(&golden_path);
+    let different_pixels: u32 = golden
+        .iter()
+        .zip(frame)
+        .map(|(golden, drawn)| (golden ^ drawn).count_ones())
+        .sum();
+    if different_pixels == 0 {
+        let _ = fs::remove_file(&new_path);
+        return;
+    }
+    write_png(&new_path, frame);
+    panic!(
+        "{} pixels are different from {}. What was drawn is in {}, run with UPDATE_SNAPSHOTS=1 if that is right",
+        different_pixels,
+        golden_path.display(),
+        new_path.display()
+    );
+}